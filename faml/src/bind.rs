@@ -0,0 +1,74 @@
+//! Runtime support for `#[derive(Faml)]`, which loads struct fields from paths of a `FamlExpr`.
+//!
+//! Fields are read with `FamlExpr::deserialize` so only the bound paths are evaluated. Fields with
+//! `#[faml(unit = "...")]` hold durations or distances as plain numbers in that unit.
+
+use crate::de::{ExprDeserializer, ValueDeserializer};
+use crate::expr::eval::Op1Evaluator;
+use crate::{FamlExpr, FamlValue};
+use anyhow::anyhow;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+pub type Result<T> = anyhow::Result<T>;
+
+/// Implemented by `#[derive(Faml)]`, together with a `set_<field>` method per field.
+pub trait FamlBind: Sized {
+    fn from_expr(expr: &FamlExpr) -> anyhow::Result<Self>;
+}
+
+pub fn missing_field(path: &str) -> anyhow::Error {
+    anyhow!("{path}: missing field")
+}
+
+/// Read the value at `path`, `None` if the path doesn't exist.
+pub fn read<T: DeserializeOwned>(
+    expr: &FamlExpr,
+    path: &str,
+    unit: Option<&str>,
+) -> anyhow::Result<Option<T>> {
+    let Some(expr) = expr.get(path) else {
+        return Ok(None);
+    };
+    let Some(unit) = unit else {
+        return Ok(Some(T::deserialize(ExprDeserializer::new(expr, path))?));
+    };
+    let value = expr.evaluate()?;
+    let one = Op1Evaluator::eval_suffix(FamlValue::Float64(1.0), unit)?;
+    let n = match (&value, one) {
+        (FamlValue::Duration(dur), FamlValue::Duration(one)) => {
+            dur.as_nanos() as f64 / one.as_nanos() as f64
+        }
+        (FamlValue::Distance(dis), FamlValue::Distance(one)) => dis.to_meters() / one.to_meters(),
+        _ => Err(anyhow!(
+            "{path}: value[{}] cannot be read in unit[{unit}]",
+            value.as_print_str()
+        ))?,
+    };
+    let n = match n.fract() == 0.0 && n.abs() < i64::MAX as f64 {
+        true => FamlValue::Int64(n as i64),
+        false => FamlValue::Float64(n),
+    };
+    Ok(Some(T::deserialize(ValueDeserializer::new(n, path))?))
+}
+
+/// Write `value` to `path` with `FamlExpr::set_value`, formulas depending on it see the new value.
+pub fn write<T: Serialize + ?Sized>(
+    expr: &mut FamlExpr,
+    path: &str,
+    value: &T,
+    unit: Option<&str>,
+) -> anyhow::Result<()> {
+    let mut value = crate::to_value(value)?;
+    if let Some(unit) = unit {
+        if value.as_float().is_none() {
+            return Err(anyhow!(
+                "{path}: value[{}] is not a number of unit[{unit}]",
+                value.as_print_str()
+            ));
+        }
+        value = Op1Evaluator::eval_suffix(value, unit)?;
+    }
+    expr.get_mut(path).set_value(value);
+    Ok(())
+}
@@ -0,0 +1,593 @@
+//! `serde::Deserializer` implementations for `FamlValue` and `&FamlExpr`.
+//!
+//! Errors carry the key path of the failed value, like `server.port: expected u16, got string`.
+//! `&FamlExpr` only evaluates the fields the target type asks for, and durations are read as
+//! `std::time::Duration` without going through strings.
+
+use crate::{FamlExpr, FamlExprImpl, FamlValue};
+use serde::de::{
+    self, DeserializeSeed, Deserializer, EnumAccess, Expected, IntoDeserializer, MapAccess,
+    SeqAccess, Unexpected, VariantAccess, Visitor,
+};
+use serde::forward_to_deserialize_any;
+use std::fmt;
+use std::vec::IntoIter;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error {
+    path: String,
+    msg: String,
+}
+
+impl Error {
+    fn at(mut self, path: &str) -> Self {
+        if self.path.is_empty() {
+            self.path = path.to_string();
+        }
+        self
+    }
+
+    fn type_name(unexp: &Unexpected) -> String {
+        match unexp {
+            Unexpected::Bool(_) => "bool".to_string(),
+            Unexpected::Unsigned(_) | Unexpected::Signed(_) => "int".to_string(),
+            Unexpected::Float(_) => "float".to_string(),
+            Unexpected::Char(_) | Unexpected::Str(_) => "string".to_string(),
+            Unexpected::Unit | Unexpected::Option => "null".to_string(),
+            Unexpected::Seq => "array".to_string(),
+            Unexpected::Map => "map".to_string(),
+            unexp => unexp.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.path.is_empty() {
+            true => write!(f, "{}", self.msg),
+            false => write!(f, "{}: {}", self.path, self.msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error {
+            path: String::new(),
+            msg: msg.to_string(),
+        }
+    }
+
+    fn invalid_type(unexp: Unexpected, exp: &dyn Expected) -> Self {
+        Self::custom(format!("expected {exp}, got {}", Self::type_name(&unexp)))
+    }
+
+    fn invalid_value(unexp: Unexpected, exp: &dyn Expected) -> Self {
+        Self::custom(format!("expected {exp}, got {unexp}"))
+    }
+}
+
+fn join_key(path: &str, key: &str) -> String {
+    match path.is_empty() {
+        true => key.to_string(),
+        false => format!("{path}.{key}"),
+    }
+}
+
+/// Deserializer over an evaluated value.
+pub struct ValueDeserializer {
+    value: FamlValue,
+    path: String,
+}
+
+impl ValueDeserializer {
+    pub fn new(value: FamlValue, path: &str) -> Self {
+        ValueDeserializer {
+            value,
+            path: path.to_string(),
+        }
+    }
+}
+
+impl<'de> Deserializer<'de> for ValueDeserializer {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let path = self.path;
+        match self.value {
+            FamlValue::None => visitor.visit_unit(),
+            FamlValue::Bool(b) => visitor.visit_bool(b),
+            FamlValue::Int64(n) => visitor.visit_i64(n),
+            FamlValue::Float64(f) => visitor.visit_f64(f),
+            FamlValue::String(s) => visitor.visit_string(s),
+            FamlValue::Array(items) => {
+                let items = items
+                    .into_iter()
+                    .enumerate()
+                    .map(|(idx, item)| ValueDeserializer::new(item, &format!("{path}[{idx}]")))
+                    .collect::<Vec<_>>();
+                visitor.visit_seq(SeqDeserializer(items.into_iter()))
+            }
+            FamlValue::Map(map) => {
+                let items = map
+                    .into_iter()
+                    .map(|(key, value)| {
+                        let value = ValueDeserializer::new(value, &join_key(&path, &key));
+                        (key, value)
+                    })
+                    .collect::<Vec<_>>();
+                visitor.visit_map(MapDeserializer::new(items))
+            }
+            // self-describing targets get the same strings as `to_json`, like "2.5 mins"
+            value @ (FamlValue::Duration(_) | FamlValue::Distance(_)) => {
+                visitor.visit_string(value.as_str())
+            }
+            FamlValue::Json(root) => root.deserialize_any(visitor).map_err(de::Error::custom),
+            FamlValue::Yaml(root) => root.deserialize_any(visitor).map_err(de::Error::custom),
+        }
+        .map_err(|err: Error| err.at(&path))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            FamlValue::None => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            FamlValue::Distance(dist) => visitor
+                .visit_f64(dist.to_meters())
+                .map_err(|err: Error| err.at(&self.path)),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_f64(visitor)
+    }
+
+    /// `std::time::Duration` is read from its `secs` and `nanos` fields.
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self.value {
+            FamlValue::Duration(dur) if name == "Duration" && fields == ["secs", "nanos"] => {
+                let path = self.path;
+                let field = |value: i64| ValueDeserializer::new(FamlValue::Int64(value), &path);
+                visitor
+                    .visit_map(MapDeserializer::new(vec![
+                        ("secs".to_string(), field(dur.as_secs() as i64)),
+                        ("nanos".to_string(), field(dur.subsec_nanos() as i64)),
+                    ]))
+                    .map_err(|err: Error| err.at(&path))
+            }
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let path = self.path;
+        match self.value {
+            FamlValue::String(variant) => visitor.visit_enum(EnumDeserializer {
+                variant,
+                value: None,
+            }),
+            FamlValue::Map(map) if map.len() == 1 => {
+                let (variant, value) = map.into_iter().next().unwrap();
+                let value = ValueDeserializer::new(value, &join_key(&path, &variant));
+                visitor.visit_enum(EnumDeserializer {
+                    variant,
+                    value: Some(value),
+                })
+            }
+            value => Err(de::Error::invalid_type(
+                ValueDeserializer::unexpected(&value),
+                &"string or map with a single key",
+            )),
+        }
+        .map_err(|err: Error| err.at(&path))
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 char str string bytes byte_buf
+        unit unit_struct seq tuple tuple_struct map identifier
+    }
+}
+
+impl ValueDeserializer {
+    fn unexpected(value: &FamlValue) -> Unexpected<'_> {
+        match value {
+            FamlValue::None => Unexpected::Unit,
+            FamlValue::Bool(b) => Unexpected::Bool(*b),
+            FamlValue::Int64(n) => Unexpected::Signed(*n),
+            FamlValue::Float64(f) => Unexpected::Float(*f),
+            FamlValue::String(s) => Unexpected::Str(s),
+            FamlValue::Array(_) => Unexpected::Seq,
+            FamlValue::Map(_) => Unexpected::Map,
+            FamlValue::Duration(_) => Unexpected::Other("duration"),
+            FamlValue::Distance(_) => Unexpected::Other("distance"),
+            FamlValue::Json(_) => Unexpected::Other("json"),
+            FamlValue::Yaml(_) => Unexpected::Other("yaml"),
+        }
+    }
+}
+
+/// Deserializer over an expression, maps and arrays are walked without evaluating the fields
+/// the target type doesn't ask for.
+pub struct ExprDeserializer<'a> {
+    expr: &'a FamlExpr,
+    path: String,
+}
+
+impl<'a> ExprDeserializer<'a> {
+    pub fn new(expr: &'a FamlExpr, path: &str) -> Self {
+        ExprDeserializer {
+            expr,
+            path: path.to_string(),
+        }
+    }
+
+    fn evaluate(self) -> Result<ValueDeserializer, Error> {
+        let value = self
+            .expr
+            .evaluate()
+            .map_err(|err| <Error as de::Error>::custom(err).at(&self.path))?;
+        Ok(ValueDeserializer::new(value, &self.path))
+    }
+}
+
+macro_rules! forward_to_value {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                match &self.expr.base().expr {
+                    FamlExprImpl::Map(_) | FamlExprImpl::Array(_) => self.deserialize_any(visitor),
+                    _ => self.evaluate()?.$method(visitor),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for ExprDeserializer<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let path = self.path.clone();
+        match &self.expr.base().expr {
+            FamlExprImpl::Map(map) => {
+                let items = map
+                    .iter()
+                    .map(|(key, value)| {
+                        let value = ExprDeserializer::new(value, &join_key(&path, key));
+                        (key.clone(), value)
+                    })
+                    .collect::<Vec<_>>();
+                visitor
+                    .visit_map(MapDeserializer::new(items))
+                    .map_err(|err: Error| err.at(&path))
+            }
+            FamlExprImpl::Array(items) => {
+                let items = items
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, item)| ExprDeserializer::new(item, &format!("{path}[{idx}]")))
+                    .collect::<Vec<_>>();
+                visitor
+                    .visit_seq(SeqDeserializer(items.into_iter()))
+                    .map_err(|err: Error| err.at(&path))
+            }
+            _ => self.evaluate()?.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match &self.expr.base().expr {
+            FamlExprImpl::Map(_) | FamlExprImpl::Array(_) => visitor.visit_some(self),
+            _ => self.evaluate()?.deserialize_option(visitor),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.evaluate()?.deserialize_enum(name, variants, visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match &self.expr.base().expr {
+            FamlExprImpl::Map(_) => self.deserialize_any(visitor),
+            _ => self.evaluate()?.deserialize_struct(name, fields, visitor),
+        }
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.evaluate()?.deserialize_unit_struct(name, visitor)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Error> {
+        match &self.expr.base().expr {
+            FamlExprImpl::Array(_) => self.deserialize_any(visitor),
+            _ => self.evaluate()?.deserialize_tuple(len, visitor),
+        }
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match &self.expr.base().expr {
+            FamlExprImpl::Array(_) => self.deserialize_any(visitor),
+            _ => self
+                .evaluate()?
+                .deserialize_tuple_struct(name, len, visitor),
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    forward_to_value! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_i128 deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+        deserialize_u128 deserialize_f32 deserialize_f64 deserialize_char deserialize_str
+        deserialize_string deserialize_bytes deserialize_byte_buf deserialize_unit
+        deserialize_seq deserialize_map deserialize_identifier
+    }
+}
+
+struct SeqDeserializer<D>(IntoIter<D>);
+
+impl<'de, D: Deserializer<'de, Error = Error>> SeqAccess<'de> for SeqDeserializer<D> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        match self.0.next() {
+            Some(item) => seed.deserialize(item).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.0.len())
+    }
+}
+
+struct MapDeserializer<D> {
+    items: IntoIter<(String, D)>,
+    value: Option<D>,
+}
+
+impl<D> MapDeserializer<D> {
+    fn new(items: Vec<(String, D)>) -> Self {
+        MapDeserializer {
+            items: items.into_iter(),
+            value: None,
+        }
+    }
+}
+
+impl<'de, D: Deserializer<'de, Error = Error>> MapAccess<'de> for MapDeserializer<D> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        match self.items.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                seed.deserialize(key.into_deserializer()).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        match self.value.take() {
+            Some(value) => seed.deserialize(value),
+            None => Err(de::Error::custom("value is missing")),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.items.len())
+    }
+}
+
+struct EnumDeserializer {
+    variant: String,
+    value: Option<ValueDeserializer>,
+}
+
+impl<'de> EnumAccess<'de> for EnumDeserializer {
+    type Error = Error;
+    type Variant = VariantDeserializer;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), Error> {
+        let variant = seed.deserialize(self.variant.into_deserializer())?;
+        Ok((variant, VariantDeserializer(self.value)))
+    }
+}
+
+struct VariantDeserializer(Option<ValueDeserializer>);
+
+impl<'de> VariantAccess<'de> for VariantDeserializer {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        match self.0 {
+            None => Ok(()),
+            Some(value) => de::Deserialize::deserialize(value),
+        }
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        match self.0 {
+            Some(value) => seed.deserialize(value),
+            None => Err(de::Error::invalid_type(
+                Unexpected::UnitVariant,
+                &"newtype variant",
+            )),
+        }
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            Some(value) => value.deserialize_seq(visitor),
+            None => Err(de::Error::invalid_type(
+                Unexpected::UnitVariant,
+                &"tuple variant",
+            )),
+        }
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self.0 {
+            Some(value) => value.deserialize_map(visitor),
+            None => Err(de::Error::invalid_type(
+                Unexpected::UnitVariant,
+                &"struct variant",
+            )),
+        }
+    }
+}
+
+macro_rules! forward_to_inner {
+    ($inner:expr; $($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                ($inner)(self).$method(visitor)
+            }
+        )*
+    };
+}
+
+macro_rules! impl_deserializer {
+    ($ty:ty, $inner:expr) => {
+        impl<'de> Deserializer<'de> for $ty {
+            type Error = Error;
+
+            fn deserialize_newtype_struct<V: Visitor<'de>>(
+                self,
+                name: &'static str,
+                visitor: V,
+            ) -> Result<V::Value, Error> {
+                ($inner)(self).deserialize_newtype_struct(name, visitor)
+            }
+
+            fn deserialize_enum<V: Visitor<'de>>(
+                self,
+                name: &'static str,
+                variants: &'static [&'static str],
+                visitor: V,
+            ) -> Result<V::Value, Error> {
+                ($inner)(self).deserialize_enum(name, variants, visitor)
+            }
+
+            fn deserialize_struct<V: Visitor<'de>>(
+                self,
+                name: &'static str,
+                fields: &'static [&'static str],
+                visitor: V,
+            ) -> Result<V::Value, Error> {
+                ($inner)(self).deserialize_struct(name, fields, visitor)
+            }
+
+            fn deserialize_unit_struct<V: Visitor<'de>>(
+                self,
+                name: &'static str,
+                visitor: V,
+            ) -> Result<V::Value, Error> {
+                ($inner)(self).deserialize_unit_struct(name, visitor)
+            }
+
+            fn deserialize_tuple<V: Visitor<'de>>(
+                self,
+                len: usize,
+                visitor: V,
+            ) -> Result<V::Value, Error> {
+                ($inner)(self).deserialize_tuple(len, visitor)
+            }
+
+            fn deserialize_tuple_struct<V: Visitor<'de>>(
+                self,
+                name: &'static str,
+                len: usize,
+                visitor: V,
+            ) -> Result<V::Value, Error> {
+                ($inner)(self).deserialize_tuple_struct(name, len, visitor)
+            }
+
+            forward_to_inner! {
+                $inner;
+                deserialize_any deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32
+                deserialize_i64 deserialize_i128 deserialize_u8 deserialize_u16 deserialize_u32
+                deserialize_u64 deserialize_u128 deserialize_f32 deserialize_f64 deserialize_char
+                deserialize_str deserialize_string deserialize_bytes deserialize_byte_buf
+                deserialize_option deserialize_unit deserialize_seq deserialize_map
+                deserialize_identifier deserialize_ignored_any
+            }
+        }
+    };
+}
+
+impl_deserializer!(FamlValue, |value| ValueDeserializer::new(value, ""));
+impl_deserializer!(&FamlExpr, |expr| ExprDeserializer::new(expr, ""));
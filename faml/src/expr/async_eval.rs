@@ -0,0 +1,159 @@
+use super::faml_expr::{FamlExpr, FamlExprImpl};
+use super::faml_value::FamlValue;
+use crate::native::{FasAsyncCallable, FasCallable, FasFuture};
+use anyhow::anyhow;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::task::Poll;
+
+/// Call node and the printed arguments, a call inside a comprehension runs once per arguments.
+type CallKey = (usize, String);
+
+thread_local! {
+    // only set during the synchronous passes of `evaluate_async`, which never await
+    static ASYNC_CALLS: RefCell<Option<AsyncCalls>> = const { RefCell::new(None) };
+}
+
+#[derive(Default)]
+pub(crate) struct AsyncCalls {
+    results: HashMap<CallKey, Result<FamlValue, String>>,
+    pending: Vec<(CallKey, FasFuture)>,
+    /// Results of sync calls, reused by the following rounds so each call runs once.
+    sync_results: HashMap<CallKey, Result<FamlValue, String>>,
+}
+
+impl AsyncCalls {
+    /// Call a sync native function, during `evaluate_async` the result of the first round is
+    /// reused instead of calling it again.
+    pub fn call_sync(
+        id: usize,
+        name: &str,
+        func: &dyn FasCallable,
+        args: Vec<FamlValue>,
+    ) -> anyhow::Result<FamlValue> {
+        let key = (id, FamlValue::Array(args.clone()).as_print_str());
+        let cached = ASYNC_CALLS.with(|calls| {
+            let calls = calls.borrow();
+            calls
+                .as_ref()
+                .map(|calls| calls.sync_results.get(&key).cloned())
+        });
+        let ret = match cached {
+            Some(Some(ret)) => ret.map_err(|err| anyhow!(err)),
+            // the cell is not borrowed while calling, the function may evaluate faml again
+            Some(None) => {
+                let ret = func.call(args);
+                let cached = ret.as_ref().map_err(|err| err.to_string()).cloned();
+                ASYNC_CALLS.with(|calls| {
+                    if let Some(calls) = calls.borrow_mut().as_mut() {
+                        calls.sync_results.insert(key, cached);
+                    }
+                });
+                ret
+            }
+            None => func.call(args),
+        };
+        ret.map_err(|err| anyhow!("native func[{name}]: {err}"))
+    }
+
+    /// Result of an async call if it already finished, otherwise the call is started in the
+    /// next round and the current pass fails.
+    pub fn call(
+        id: usize,
+        name: &str,
+        func: &dyn FasAsyncCallable,
+        args: Vec<FamlValue>,
+    ) -> anyhow::Result<FamlValue> {
+        let key = (id, FamlValue::Array(args.clone()).as_print_str());
+        let (ret, is_pending) = ASYNC_CALLS.with(|calls| {
+            let calls = calls.borrow();
+            let calls = calls
+                .as_ref()
+                .ok_or_else(|| anyhow!("native func[{name}] is async, use evaluate_async"))?;
+            let is_pending = calls.pending.iter().any(|(item, _)| *item == key);
+            anyhow::Ok((calls.results.get(&key).cloned(), is_pending))
+        })?;
+        if let Some(ret) = ret {
+            return ret.map_err(|err| anyhow!("native func[{name}]: {err}"));
+        }
+        if !is_pending {
+            // the cell is not borrowed while starting the call, the function may evaluate faml again
+            let fut = func.call(args);
+            ASYNC_CALLS.with(|calls| {
+                if let Some(calls) = calls.borrow_mut().as_mut()
+                    && !calls.pending.iter().any(|(item, _)| *item == key)
+                {
+                    calls.pending.push((key, fut));
+                }
+            });
+        }
+        Err(anyhow!("native func[{name}] is pending"))
+    }
+
+    fn scope<R>(self, f: impl FnOnce() -> R) -> (R, Self) {
+        let prev = ASYNC_CALLS.with(|calls| calls.replace(Some(self)));
+        let ret = f();
+        let calls = ASYNC_CALLS.with(|calls| calls.replace(prev));
+        (ret, calls.unwrap_or_default())
+    }
+
+    /// Poll all pending calls together until every one of them finished.
+    async fn run_pending(&mut self) {
+        let mut pending = std::mem::take(&mut self.pending);
+        let results = &mut self.results;
+        std::future::poll_fn(|cx| {
+            pending.retain_mut(|(key, fut)| match fut.as_mut().poll(cx) {
+                Poll::Ready(ret) => {
+                    results.insert(key.clone(), ret.map_err(|err| err.to_string()));
+                    false
+                }
+                Poll::Pending => true,
+            });
+            match pending.is_empty() {
+                true => Poll::Ready(()),
+                false => Poll::Pending,
+            }
+        })
+        .await;
+    }
+}
+
+impl FamlExpr {
+    /// Evaluate with async native functions registered by `Native::add_async_func`. Async calls
+    /// whose arguments are known are started together, so independent fields resolve
+    /// concurrently, and the evaluation is repeated until all calls it needs have finished.
+    /// Calls in untaken branches may be started too.
+    pub async fn evaluate_async(&self) -> anyhow::Result<FamlValue> {
+        let calls: Vec<_> = self
+            .native_calls()
+            .into_iter()
+            .filter(|call| call.is_async_call())
+            .collect();
+        let mut async_calls = AsyncCalls::default();
+        loop {
+            let ret;
+            (ret, async_calls) = async_calls.scope(|| {
+                for call in &calls {
+                    // starts the call once its arguments are known, errors show up below
+                    let _ = call.evaluate();
+                }
+                self.evaluate()
+            });
+            if async_calls.pending.is_empty() {
+                return ret;
+            }
+            async_calls.run_pending().await;
+        }
+    }
+
+    fn is_async_call(&self) -> bool {
+        let FamlExprImpl::InvokeFunc((func, _)) = &self.base().expr else {
+            return false;
+        };
+        let FamlExprImpl::TempName(names) = &func.base().expr else {
+            return false;
+        };
+        let name = names[1..].join(".");
+        func.find_func(&name).is_none() && func.find_async_func(&name).is_some()
+    }
+}
@@ -0,0 +1,319 @@
+use super::faml_expr::{FamlExpr, FamlExprEntry, FamlExprImpl, FamlExprMatchPattern};
+use anyhow::anyhow;
+use std::collections::{HashMap, HashSet};
+
+impl FamlExpr {
+    /// Check the references of all formulas without evaluating them, unresolved names and
+    /// reference cycles are reported with the key path. Names inside values that can't be known
+    /// statically, like fields of formulas or `json###` literals, are not checked.
+    pub fn check_refs(&self) -> anyhow::Result<()> {
+        let mut checker = RefChecker::default();
+        checker.collect(self, true);
+        if checker.errors.is_empty() {
+            let mut states = HashMap::new();
+            for field in checker.fields.clone() {
+                checker.visit(&field, &mut states, &mut vec![]);
+            }
+        }
+        match checker.errors.is_empty() {
+            true => Ok(()),
+            false => Err(anyhow!("{}", checker.errors.join("\n"))),
+        }
+    }
+
+    /// `native.*` calls inside the expression and the nodes it references.
+    pub(crate) fn native_calls(&self) -> Vec<FamlExpr> {
+        let mut checker = RefChecker::default();
+        let mut visited = HashSet::new();
+        let mut queue = vec![self.clone()];
+        while let Some(expr) = queue.pop() {
+            if visited.insert(expr.id()) {
+                queue.extend(checker.collect(&expr, false));
+            }
+        }
+        let mut ids = HashSet::new();
+        checker.calls.retain(|call| ids.insert(call.id()));
+        checker.calls
+    }
+}
+
+#[derive(PartialEq)]
+enum VisitState {
+    Visiting,
+    Done,
+}
+
+#[derive(Default)]
+struct RefChecker {
+    paths: HashMap<usize, String>,
+    /// Fields of maps in document order, cycles are searched from them.
+    fields: Vec<FamlExpr>,
+    deps: HashMap<usize, Vec<FamlExpr>>,
+    calls: Vec<FamlExpr>,
+    errors: Vec<String>,
+}
+
+impl RefChecker {
+    /// Nodes referenced by formulas inside `expr`, errors are only recorded on the first walk.
+    fn collect(&mut self, expr: &FamlExpr, report: bool) -> Vec<FamlExpr> {
+        let mut ret = vec![];
+        self.walk(expr, "", &mut vec![], report, &mut ret);
+        ret
+    }
+
+    fn walk(
+        &mut self,
+        expr: &FamlExpr,
+        path: &str,
+        locals: &mut Vec<String>,
+        report: bool,
+        ret: &mut Vec<FamlExpr>,
+    ) {
+        if report {
+            self.paths.insert(expr.id(), path.to_string());
+        }
+        match &expr.base().expr {
+            FamlExprImpl::None | FamlExprImpl::Value(_) => (),
+            FamlExprImpl::Array(items) => {
+                for (idx, item) in items.iter().enumerate() {
+                    self.walk(item, &format!("{path}[{idx}]"), locals, report, ret);
+                }
+            }
+            FamlExprImpl::Map(map) => {
+                for (key, item) in map {
+                    let path = match path.is_empty() {
+                        true => key.clone(),
+                        false => format!("{path}.{key}"),
+                    };
+                    // evaluating a map evaluates its fields, so they are its dependencies
+                    if !report {
+                        ret.push(item.clone());
+                        continue;
+                    }
+                    self.fields.push(item.clone());
+                    self.walk(item, &path, locals, report, ret);
+                }
+            }
+            FamlExprImpl::ArrayEntries(entries) | FamlExprImpl::MapEntries(entries) => {
+                for entry in entries {
+                    self.walk_entry(entry, path, locals, report, ret);
+                }
+            }
+            FamlExprImpl::TempName(names) => self.resolve(expr, names, path, locals, report, ret),
+            FamlExprImpl::Op1Prefix((_, a)) | FamlExprImpl::Op1Suffix((a, _)) => {
+                self.walk(a, path, locals, report, ret)
+            }
+            FamlExprImpl::Op2((a, _, b)) | FamlExprImpl::AccessVar((a, b)) => {
+                self.walk(a, path, locals, report, ret);
+                self.walk(b, path, locals, report, ret);
+            }
+            FamlExprImpl::Op3((a, b, c)) => {
+                self.walk(a, path, locals, report, ret);
+                self.walk(b, path, locals, report, ret);
+                self.walk(c, path, locals, report, ret);
+            }
+            FamlExprImpl::Range(range) => {
+                self.walk(&range.start, path, locals, report, ret);
+                self.walk(&range.end, path, locals, report, ret);
+                self.walk(&range.step, path, locals, report, ret);
+            }
+            FamlExprImpl::Match(mat) => {
+                self.walk(&mat.value, path, locals, report, ret);
+                for arm in &mat.arms {
+                    let len = locals.len();
+                    for pattern in &arm.patterns {
+                        match pattern {
+                            FamlExprMatchPattern::Wildcard => (),
+                            FamlExprMatchPattern::Binding(expr) => {
+                                let name = FamlExprMatchPattern::binding_name(expr);
+                                let names = vec![name.clone()];
+                                match locals.contains(&name)
+                                    || expr.get_temp_name_expr(&names).is_ok()
+                                {
+                                    true => self.walk(expr, path, locals, report, ret),
+                                    false => locals.push(name),
+                                }
+                            }
+                            FamlExprMatchPattern::Value(value) => {
+                                self.walk(value, path, locals, report, ret)
+                            }
+                            FamlExprMatchPattern::Range(range) => {
+                                self.walk(&range.start, path, locals, report, ret);
+                                self.walk(&range.end, path, locals, report, ret);
+                            }
+                        }
+                    }
+                    self.walk(&arm.guard, path, locals, report, ret);
+                    self.walk(&arm.value, path, locals, report, ret);
+                    locals.truncate(len);
+                }
+            }
+            FamlExprImpl::Comprehension(comp) => {
+                self.walk(&comp.iter, path, locals, report, ret);
+                let len = locals.len();
+                locals.extend(comp.names.iter().cloned());
+                if let Some(key) = &comp.key {
+                    self.walk(key, path, locals, report, ret);
+                }
+                self.walk(&comp.value, path, locals, report, ret);
+                self.walk(&comp.cond, path, locals, report, ret);
+                locals.truncate(len);
+            }
+            FamlExprImpl::FormatString((_, items)) => {
+                for (item, _) in items {
+                    self.walk(item, path, locals, report, ret);
+                }
+            }
+            FamlExprImpl::InvokeFunc((func, args)) => {
+                for arg in args {
+                    self.walk(arg, path, locals, report, ret);
+                }
+                // `native.func()` and bare method calls are resolved at runtime
+                if let FamlExprImpl::TempName(names) = &func.base().expr
+                    && names.len() > 1
+                {
+                    match &names[0][..] {
+                        "native" => self.calls.push(expr.clone()),
+                        _ => {
+                            let obj = &names[..names.len() - 1];
+                            self.resolve(func, obj, path, locals, report, ret);
+                        }
+                    }
+                }
+            }
+            FamlExprImpl::IfAnno(if_anno) => {
+                for (cond, value) in &if_anno.ifcond_values {
+                    self.walk(cond, path, locals, report, ret);
+                    self.walk(value, path, locals, report, ret);
+                }
+                self.walk(&if_anno.default_value, path, locals, report, ret);
+            }
+            FamlExprImpl::ConstraintAnno(cst_anno) => {
+                self.walk(&cst_anno.value, path, locals, report, ret);
+                locals.push("value".to_string());
+                for constraint in &cst_anno.constraints {
+                    self.walk(constraint, path, locals, report, ret);
+                }
+                locals.pop();
+            }
+            FamlExprImpl::TypeAnno(type_anno) => {
+                self.walk(&type_anno.value, path, locals, report, ret)
+            }
+        }
+    }
+
+    fn walk_entry(
+        &mut self,
+        entry: &FamlExprEntry,
+        path: &str,
+        locals: &mut Vec<String>,
+        report: bool,
+        ret: &mut Vec<FamlExpr>,
+    ) {
+        match entry {
+            FamlExprEntry::Value(value) | FamlExprEntry::Spread(value) => {
+                self.walk(value, path, locals, report, ret)
+            }
+            FamlExprEntry::Pair((key, value)) => {
+                self.walk(key, path, locals, report, ret);
+                self.walk(value, path, locals, report, ret);
+            }
+            FamlExprEntry::If((cond, entry)) => {
+                self.walk(cond, path, locals, report, ret);
+                self.walk_entry(entry, path, locals, report, ret);
+            }
+        }
+    }
+
+    /// Follow the name the same way `evaluate` does, stopping at nodes that aren't maps.
+    fn resolve(
+        &mut self,
+        expr: &FamlExpr,
+        names: &[String],
+        path: &str,
+        locals: &[String],
+        report: bool,
+        ret: &mut Vec<FamlExpr>,
+    ) {
+        let Some(first) = names.first() else {
+            return;
+        };
+        // `native.*` names are host objects resolved at runtime
+        if locals.contains(first) || ["nan", "infinity", "null", "native"].contains(&&first[..]) {
+            return;
+        }
+        let skip = match &first[..] {
+            "base" | "super" => 1,
+            _ => 0,
+        };
+        let Ok(mut target) = expr.get_temp_name_expr(&names[..skip].to_vec()) else {
+            return;
+        };
+        for name in &names[skip..] {
+            let next = match &target.skip_type_anno().base().expr {
+                FamlExprImpl::Map(map) => map.get(name).cloned(),
+                _ => return,
+            };
+            target = match next {
+                Some(next) => next,
+                None => {
+                    if report {
+                        let path = match path.is_empty() {
+                            true => "".to_string(),
+                            false => format!("{path}: "),
+                        };
+                        let name = names.join(".");
+                        self.errors.push(format!("{path}unresolved name[{name}]"));
+                    }
+                    return;
+                }
+            };
+        }
+        ret.push(target);
+    }
+
+    fn visit(
+        &mut self,
+        expr: &FamlExpr,
+        states: &mut HashMap<usize, VisitState>,
+        stack: &mut Vec<usize>,
+    ) {
+        let id = expr.id();
+        match states.get(&id) {
+            Some(VisitState::Done) => return,
+            Some(VisitState::Visiting) => {
+                let start = stack.iter().position(|item| *item == id).unwrap_or(0);
+                let mut cycle: Vec<_> = stack[start..].iter().map(|id| self.path(*id)).collect();
+                cycle.push(self.path(id));
+                self.errors
+                    .push(format!("reference cycle: {}", cycle.join(" -> ")));
+                return;
+            }
+            None => (),
+        }
+        states.insert(id, VisitState::Visiting);
+        stack.push(id);
+        let deps = match self.deps.get(&id) {
+            Some(deps) => deps.clone(),
+            None => {
+                let deps = self.collect(expr, false);
+                self.deps.insert(id, deps.clone());
+                deps
+            }
+        };
+        for dep in deps {
+            if self.errors.is_empty() {
+                self.visit(&dep, states, stack);
+            }
+        }
+        stack.pop();
+        states.insert(id, VisitState::Done);
+    }
+
+    fn path(&self, id: usize) -> String {
+        match self.paths.get(&id) {
+            Some(path) if !path.is_empty() => path.clone(),
+            _ => "<root>".to_string(),
+        }
+    }
+}
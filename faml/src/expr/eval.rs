@@ -4,6 +4,30 @@ use anyhow::anyhow;
 use std::ops::*;
 use std::{collections::HashMap, sync::OnceLock, time::Duration};
 
+/// Duration suffixes with the abbreviations format specs accept, and their length in nanoseconds.
+pub(crate) const DURATION_UNITS: [(&str, &[&str], f64); 10] = [
+    ("nanoseconds", &["ns"], 1.0),
+    ("microseconds", &["us"], 1_000.0),
+    ("milliseconds", &["ms"], 1_000_000.0),
+    ("seconds", &["secs", "s"], 1_000_000_000.0),
+    ("mins", &[], 60_000_000_000.0),
+    ("hours", &[], 3_600_000_000_000.0),
+    ("days", &[], 86_400_000_000_000.0),
+    ("weeks", &[], 604_800_000_000_000.0),
+    ("months", &[], 2_592_000_000_000_000.0),
+    ("years", &[], 31_536_000_000_000_000.0),
+];
+
+/// Distance suffixes with the abbreviations format specs accept, and their length in meters.
+pub(crate) const DISTANCE_UNITS: [(&str, &[&str], f64); 6] = [
+    ("nanometers", &["nm"], 0.000_000_001),
+    ("micrometers", &["um"], 0.000_001),
+    ("millimeters", &["mm"], 0.001),
+    ("meters", &["m"], 1.0),
+    ("kilometers", &["km"], 1_000.0),
+    ("megameters", &["Mm"], 1_000_000.0),
+];
+
 pub(crate) struct Op1Evaluator {}
 
 impl Op1Evaluator {
@@ -22,8 +46,12 @@ impl Op1Evaluator {
     }
 
     pub fn eval_suffix(left: FamlValue, op: &str) -> anyhow::Result<FamlValue> {
-        const G: f64 = Duration::from_secs(1).as_nanos() as f64;
-        const D: f64 = Duration::from_secs(86400).as_secs() as f64;
+        let find = |units: &[(&str, &[&str], f64)]| {
+            units
+                .iter()
+                .find(|(unit, ..)| *unit == op)
+                .map(|(.., scale)| *scale)
+        };
         Ok(match (op, &left) {
             ("++", &FamlValue::Int64(n)) => FamlValue::Int64(n + 1),
             ("++", &FamlValue::Float64(n)) => FamlValue::Float64(n + 1.0),
@@ -34,23 +62,13 @@ impl Op1Evaluator {
                     .as_float()
                     .ok_or_else(|| anyhow!("cannot calc date unit for another type"))?;
 
+                if let Some(nanos) = find(&DURATION_UNITS) {
+                    return Ok(Duration::from_nanos((n * nanos).round() as u64).into());
+                }
+                if let Some(meters) = find(&DISTANCE_UNITS) {
+                    return Ok(Distance::from_meters(n * meters).into());
+                }
                 match op {
-                    "nanoseconds" => Duration::from_nanos(n.round() as u64).into(),
-                    "microseconds" => Duration::from_nanos((n * 1_000.0).round() as u64).into(),
-                    "milliseconds" => Duration::from_nanos((n * 1_000_000.0).round() as u64).into(),
-                    "seconds" => Duration::from_nanos((n * G).round() as u64).into(),
-                    "mins" => Duration::from_nanos((n * G * 60.0).round() as u64).into(),
-                    "hours" => Duration::from_nanos((n * G * 3600.0).round() as u64).into(),
-                    "days" => Duration::from_nanos((n * G * D).round() as u64).into(),
-                    "weeks" => Duration::from_nanos((n * G * D * 7.0).round() as u64).into(),
-                    "months" => Duration::from_nanos((n * G * D * 30.0).round() as u64).into(),
-                    "years" => Duration::from_nanos((n * G * D * 365.0).round() as u64).into(),
-                    "nanometers" => Distance::from_nanometers(n).into(),
-                    "micrometers" => Distance::from_micrometers(n).into(),
-                    "millimeters" => Distance::from_millimeters(n).into(),
-                    "meters" => Distance::from_meters(n).into(),
-                    "kilometers" => Distance::from_kilometers(n).into(),
-                    "megameters" => Distance::from_megameters(n).into(),
                     "KB" => (n * 1024.0).into(),
                     "MB" => (n * 1024.0 * 1024.0).into(),
                     "GB" => (n * 1024.0 * 1024.0 * 1024.0).into(),
@@ -81,24 +99,30 @@ impl Op2Evaluator {
                     ("^", 4),
                     ("|", 4),
                     ("&", 4),
-                    ("<", 5),
-                    ("<=", 5),
-                    (">", 5),
-                    (">=", 5),
-                    ("==", 6),
-                    ("!=", 6),
-                    ("&&", 7),
-                    ("||", 8),
+                    ("..", 5),
+                    ("..=", 5),
+                    ("<", 6),
+                    ("<=", 6),
+                    (">", 6),
+                    (">=", 6),
+                    ("in", 6),
+                    ("not in", 6),
+                    ("==", 7),
+                    ("!=", 7),
+                    ("&&", 8),
+                    ("||", 9),
                 ]
                 .into_iter()
                 .collect()
             })
             .get(op)
-            .unwrap_or(&9)
+            .unwrap_or(&10)
     }
 
     pub fn eval(left: FamlValue, op: &str, right: FamlValue) -> anyhow::Result<FamlValue> {
-        match (left, op, right) {
+        match (left.normalize(), op, right.normalize()) {
+            (left, "in", right) => Ok(FamlValue::Bool(Self::eval_in(&left, &right)?)),
+            (left, "not in", right) => Ok(FamlValue::Bool(!Self::eval_in(&left, &right)?)),
             (FamlValue::Bool(left), _, FamlValue::Bool(right)) => {
                 Ok(FamlValue::Bool(Self::eval_bool(left, op, right)?))
             }
@@ -138,6 +162,22 @@ impl Op2Evaluator {
             (FamlValue::String(left), "*", FamlValue::Int64(right)) if right >= 0 => {
                 Ok(FamlValue::String(left.repeat(right as usize)))
             }
+            (FamlValue::Array(left), "==" | "!=", FamlValue::Array(right)) => {
+                let equal = left.len() == right.len()
+                    && left
+                        .iter()
+                        .zip(right.iter())
+                        .all(|(a, b)| Self::is_equal(a, b));
+                Ok(FamlValue::Bool(equal == (op == "==")))
+            }
+            (FamlValue::Map(left), "==" | "!=", FamlValue::Map(right)) => {
+                let equal = left.len() == right.len()
+                    && left.iter().all(|(k, a)| match right.get(k) {
+                        Some(b) => Self::is_equal(a, b),
+                        None => false,
+                    });
+                Ok(FamlValue::Bool(equal == (op == "==")))
+            }
             (FamlValue::Array(left), "+", FamlValue::Array(right)) => {
                 let mut left = left.clone();
                 left.extend(right.clone());
@@ -152,6 +192,29 @@ impl Op2Evaluator {
         }
     }
 
+    pub fn is_equal(left: &FamlValue, right: &FamlValue) -> bool {
+        left == right
+            || matches!(
+                Self::eval(left.clone(), "==", right.clone()),
+                Ok(FamlValue::Bool(true))
+            )
+    }
+
+    fn eval_in(left: &FamlValue, right: &FamlValue) -> anyhow::Result<bool> {
+        Ok(match right {
+            FamlValue::Array(arr) => arr.iter().any(|item| Self::is_equal(left, item)),
+            FamlValue::Map(map) => match left {
+                FamlValue::String(key) => map.contains_key(key),
+                _ => false,
+            },
+            FamlValue::String(s) => match left {
+                FamlValue::String(sub) => s.contains(sub),
+                _ => Err(anyhow!("only type[string] can be searched in string"))?,
+            },
+            _ => Err(anyhow!("illegal operator: in"))?,
+        })
+    }
+
     fn eval_bool(left: bool, op: &str, right: bool) -> anyhow::Result<bool> {
         Ok(match op {
             "&&" => left && right,
@@ -207,6 +270,10 @@ impl Op2Evaluator {
     fn eval_string(left: &str, op: &str, right: &str) -> anyhow::Result<FamlValue> {
         match op {
             "+" => Ok(FamlValue::String(format!("{left}{right}"))),
+            "<" => Ok(FamlValue::Bool(left < right)),
+            "<=" => Ok(FamlValue::Bool(left <= right)),
+            ">" => Ok(FamlValue::Bool(left > right)),
+            ">=" => Ok(FamlValue::Bool(left >= right)),
             "==" => Ok(FamlValue::Bool(left == right)),
             "!=" => Ok(FamlValue::Bool(left != right)),
             _ => Err(anyhow!("illegal operator: {op}")),
@@ -291,6 +358,146 @@ impl Op2Evaluator {
     }
 }
 
+/// Upper bound of the number of elements a range can be expanded into.
+pub(crate) const MAX_RANGE_LEN: i128 = 1_000_000;
+
+pub(crate) struct RangeEvaluator {}
+
+impl RangeEvaluator {
+    pub fn contains(
+        val: &FamlValue,
+        start: FamlValue,
+        end: FamlValue,
+        inclusive: bool,
+    ) -> anyhow::Result<bool> {
+        let compare = |left: &FamlValue, op: &str, right: &FamlValue| {
+            Op2Evaluator::eval(left.clone(), op, right.clone())?
+                .as_bool()
+                .ok_or_else(|| anyhow!("cannot compare range bounds"))
+        };
+        let end_op = if inclusive { "<=" } else { "<" };
+        Ok(compare(&start, "<=", val)? && compare(val, end_op, &end)?)
+    }
+
+    pub fn expand(
+        start: FamlValue,
+        end: FamlValue,
+        step: FamlValue,
+        inclusive: bool,
+    ) -> anyhow::Result<FamlValue> {
+        let (start, end) = match (start.as_int(), end.as_int()) {
+            (Some(start), Some(end)) => (start, end),
+            _ => return Err(anyhow!("only type[int] range can be expanded")),
+        };
+        let step = match step {
+            FamlValue::None => 1,
+            FamlValue::Int64(0) => return Err(anyhow!("range step cannot be zero")),
+            FamlValue::Int64(step) => step,
+            _ => return Err(anyhow!("only type[int] range step supported")),
+        };
+        let distance = (end as i128 - start as i128) * step.signum() as i128;
+        let span = if inclusive { distance } else { distance - 1 };
+        let len = if span < 0 {
+            0
+        } else {
+            span / (step as i128).abs() + 1
+        };
+        if len > MAX_RANGE_LEN {
+            return Err(anyhow!(
+                "range[{start}..{end}] has {len} elements, more than the limit {MAX_RANGE_LEN}"
+            ));
+        }
+        let mut ret = Vec::with_capacity(len as usize);
+        let mut n = Some(start);
+        while let Some(cur) = n.filter(|_| ret.len() < len as usize) {
+            ret.push(FamlValue::Int64(cur));
+            n = cur.checked_add(step);
+        }
+        Ok(FamlValue::Array(ret))
+    }
+}
+
+pub(crate) struct IndexEvaluator {}
+
+impl IndexEvaluator {
+    fn normalize(index: i64, len: usize) -> Option<usize> {
+        let index = if index < 0 { index + len as i64 } else { index };
+        match index >= 0 && index < len as i64 {
+            true => Some(index as usize),
+            false => None,
+        }
+    }
+
+    pub fn index(val: &FamlValue, index: i64) -> Option<FamlValue> {
+        match val {
+            FamlValue::Array(arr) => Self::normalize(index, arr.len()).map(|i| arr[i].clone()),
+            FamlValue::String(s) => {
+                let chars: Vec<_> = s.chars().collect();
+                Self::normalize(index, chars.len()).map(|i| chars[i].to_string().into())
+            }
+            _ => None,
+        }
+    }
+
+    fn slice_indices(
+        len: usize,
+        start: Option<i64>,
+        end: Option<i64>,
+        step: Option<i64>,
+    ) -> anyhow::Result<Vec<usize>> {
+        let len = len as i64;
+        let clamp = |index: i64, lower: i64, upper: i64| {
+            let index = if index < 0 { index + len } else { index };
+            index.clamp(lower, upper)
+        };
+        let mut ret = vec![];
+        match step.unwrap_or(1) {
+            0 => return Err(anyhow!("slice step cannot be zero")),
+            step if step > 0 => {
+                let mut n = start.map(|i| clamp(i, 0, len)).unwrap_or(0);
+                let end = end.map(|i| clamp(i, 0, len)).unwrap_or(len);
+                while n < end {
+                    ret.push(n as usize);
+                    n += step;
+                }
+            }
+            step => {
+                let mut n = start.map(|i| clamp(i, -1, len - 1)).unwrap_or(len - 1);
+                let end = end.map(|i| clamp(i, -1, len - 1)).unwrap_or(-1);
+                while n > end {
+                    ret.push(n as usize);
+                    n += step;
+                }
+            }
+        }
+        Ok(ret)
+    }
+
+    pub fn slice(
+        val: &FamlValue,
+        start: Option<i64>,
+        end: Option<i64>,
+        step: Option<i64>,
+    ) -> anyhow::Result<FamlValue> {
+        match val {
+            FamlValue::Array(arr) => {
+                let indices = Self::slice_indices(arr.len(), start, end, step)?;
+                Ok(FamlValue::Array(
+                    indices.into_iter().map(|i| arr[i].clone()).collect(),
+                ))
+            }
+            FamlValue::String(s) => {
+                let chars: Vec<_> = s.chars().collect();
+                let indices = Self::slice_indices(chars.len(), start, end, step)?;
+                Ok(FamlValue::String(
+                    indices.into_iter().map(|i| chars[i]).collect(),
+                ))
+            }
+            _ => Err(anyhow!("only type[array] or type[string] can be sliced")),
+        }
+    }
+}
+
 macro_rules! impl_calc {
     ($trait:ident, $method:ident, $op:expr) => {
         impl $trait<f64> for FamlValue {
@@ -1,9 +1,13 @@
-use super::eval::{Op1Evaluator, Op2Evaluator};
+use super::async_eval::AsyncCalls;
+use super::eval::{IndexEvaluator, Op1Evaluator, Op2Evaluator, RangeEvaluator};
+use super::faml_type::FamlType;
 use super::faml_value::FamlValue;
-use crate::Native;
-use crate::expr::invoke::InvokeExt;
+use super::format_spec::FormatSpec;
+use super::scope::LocalScope;
+use crate::native::{FasAsyncCallable, FasCallable, FunctionRegistry, HostObject, HostValue};
 use crate::string_utils::IntoBaseExt;
 use anyhow::anyhow;
+use indexmap::IndexMap;
 use pest::Parser;
 use pest_derive::Parser;
 use serde::Deserialize;
@@ -21,17 +25,23 @@ pub enum FamlExprImpl {
     None,
     Value(FamlValue),
     Array(Vec<FamlExpr>),
-    Map(HashMap<String, FamlExpr>),
+    Map(IndexMap<String, FamlExpr>),
+    ArrayEntries(Vec<FamlExprEntry>),
+    MapEntries(Vec<FamlExprEntry>),
     TempName(Vec<String>),
     Op1Prefix((String, FamlExpr)),
     Op1Suffix((FamlExpr, String)),
     Op2((FamlExpr, String, FamlExpr)),
     Op3((FamlExpr, FamlExpr, FamlExpr)),
-    FormatString((Vec<String>, Vec<FamlExpr>)),
+    Range(FamlExprRange),
+    Match(FamlExprMatch),
+    Comprehension(FamlExprComprehension),
+    FormatString((Vec<String>, Vec<(FamlExpr, FormatSpec)>)),
     AccessVar((FamlExpr, FamlExpr)),
     InvokeFunc((FamlExpr, Vec<FamlExpr>)),
     IfAnno(FamlExprIfAnno),
     ConstraintAnno(FamlExprConstraintAnno),
+    TypeAnno(FamlExprTypeAnno),
 }
 
 #[derive(Debug, Clone)]
@@ -46,11 +56,66 @@ pub struct FamlExprConstraintAnno {
     pub value: FamlExpr,
 }
 
+#[derive(Debug, Clone)]
+pub struct FamlExprTypeAnno {
+    pub ty: FamlType,
+    pub path: String,
+    pub value: FamlExpr,
+}
+
+#[derive(Debug, Clone)]
+pub enum FamlExprEntry {
+    Value(FamlExpr),
+    Pair((FamlExpr, FamlExpr)),
+    Spread(FamlExpr),
+    If((FamlExpr, Box<FamlExprEntry>)),
+}
+
+#[derive(Debug, Clone)]
+pub struct FamlExprRange {
+    pub start: FamlExpr,
+    pub end: FamlExpr,
+    pub step: FamlExpr,
+    pub inclusive: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct FamlExprMatch {
+    pub value: FamlExpr,
+    pub arms: Vec<FamlExprMatchArm>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FamlExprMatchArm {
+    pub patterns: Vec<FamlExprMatchPattern>,
+    pub guard: FamlExpr,
+    pub value: FamlExpr,
+}
+
+#[derive(Debug, Clone)]
+pub enum FamlExprMatchPattern {
+    Wildcard,
+    /// a bare name, compared with the local or field it names, and bound otherwise
+    Binding(FamlExpr),
+    Value(FamlExpr),
+    Range(FamlExprRange),
+}
+
+#[derive(Debug, Clone)]
+pub struct FamlExprComprehension {
+    pub key: Option<FamlExpr>,
+    pub value: FamlExpr,
+    pub names: Vec<String>,
+    pub iter: FamlExpr,
+    pub cond: FamlExpr,
+}
+
 #[derive(Debug, Clone)]
 pub struct FamlExprBase {
     pub expr: FamlExprImpl,
     base_expr: WeakFamlExpr,
     super_expr: WeakFamlExpr,
+    registry: Option<FunctionRegistry>,
 }
 
 #[derive(Debug, Clone)]
@@ -89,6 +154,387 @@ impl FamlExprConstraintAnno {
         self.value
             .init_weak_expr(base_expr.clone(), super_expr.clone());
     }
+
+    pub fn check(&self, value: &FamlValue) -> anyhow::Result<()> {
+        if value.is_none() {
+            return Ok(());
+        }
+        let vars: HashMap<_, _> = [("value".to_string(), value.clone())].into();
+        for constraint in &self.constraints {
+            let ret = LocalScope::with(vars.clone(), || constraint.evaluate())?;
+            if ret.as_bool() != Some(true) {
+                let (_, vstr) = LocalScope::with(vars.clone(), || {
+                    constraint.trace_internal(false, &mut HashMap::new())
+                })?;
+                return Err(anyhow!(
+                    "constraint[{vstr}] not satisfied by value[{}]",
+                    value.as_str()
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FamlExprTypeAnno {
+    pub fn init_weak_expr(&mut self, base_expr: WeakFamlExpr, super_expr: WeakFamlExpr) {
+        self.value.init_weak_expr(base_expr, super_expr);
+    }
+}
+
+impl FamlExprEntry {
+    fn get_pair(&self, name: &str) -> Option<&FamlExpr> {
+        match self {
+            FamlExprEntry::Pair((key, value)) => match &key.base().expr {
+                FamlExprImpl::Value(FamlValue::String(key)) if key == name => Some(value),
+                _ => None,
+            },
+            FamlExprEntry::If((_, entry)) => entry.get_pair(name),
+            _ => None,
+        }
+    }
+
+    pub fn init_weak_expr(&mut self, base_expr: WeakFamlExpr, super_expr: WeakFamlExpr) {
+        match self {
+            FamlExprEntry::Value(expr) | FamlExprEntry::Spread(expr) => {
+                expr.init_weak_expr(base_expr, super_expr)
+            }
+            FamlExprEntry::Pair((key, value)) => {
+                key.init_weak_expr(base_expr.clone(), super_expr.clone());
+                value.init_weak_expr(base_expr, super_expr);
+            }
+            FamlExprEntry::If((cond, entry)) => {
+                cond.init_weak_expr(base_expr.clone(), super_expr.clone());
+                entry.init_weak_expr(base_expr, super_expr);
+            }
+        }
+    }
+
+    fn is_enabled(cond: &FamlExpr) -> anyhow::Result<bool> {
+        cond.evaluate()?
+            .as_bool()
+            .ok_or_else(|| anyhow!("bool expected"))
+    }
+
+    pub fn eval_array(&self, ret: &mut Vec<FamlValue>) -> anyhow::Result<()> {
+        match self {
+            FamlExprEntry::Value(expr) => ret.push(expr.evaluate()?),
+            FamlExprEntry::Pair(_) => Err(anyhow!("key-value entry is not allowed in array"))?,
+            FamlExprEntry::Spread(expr) => match expr.evaluate()? {
+                FamlValue::None => (),
+                FamlValue::Array(arr) => ret.extend(arr),
+                _ => Err(anyhow!("only type[array] can be spread into array"))?,
+            },
+            FamlExprEntry::If((cond, entry)) => {
+                if Self::is_enabled(cond)? {
+                    entry.eval_array(ret)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn eval_map(&self, ret: &mut IndexMap<String, FamlValue>) -> anyhow::Result<()> {
+        match self {
+            FamlExprEntry::Value(_) => Err(anyhow!("key-value entry expected in map"))?,
+            FamlExprEntry::Pair((key, value)) => {
+                let key = match key.evaluate()? {
+                    FamlValue::String(key) => key,
+                    FamlValue::Int64(key) => key.to_string(),
+                    _ => Err(anyhow!("only type[string] or type[int] map key supported"))?,
+                };
+                ret.insert(key, value.evaluate()?);
+            }
+            FamlExprEntry::Spread(expr) => match expr.evaluate()? {
+                FamlValue::None => (),
+                FamlValue::Map(map) => ret.extend(map),
+                _ => Err(anyhow!("only type[map] can be spread into map"))?,
+            },
+            FamlExprEntry::If((cond, entry)) => {
+                if Self::is_enabled(cond)? {
+                    entry.eval_map(ret)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn trace_str(&self, maps: &mut HashMap<String, (FamlValue, String)>) -> anyhow::Result<String> {
+        Ok(match self {
+            FamlExprEntry::Value(expr) => expr.trace_internal(false, maps)?.1,
+            FamlExprEntry::Pair((key, value)) => {
+                let key = match &key.base().expr {
+                    FamlExprImpl::Value(FamlValue::String(key)) => key.clone(),
+                    _ => format!("[{}]", key.trace_internal(false, maps)?.1),
+                };
+                format!("{key}: {}", value.trace_internal(false, maps)?.1)
+            }
+            FamlExprEntry::Spread(expr) => format!("...{}", expr.trace_internal(true, maps)?.1),
+            FamlExprEntry::If((cond, entry)) => {
+                let (_, vstr_cond) = cond.trace_internal(false, maps)?;
+                format!("if {vstr_cond}: {}", entry.trace_str(maps)?)
+            }
+        })
+    }
+}
+
+impl FamlExprRange {
+    pub fn init_weak_expr(&mut self, base_expr: WeakFamlExpr, super_expr: WeakFamlExpr) {
+        self.start
+            .init_weak_expr(base_expr.clone(), super_expr.clone());
+        self.end
+            .init_weak_expr(base_expr.clone(), super_expr.clone());
+        self.step
+            .init_weak_expr(base_expr.clone(), super_expr.clone());
+    }
+
+    pub fn contains(&self, value: &FamlValue) -> anyhow::Result<bool> {
+        let start = self.start.evaluate()?;
+        let end = self.end.evaluate()?;
+        RangeEvaluator::contains(value, start, end, self.inclusive)
+    }
+
+    pub fn slice(&self, value: &FamlValue) -> anyhow::Result<FamlValue> {
+        let bound = |expr: &FamlExpr| match expr.evaluate()? {
+            FamlValue::None => Ok(None),
+            FamlValue::Int64(n) => Ok(Some(n)),
+            _ => Err(anyhow!("only type[int] slice index supported")),
+        };
+        let start = bound(&self.start)?;
+        let mut end = bound(&self.end)?;
+        if self.inclusive {
+            end = end.map(|n| n + 1).filter(|n| *n != 0);
+        }
+        let step = bound(&self.step)?;
+        IndexEvaluator::slice(value, start, end, step)
+    }
+
+    fn trace_parts(
+        &self,
+        maps: &mut HashMap<String, (FamlValue, String)>,
+    ) -> anyhow::Result<[String; 3]> {
+        let mut trace_part = |expr: &FamlExpr| match expr.is_none() {
+            true => Ok("".to_string()),
+            false => expr.trace_internal(true, maps).map(|(_, vstr)| vstr),
+        };
+        Ok([
+            trace_part(&self.start)?,
+            trace_part(&self.end)?,
+            trace_part(&self.step)?,
+        ])
+    }
+
+    fn trace_str(&self, maps: &mut HashMap<String, (FamlValue, String)>) -> anyhow::Result<String> {
+        let [start, end, _] = self.trace_parts(maps)?;
+        let op = if self.inclusive { "..=" } else { ".." };
+        Ok(format!("{start}{op}{end}"))
+    }
+}
+
+impl FamlExprMatchPattern {
+    pub fn init_weak_expr(&mut self, base_expr: WeakFamlExpr, super_expr: WeakFamlExpr) {
+        match self {
+            FamlExprMatchPattern::Wildcard => (),
+            FamlExprMatchPattern::Binding(expr) | FamlExprMatchPattern::Value(expr) => {
+                expr.init_weak_expr(base_expr, super_expr)
+            }
+            FamlExprMatchPattern::Range(range) => range.init_weak_expr(base_expr, super_expr),
+        }
+    }
+
+    fn is_match(
+        &self,
+        value: &FamlValue,
+        vars: &mut HashMap<String, FamlValue>,
+    ) -> anyhow::Result<bool> {
+        Ok(match self {
+            FamlExprMatchPattern::Wildcard => true,
+            FamlExprMatchPattern::Binding(expr) => match Self::resolve_binding(expr)? {
+                Some(other) => Op2Evaluator::is_equal(value, &other),
+                None => {
+                    vars.insert(Self::binding_name(expr), value.clone());
+                    true
+                }
+            },
+            FamlExprMatchPattern::Value(expr) => Op2Evaluator::is_equal(value, &expr.evaluate()?),
+            FamlExprMatchPattern::Range(range) => range.contains(value)?,
+        })
+    }
+
+    pub(crate) fn binding_name(expr: &FamlExpr) -> String {
+        match &expr.base().expr {
+            FamlExprImpl::TempName(names) => names.join("."),
+            _ => "".to_string(),
+        }
+    }
+
+    fn resolve_binding(expr: &FamlExpr) -> anyhow::Result<Option<FamlValue>> {
+        let FamlExprImpl::TempName(names) = &expr.base().expr else {
+            return Ok(None);
+        };
+        if let Some(val) = LocalScope::get(names) {
+            return Ok(Some(val?));
+        }
+        match expr.get_temp_name_expr(names) {
+            Ok(target) => Ok(Some(target.evaluate()?)),
+            Err(_) => Ok(None),
+        }
+    }
+
+    fn trace_str(&self, maps: &mut HashMap<String, (FamlValue, String)>) -> anyhow::Result<String> {
+        Ok(match self {
+            FamlExprMatchPattern::Wildcard => "_".to_string(),
+            FamlExprMatchPattern::Binding(expr) => Self::binding_name(expr),
+            FamlExprMatchPattern::Value(expr) => match &expr.base().expr {
+                FamlExprImpl::Value(val) => val.as_print_str(),
+                _ => expr.trace_internal(true, maps)?.1,
+            },
+            FamlExprMatchPattern::Range(range) => range.trace_str(maps)?,
+        })
+    }
+}
+
+impl FamlExprMatch {
+    pub fn init_weak_expr(&mut self, base_expr: WeakFamlExpr, super_expr: WeakFamlExpr) {
+        self.value
+            .init_weak_expr(base_expr.clone(), super_expr.clone());
+        for arm in &mut self.arms {
+            for pattern in &mut arm.patterns {
+                pattern.init_weak_expr(base_expr.clone(), super_expr.clone());
+            }
+            arm.guard
+                .init_weak_expr(base_expr.clone(), super_expr.clone());
+            arm.value
+                .init_weak_expr(base_expr.clone(), super_expr.clone());
+        }
+    }
+
+    fn select(&self) -> anyhow::Result<(&FamlExprMatchArm, HashMap<String, FamlValue>)> {
+        let value = self.value.evaluate()?;
+        for arm in &self.arms {
+            for pattern in &arm.patterns {
+                let mut vars = HashMap::new();
+                if !pattern.is_match(&value, &mut vars)? {
+                    continue;
+                }
+                if !arm.guard.is_none() {
+                    let guard = LocalScope::with(vars.clone(), || arm.guard.evaluate())?;
+                    if guard.as_bool() != Some(true) {
+                        continue;
+                    }
+                }
+                return Ok((arm, vars));
+            }
+        }
+        Err(anyhow!("no match arm for value[{}]", value.as_str()))
+    }
+}
+
+impl FamlExprComprehension {
+    pub fn init_weak_expr(&mut self, base_expr: WeakFamlExpr, super_expr: WeakFamlExpr) {
+        if let Some(key) = &mut self.key {
+            key.init_weak_expr(base_expr.clone(), super_expr.clone());
+        }
+        self.value
+            .init_weak_expr(base_expr.clone(), super_expr.clone());
+        self.iter
+            .init_weak_expr(base_expr.clone(), super_expr.clone());
+        self.cond
+            .init_weak_expr(base_expr.clone(), super_expr.clone());
+    }
+
+    fn iterate(&self) -> anyhow::Result<Vec<HashMap<String, FamlValue>>> {
+        let iter = self.iter.evaluate()?;
+        let is_map = iter.is_map();
+        let items: Vec<(FamlValue, FamlValue)> = match iter {
+            FamlValue::None => vec![],
+            FamlValue::Array(arr) => arr
+                .into_iter()
+                .enumerate()
+                .map(|(i, val)| ((i as i64).into(), val))
+                .collect(),
+            FamlValue::Map(map) => map.into_iter().map(|(k, v)| (k.into(), v)).collect(),
+            FamlValue::String(s) => s
+                .chars()
+                .enumerate()
+                .map(|(i, c)| ((i as i64).into(), c.to_string().into()))
+                .collect(),
+            _ => Err(anyhow!(
+                "only type[array], type[map] or type[string] can be iterated"
+            ))?,
+        };
+        let mut ret = vec![];
+        for (key, val) in items {
+            let vars: HashMap<_, _> = match &self.names[..] {
+                [name] if is_map => [(name.clone(), key)].into(),
+                [name] => [(name.clone(), val)].into(),
+                [name1, name2] => [(name1.clone(), key), (name2.clone(), val)].into(),
+                _ => unreachable!(),
+            };
+            if !self.cond.is_none() {
+                let cond = LocalScope::with(vars.clone(), || self.cond.evaluate())?;
+                if !cond.as_bool().ok_or_else(|| anyhow!("bool expected"))? {
+                    continue;
+                }
+            }
+            ret.push(vars);
+        }
+        Ok(ret)
+    }
+
+    pub fn evaluate(&self) -> anyhow::Result<FamlValue> {
+        let iterations = self.iterate()?;
+        match &self.key {
+            None => {
+                let mut ret = vec![];
+                for vars in iterations {
+                    ret.push(LocalScope::with(vars, || self.value.evaluate())?);
+                }
+                Ok(FamlValue::Array(ret))
+            }
+            Some(key) => {
+                let mut ret = IndexMap::new();
+                for vars in iterations {
+                    let (key, value) = LocalScope::with(vars, || {
+                        anyhow::Ok((key.evaluate()?, self.value.evaluate()?))
+                    })?;
+                    let key = match key {
+                        FamlValue::String(key) => key,
+                        FamlValue::Int64(key) => key.to_string(),
+                        _ => Err(anyhow!("only type[string] or type[int] map key supported"))?,
+                    };
+                    ret.insert(key, value);
+                }
+                Ok(FamlValue::Map(ret))
+            }
+        }
+    }
+
+    fn trace_str(&self, maps: &mut HashMap<String, (FamlValue, String)>) -> anyhow::Result<String> {
+        let (_, vstr_iter) = self.iter.trace_internal(false, maps)?;
+        let mut vstr = format!("for {} in {vstr_iter}", self.names.join(", "));
+        let vstr_value = match self.iterate()?.into_iter().next() {
+            Some(vars) => LocalScope::with(vars, || {
+                if !self.cond.is_none() {
+                    let (_, vstr_cond) = self.cond.trace_internal(false, maps)?;
+                    vstr = format!("{vstr} if {vstr_cond}");
+                }
+                let (_, vstr_value) = self.value.trace_internal(false, maps)?;
+                match &self.key {
+                    Some(key) => {
+                        let (_, vstr_key) = key.trace_internal(true, maps)?;
+                        anyhow::Ok(format!("{vstr_key}: {vstr_value}"))
+                    }
+                    None => Ok(vstr_value),
+                }
+            })?,
+            None => "_".to_string(),
+        };
+        Ok(match &self.key {
+            Some(_) => format!("{{{vstr_value} {vstr}}}"),
+            None => format!("[{vstr_value} {vstr}]"),
+        })
+    }
 }
 
 impl FamlExpr {
@@ -120,12 +566,104 @@ impl FamlExpr {
         WeakFamlExpr(Arc::downgrade(&(self.0)))
     }
 
+    /// Identity of the node, shared by clones.
+    pub(crate) fn id(&self) -> usize {
+        Arc::as_ptr(&self.0) as *const () as usize
+    }
+
     pub fn is_none(&self) -> bool {
         match self.base().expr {
             FamlExprImpl::None => true,
             _ => false,
         }
     }
+
+    /// Type declared on the node, annotated fields may also have constraints.
+    pub fn declared_type(&self) -> Option<&FamlType> {
+        match &self.base().expr {
+            FamlExprImpl::TypeAnno(type_anno) => Some(&type_anno.ty),
+            FamlExprImpl::ConstraintAnno(cst_anno) => cst_anno.value.declared_type(),
+            _ => None,
+        }
+    }
+
+    /// Attach functions to the document, `native.*` names of all its formulas look them up
+    /// before the global functions and objects registered with `Native`.
+    pub fn set_registry(&mut self, registry: FunctionRegistry) {
+        self.base_mut().registry = Some(registry);
+    }
+
+    pub fn registry(&self) -> Option<&FunctionRegistry> {
+        self.base().registry.as_ref()
+    }
+
+    pub(crate) fn find_func(&self, name: &str) -> Option<Box<dyn FasCallable + 'static>> {
+        let root = self.base().base_expr.upgrade().ok();
+        root.and_then(|root| root.registry()?.get_func(name))
+            .or_else(|| FunctionRegistry::global().get_func(name))
+    }
+
+    pub(crate) fn find_async_func(
+        &self,
+        name: &str,
+    ) -> Option<Box<dyn FasAsyncCallable + 'static>> {
+        let root = self.base().base_expr.upgrade().ok();
+        root.and_then(|root| root.registry()?.get_async_func(name))
+            .or_else(|| FunctionRegistry::global().get_async_func(name))
+    }
+
+    /// Extension method of the document registry, then of the global one.
+    fn find_method(&self, ty: &str, name: &str) -> Option<Box<dyn FasCallable + 'static>> {
+        let root = self.base().base_expr.upgrade().ok();
+        root.and_then(|root| root.registry()?.get_method(ty, name))
+            .or_else(|| FunctionRegistry::global().get_method(ty, name))
+    }
+
+    fn find_object(&self, name: &str) -> Option<Arc<dyn HostObject>> {
+        let root = self.base().base_expr.upgrade().ok();
+        root.and_then(|root| root.registry()?.get_object(name))
+            .or_else(|| FunctionRegistry::global().get_object(name))
+    }
+
+    /// Resolve `native.a.b` through the host objects, the longest registered name is the object
+    /// and the remaining names are read as its properties. `None` if no object is registered.
+    fn resolve_native(&self, names: &[String]) -> anyhow::Result<Option<HostValue>> {
+        let found = (1..=names.len()).rev().find_map(|len| {
+            let obj = self.find_object(&names[..len].join("."))?;
+            Some((HostValue::Object(obj), len))
+        });
+        let Some((mut val, len)) = found else {
+            return Ok(None);
+        };
+        for (idx, name) in names.iter().enumerate().skip(len) {
+            let path = names[..=idx].join(".");
+            val = match val {
+                HostValue::Object(obj) => obj
+                    .get(name)
+                    .map_err(|err| anyhow!("native[{path}]: {err}"))?,
+                HostValue::Value(val) => val.normalize().get(name).cloned().map(HostValue::Value),
+            }
+            .ok_or_else(|| anyhow!("native[{path}] not found"))?;
+        }
+        Ok(Some(val))
+    }
+
+    pub(crate) fn skip_type_anno(&self) -> &Self {
+        match &self.base().expr {
+            FamlExprImpl::TypeAnno(type_anno) => type_anno.value.skip_type_anno(),
+            _ => self,
+        }
+    }
+
+    fn skip_type_anno_mut(&mut self) -> &mut Self {
+        if !matches!(self.base().expr, FamlExprImpl::TypeAnno(_)) {
+            return self;
+        }
+        match &mut self.base_mut().expr {
+            FamlExprImpl::TypeAnno(type_anno) => type_anno.value.skip_type_anno_mut(),
+            _ => unreachable!(),
+        }
+    }
 }
 
 impl FamlExprBase {
@@ -144,6 +682,7 @@ impl FamlExprImpl {
             expr: self,
             base_expr: WeakFamlExpr(Weak::new()),
             super_expr: WeakFamlExpr(Weak::new()),
+            registry: None,
         }
     }
 
@@ -154,7 +693,7 @@ impl FamlExprImpl {
 
 impl FamlExpr {
     pub fn get_at(&self, index: usize) -> Option<&Self> {
-        let expr_impl = &unsafe { &*self.0.get() }.expr;
+        let expr_impl = &self.skip_type_anno().base().expr;
         if let FamlExprImpl::Array(arr) = expr_impl {
             return Some(&arr[index]);
         }
@@ -162,7 +701,7 @@ impl FamlExpr {
     }
 
     pub fn get_at_mut(&mut self, index: usize) -> &mut Self {
-        let expr_impl = &mut unsafe { &mut *self.0.get() }.expr;
+        let expr_impl = &mut self.skip_type_anno_mut().base_mut().expr;
         let arr = match expr_impl {
             FamlExprImpl::Array(arr) => arr,
             _ => {
@@ -199,11 +738,15 @@ impl FamlExpr {
                 let num: usize = path[1..path.len() - 1].parse().ok()?;
                 obj_ref = obj_ref.get_at(num)?;
             } else {
-                let map = match &obj_ref.base().expr {
-                    FamlExprImpl::Map(map) => map,
+                obj_ref = match &obj_ref.skip_type_anno().base().expr {
+                    FamlExprImpl::Map(map) => map.get(path)?,
+                    // later entries override earlier ones, only static keys can be referenced
+                    FamlExprImpl::MapEntries(entries) => entries
+                        .iter()
+                        .rev()
+                        .find_map(|entry| entry.get_pair(path))?,
                     _ => return None,
                 };
-                obj_ref = map.get(path)?;
             }
         }
         Some(obj_ref)
@@ -221,11 +764,11 @@ impl FamlExpr {
                 let num: usize = path[1..path.len() - 1].parse().unwrap_or(0);
                 obj_ref = obj_ref.get_at_mut(num);
             } else {
-                let expr_impl = &mut obj_ref.base_mut().expr;
+                let expr_impl = &mut obj_ref.skip_type_anno_mut().base_mut().expr;
                 let map = match expr_impl {
                     FamlExprImpl::Map(map) => map,
                     _ => {
-                        *expr_impl = FamlExprImpl::Map(HashMap::new());
+                        *expr_impl = FamlExprImpl::Map(IndexMap::new());
                         match expr_impl {
                             FamlExprImpl::Map(map) => map,
                             _ => panic!(),
@@ -338,41 +881,143 @@ impl FamlExpr {
         Ok(expr)
     }
 
+    pub fn from_toml(root: toml::Value) -> anyhow::Result<Self> {
+        let mut expr = FamlValue::from_toml(root)?.to_expr();
+        let base_expr = expr.to_weak();
+        expr.init_weak_expr(base_expr.clone(), base_expr);
+        Ok(expr)
+    }
+
     fn parse_faml(root: pest::iterators::Pair<'_, Rule>) -> anyhow::Result<Self> {
+        let mut types = HashMap::new();
+        for root_item in root.clone().into_inner() {
+            if root_item.as_rule() == Rule::type_decl {
+                let mut root_items = root_item.into_inner().skip(1);
+                let name = root_items.next().unwrap().as_str().to_string();
+                if FamlType::from_name(&name).is_some() || types.contains_key(&name) {
+                    Err(anyhow!("type[{name}] is already declared"))?;
+                }
+                let ty = match Self::parse_type_expr(root_items.next().unwrap(), &types)? {
+                    FamlType::Struct((_, fields)) => FamlType::Struct((name.clone(), fields)),
+                    ty => ty,
+                };
+                types.insert(name, ty);
+            } else if root_item.as_rule() == Rule::enum_decl {
+                let mut root_items = root_item.into_inner().skip(1);
+                let name = root_items.next().unwrap().as_str().to_string();
+                if FamlType::from_name(&name).is_some() || types.contains_key(&name) {
+                    Err(anyhow!("type[{name}] is already declared"))?;
+                }
+                let mut variants: Vec<String> = vec![];
+                for variant in root_items {
+                    let variant = variant.into_inner().next().unwrap();
+                    let variant = match variant.as_rule() {
+                        Rule::string_literal => variant.as_str().into_base(),
+                        _ => variant.as_str().to_string(),
+                    };
+                    if variants.contains(&variant) {
+                        Err(anyhow!(
+                            "variant[{variant}] of enum[{name}] is already declared"
+                        ))?;
+                    }
+                    variants.push(variant);
+                }
+                types.insert(name.clone(), FamlType::Enum((name, variants)));
+            }
+        }
         let mut ret = FamlExpr::new();
         for root_item in root.into_inner() {
             match root_item.as_rule() {
                 Rule::group_block => {
-                    let val = Self::parse_block(root_item)?;
+                    let val = Self::parse_block(root_item, &types)?;
                     ret.apply(val)?;
                 }
-                Rule::EOI => (),
+                Rule::type_decl | Rule::enum_decl | Rule::EOI => (),
                 _ => unreachable!(),
             }
         }
         Ok(ret)
     }
 
-    fn parse_block(root: pest::iterators::Pair<'_, Rule>) -> anyhow::Result<Self> {
+    /// Named types must be declared before they are used, so types can't be recursive.
+    fn parse_type_expr(
+        root: pest::iterators::Pair<'_, Rule>,
+        types: &HashMap<String, FamlType>,
+    ) -> anyhow::Result<FamlType> {
+        let mut ret = FamlType::Any;
+        for root_item in root.into_inner() {
+            ret = match root_item.as_rule() {
+                Rule::id => {
+                    let name = root_item.as_str();
+                    match FamlType::from_name(name) {
+                        Some(ty) => ty,
+                        None => types
+                            .get(name)
+                            .cloned()
+                            .ok_or_else(|| anyhow!("type[{name}] not found"))?,
+                    }
+                }
+                Rule::type_array => {
+                    let item =
+                        Self::parse_type_expr(root_item.into_inner().next().unwrap(), types)?;
+                    FamlType::Array(Box::new(item))
+                }
+                Rule::type_struct => {
+                    let mut fields: Vec<(String, FamlType)> = vec![];
+                    for field in root_item.into_inner() {
+                        let mut field_items = field.into_inner();
+                        let key = field_items.next().unwrap().as_str().to_string();
+                        if fields.iter().any(|(k, _)| *k == key) {
+                            Err(anyhow!("field[{key}] is already declared"))?;
+                        }
+                        let ty = Self::parse_type_expr(field_items.next().unwrap(), types)?;
+                        fields.push((key, ty));
+                    }
+                    FamlType::Struct((String::new(), fields))
+                }
+                Rule::type_optional => match ret {
+                    FamlType::Option(_) | FamlType::Any => ret,
+                    ty => FamlType::Option(Box::new(ty)),
+                },
+                _ => unreachable!(),
+            };
+        }
+        Ok(ret)
+    }
+
+    fn parse_block(
+        root: pest::iterators::Pair<'_, Rule>,
+        types: &HashMap<String, FamlType>,
+    ) -> anyhow::Result<Self> {
         let mut anno_if_expr = None;
         let mut head = vec![];
+        let mut head_type = None;
         let mut is_array_head = false;
-        let mut ret = HashMap::new();
+        let mut ret = IndexMap::new();
         for root_item in root.into_inner() {
             match root_item.as_rule() {
                 Rule::anno_if => {
                     anno_if_expr = Some(Self::parse_expr(root_item.into_inner().next().unwrap())?)
                 }
-                Rule::group_head => head = Self::parse_ids(root_item),
-                Rule::group_array_head => {
-                    head = Self::parse_ids(root_item);
-                    is_array_head = true;
+                Rule::group_head | Rule::group_array_head => {
+                    is_array_head = root_item.as_rule() == Rule::group_array_head;
+                    for head_item in root_item.into_inner() {
+                        match head_item.as_rule() {
+                            Rule::ids => head = Self::parse_ids(head_item),
+                            Rule::type_anno => {
+                                let type_expr = head_item.into_inner().next().unwrap();
+                                head_type = Some(Self::parse_type_expr(type_expr, types)?);
+                            }
+                            _ => unreachable!(),
+                        }
+                    }
                 }
                 Rule::assign_pair => {
-                    let (key, mut value) = Self::parse_assign_pair(root_item)?;
+                    let (key, mut value) =
+                        Self::parse_assign_pair(root_item, &head.join("."), types)?;
                     let mut keys: Vec<_> = key.split('.').map(|key| key.to_string()).collect();
                     while keys.len() > 1 {
-                        let mut tmp_map = HashMap::new();
+                        let mut tmp_map = IndexMap::new();
                         tmp_map
                             .entry(keys.remove(keys.len() - 1))
                             .or_insert(FamlExpr::new())
@@ -387,6 +1032,14 @@ impl FamlExpr {
             }
         }
         let mut ret = FamlExprImpl::Map(ret).to_expr();
+        if let Some(ty) = head_type {
+            ret = FamlExprImpl::TypeAnno(FamlExprTypeAnno {
+                ty,
+                path: head.join("."),
+                value: ret,
+            })
+            .to_expr();
+        }
         if is_array_head {
             ret = FamlExprImpl::Array(vec![ret]).to_expr();
         }
@@ -404,9 +1057,14 @@ impl FamlExpr {
         Ok(ret)
     }
 
-    fn parse_assign_pair(root: pest::iterators::Pair<'_, Rule>) -> anyhow::Result<(String, Self)> {
+    fn parse_assign_pair(
+        root: pest::iterators::Pair<'_, Rule>,
+        prefix: &str,
+        types: &HashMap<String, FamlType>,
+    ) -> anyhow::Result<(String, Self)> {
         let mut constraints = vec![];
         let mut anno_if_expr = None;
+        let mut value_type = None;
         let mut keys = vec![];
         let mut value = FamlExpr::new();
         for root_item in root.into_inner() {
@@ -422,6 +1080,10 @@ impl FamlExpr {
                     }
                 }
                 Rule::ids => keys = Self::parse_ids(root_item),
+                Rule::type_anno => {
+                    let type_expr = root_item.into_inner().next().unwrap();
+                    value_type = Some(Self::parse_type_expr(type_expr, types)?);
+                }
                 Rule::expr => value = Self::parse_expr(root_item)?,
                 _ => unreachable!(),
             }
@@ -433,12 +1095,23 @@ impl FamlExpr {
             })
             .to_expr();
         }
+        if let Some(ty) = value_type {
+            let path = match prefix.is_empty() {
+                true => keys.join("."),
+                false => format!("{prefix}.{}", keys.join(".")),
+            };
+            // literals are checked while parsing, others are checked while evaluating
+            if let FamlExprImpl::Value(val) = &value.base().expr {
+                ty.cast(val.clone(), &path)?;
+            }
+            value = FamlExprImpl::TypeAnno(FamlExprTypeAnno { ty, path, value }).to_expr();
+        }
         if !constraints.is_empty() {
             value = FamlExprImpl::ConstraintAnno(FamlExprConstraintAnno { constraints, value })
                 .to_expr();
         }
         while keys.len() > 1 {
-            let mut tmp_map = HashMap::new();
+            let mut tmp_map = IndexMap::new();
             tmp_map.insert(keys.remove(keys.len() - 1), value);
             value = FamlExprImpl::Map(tmp_map).to_expr();
         }
@@ -448,22 +1121,6 @@ impl FamlExpr {
     fn parse_expr(root: pest::iterators::Pair<'_, Rule>) -> anyhow::Result<Self> {
         let root_item = root.into_inner().next().unwrap();
         match root_item.as_rule() {
-            Rule::json_expr => {
-                let mut json_str = root_item.as_str();
-                json_str = json_str
-                    .trim_start_matches("json###")
-                    .trim_end_matches("###");
-                let root: serde_json::Value = serde_json::from_str(json_str)?;
-                Ok(FamlValue::Json(root).to_expr())
-            }
-            Rule::yaml_expr => {
-                let mut yaml_str = root_item.as_str();
-                yaml_str = yaml_str
-                    .trim_start_matches("yaml###")
-                    .trim_end_matches("###");
-                let root: serde_yaml::Value = serde_yaml::from_str(yaml_str)?;
-                Ok(FamlValue::Yaml(root).to_expr())
-            }
             Rule::op3_expr => Self::parse_op3_expr(root_item),
             Rule::weak_expr => Self::parse_weak_expr(root_item),
             _ => unreachable!(),
@@ -479,38 +1136,109 @@ impl FamlExpr {
         }
     }
 
+    fn parse_entry(root: pest::iterators::Pair<'_, Rule>) -> anyhow::Result<FamlExprEntry> {
+        let root_item = root.into_inner().next().unwrap();
+        Ok(match root_item.as_rule() {
+            Rule::expr => FamlExprEntry::Value(Self::parse_expr(root_item)?),
+            Rule::spread_entry => {
+                let expr = root_item.into_inner().next().unwrap();
+                FamlExprEntry::Spread(Self::parse_expr(expr)?)
+            }
+            Rule::array_if_entry | Rule::map_if_entry => {
+                let mut root_items = root_item.into_inner().skip(1);
+                let cond = Self::parse_weak_expr(root_items.next().unwrap())?;
+                let entry = Self::parse_entry(root_items.next().unwrap())?;
+                FamlExprEntry::If((cond, Box::new(entry)))
+            }
+            Rule::map_pair_entry => {
+                let mut root_items = root_item.into_inner();
+                let key = root_items.next().unwrap().into_inner().next().unwrap();
+                let key = match key.as_rule() {
+                    Rule::string_literal => FamlValue::String(key.as_str().into_base()).to_expr(),
+                    _ => Self::parse_expr(key)?,
+                };
+                FamlExprEntry::Pair((key, Self::parse_expr(root_items.next().unwrap())?))
+            }
+            Rule::map_assign_pair => {
+                let (key, value) = Self::parse_assign_pair(root_item, "", &HashMap::new())?;
+                FamlExprEntry::Pair((FamlValue::String(key).to_expr(), value))
+            }
+            _ => unreachable!(),
+        })
+    }
+
     fn parse_array_expr(root: pest::iterators::Pair<'_, Rule>) -> anyhow::Result<Self> {
-        let mut exprs = vec![];
+        let mut entries = vec![];
         for root_item in root.into_inner() {
             match root_item.as_rule() {
-                Rule::expr => exprs.push(Self::parse_expr(root_item)?),
-                Rule::exprs => return Self::parse_array_expr(root_item),
+                Rule::array_entry => entries.push(Self::parse_entry(root_item)?),
                 _ => unreachable!(),
             }
         }
+        if !entries
+            .iter()
+            .all(|entry| matches!(entry, FamlExprEntry::Value(_)))
+        {
+            return Ok(FamlExprImpl::ArrayEntries(entries).to_expr());
+        }
+        let exprs = entries
+            .into_iter()
+            .filter_map(|entry| match entry {
+                FamlExprEntry::Value(expr) => Some(expr),
+                _ => None,
+            })
+            .collect();
         Ok(FamlExprImpl::Array(exprs).to_expr())
     }
 
     fn parse_map_expr(root: pest::iterators::Pair<'_, Rule>) -> anyhow::Result<Self> {
-        let mut map = HashMap::new();
+        let mut entries = vec![];
         for root_item in root.into_inner() {
             match root_item.as_rule() {
-                Rule::map_assign_pair => {
-                    let (key, value) = Self::parse_assign_pair(root_item)?;
-                    map.insert(key, value);
-                }
+                Rule::map_entry => entries.push(Self::parse_entry(root_item)?),
                 _ => unreachable!(),
             }
         }
+        let is_static = entries.iter().all(|entry| match entry {
+            FamlExprEntry::Pair((key, _)) => {
+                matches!(key.base().expr, FamlExprImpl::Value(FamlValue::String(_)))
+            }
+            _ => false,
+        });
+        if !is_static {
+            return Ok(FamlExprImpl::MapEntries(entries).to_expr());
+        }
+        let mut map = IndexMap::new();
+        for entry in entries {
+            if let FamlExprEntry::Pair((key, value)) = entry {
+                map.insert(key.evaluate()?.as_str(), value);
+            }
+        }
         Ok(FamlExprImpl::Map(map).to_expr())
     }
 
+    fn parse_embedded_expr(root: pest::iterators::Pair<'_, Rule>) -> anyhow::Result<Self> {
+        let (lang, content) = root.as_str().split_at(4);
+        let content = content.trim_start_matches("###").trim_end_matches("###");
+        let value = match lang {
+            "json" => FamlValue::from_json(serde_json::from_str(content)?)?,
+            "yaml" => FamlValue::from_yaml(serde_yaml::from_str(content)?)?,
+            _ => FamlValue::from_toml(toml::Value::Table(content.parse()?))?,
+        };
+        Ok(value.to_expr())
+    }
+
     fn parse_strong_expr(root: pest::iterators::Pair<'_, Rule>) -> anyhow::Result<Self> {
         let root_item = root.into_inner().next().unwrap();
         match root_item.as_rule() {
+            Rule::json_expr | Rule::yaml_expr | Rule::toml_expr => {
+                Self::parse_embedded_expr(root_item)
+            }
             Rule::base_expr => Self::parse_base_expr(root_item),
             Rule::array_expr => Self::parse_array_expr(root_item),
             Rule::map_expr => Self::parse_map_expr(root_item),
+            Rule::match_expr => Self::parse_match_expr(root_item),
+            Rule::array_comp_expr | Rule::map_comp_expr => Self::parse_comp_expr(root_item),
             _ => unreachable!(),
         }
     }
@@ -541,6 +1269,14 @@ impl FamlExpr {
                         Rule::expr => {
                             return Ok(SuffixOp::AccessVar(FamlExpr::parse_expr(root_item)?));
                         }
+                        Rule::slice_expr => {
+                            return Ok(SuffixOp::AccessVar(FamlExpr::parse_slice_expr(root_item)?));
+                        }
+                        // `.name` after a literal or an index reads the key like `["name"]`
+                        Rule::id => {
+                            let key = FamlValue::String(root_item.as_str().to_string());
+                            return Ok(SuffixOp::AccessVar(FamlExprImpl::Value(key).to_expr()));
+                        }
                         _ => unreachable!(),
                     }
                 }
@@ -577,6 +1313,130 @@ impl FamlExpr {
         Ok(expr)
     }
 
+    fn parse_comp_expr(root: pest::iterators::Pair<'_, Rule>) -> anyhow::Result<Self> {
+        let mut exprs = vec![];
+        let mut comp = FamlExprComprehension {
+            key: None,
+            value: FamlExpr::new(),
+            names: vec![],
+            iter: FamlExpr::new(),
+            cond: FamlExpr::new(),
+        };
+        for root_item in root.into_inner() {
+            match root_item.as_rule() {
+                Rule::weak_expr => exprs.push(Self::parse_weak_expr(root_item)?),
+                Rule::expr => exprs.push(Self::parse_expr(root_item)?),
+                Rule::comp_clause => {
+                    let mut conds = vec![];
+                    for root_item1 in root_item.into_inner() {
+                        match root_item1.as_rule() {
+                            Rule::id => comp.names.push(root_item1.as_str().to_string()),
+                            Rule::weak_expr => conds.push(Self::parse_weak_expr(root_item1)?),
+                            Rule::kw_for | Rule::kw_if | Rule::op_in => (),
+                            _ => unreachable!(),
+                        }
+                    }
+                    comp.iter = conds.remove(0);
+                    if let Some(cond) = conds.pop() {
+                        comp.cond = cond;
+                    }
+                }
+                _ => unreachable!(),
+            }
+        }
+        comp.value = exprs.pop().unwrap();
+        comp.key = exprs.pop();
+        Ok(FamlExprImpl::Comprehension(comp).to_expr())
+    }
+
+    fn parse_match_expr(root: pest::iterators::Pair<'_, Rule>) -> anyhow::Result<Self> {
+        let mut value = FamlExpr::new();
+        let mut arms = vec![];
+        for root_item in root.into_inner() {
+            match root_item.as_rule() {
+                Rule::match_kw => (),
+                Rule::weak_expr => value = Self::parse_weak_expr(root_item)?,
+                Rule::match_arm => arms.push(Self::parse_match_arm(root_item)?),
+                _ => unreachable!(),
+            }
+        }
+        Ok(FamlExprImpl::Match(FamlExprMatch { value, arms }).to_expr())
+    }
+
+    fn parse_match_arm(root: pest::iterators::Pair<'_, Rule>) -> anyhow::Result<FamlExprMatchArm> {
+        let mut arm = FamlExprMatchArm {
+            patterns: vec![],
+            guard: FamlExpr::new(),
+            value: FamlExpr::new(),
+        };
+        for root_item in root.into_inner() {
+            match root_item.as_rule() {
+                Rule::match_pattern => arm.patterns.push(Self::parse_match_pattern(root_item)?),
+                Rule::match_guard => {
+                    let guard = root_item.into_inner().nth(1).unwrap();
+                    arm.guard = Self::parse_expr(guard)?;
+                }
+                Rule::expr => arm.value = Self::parse_expr(root_item)?,
+                _ => unreachable!(),
+            }
+        }
+        Ok(arm)
+    }
+
+    fn parse_match_pattern(
+        root: pest::iterators::Pair<'_, Rule>,
+    ) -> anyhow::Result<FamlExprMatchPattern> {
+        let mut exprs = vec![];
+        let mut inclusive = false;
+        for root_item in root.into_inner() {
+            match root_item.as_rule() {
+                Rule::middle_expr => exprs.push(Self::parse_middle_expr(root_item)?),
+                Rule::match_range_op => inclusive = root_item.as_str() == "..=",
+                _ => unreachable!(),
+            }
+        }
+        if exprs.len() == 2 {
+            let end = exprs.remove(1);
+            let start = exprs.remove(0);
+            return Ok(FamlExprMatchPattern::Range(FamlExprRange {
+                start,
+                end,
+                step: FamlExpr::new(),
+                inclusive,
+            }));
+        }
+        let expr = exprs.remove(0);
+        if let FamlExprImpl::TempName(names) = &expr.base().expr
+            && names.len() == 1
+        {
+            match &names[0][..] {
+                "_" => return Ok(FamlExprMatchPattern::Wildcard),
+                "null" | "nan" | "infinity" => (),
+                _ => return Ok(FamlExprMatchPattern::Binding(expr)),
+            }
+        }
+        Ok(FamlExprMatchPattern::Value(expr))
+    }
+
+    fn parse_slice_expr(root: pest::iterators::Pair<'_, Rule>) -> anyhow::Result<Self> {
+        let mut range = FamlExprRange {
+            start: FamlExpr::new(),
+            end: FamlExpr::new(),
+            step: FamlExpr::new(),
+            inclusive: false,
+        };
+        for root_item in root.into_inner() {
+            let expr = Self::parse_expr(root_item.clone().into_inner().next().unwrap())?;
+            match root_item.as_rule() {
+                Rule::slice_start => range.start = expr,
+                Rule::slice_end => range.end = expr,
+                Rule::slice_step => range.step = expr,
+                _ => unreachable!(),
+            }
+        }
+        Ok(FamlExprImpl::Range(range).to_expr())
+    }
+
     fn parse_weak_expr(root: pest::iterators::Pair<'_, Rule>) -> anyhow::Result<Self> {
         let mut exprs = vec![];
         let mut ops = vec![];
@@ -584,7 +1444,10 @@ impl FamlExpr {
         for root_item in root.into_inner() {
             match root_item.as_rule() {
                 Rule::middle_expr => exprs.push(Self::parse_middle_expr(root_item)?),
-                Rule::op2 => ops.push(root_item.as_str().to_string()),
+                Rule::op2 => {
+                    let op: Vec<_> = root_item.as_str().split_whitespace().collect();
+                    ops.push(op.join(" "))
+                }
                 _ => unreachable!(),
             }
         }
@@ -596,11 +1459,11 @@ impl FamlExpr {
             })
             .collect();
         //
-        for i in 0..10 {
+        for i in 0..11 {
             if exprs.len() == 1 {
                 break;
             }
-            if i == 5 {
+            if i == 6 {
                 let mut j = 1;
                 while j < ops.len() {
                     if ops[j - 1].1 == i && ops[j].1 == i {
@@ -627,7 +1490,16 @@ impl FamlExpr {
                 let left = exprs.remove(idx);
                 let right = exprs.remove(idx);
                 let op = ops.remove(idx).0;
-                let expr = FamlExprImpl::Op2((left, op, right)).to_expr();
+                let expr = match &op[..] {
+                    ".." | "..=" => FamlExprImpl::Range(FamlExprRange {
+                        start: left,
+                        end: right,
+                        step: FamlExpr::new(),
+                        inclusive: op == "..=",
+                    }),
+                    _ => FamlExprImpl::Op2((left, op, right)),
+                }
+                .to_expr();
                 exprs.insert(idx, expr);
                 // Don't increment idx since we've modified the vectors
             }
@@ -639,7 +1511,7 @@ impl FamlExpr {
         let mut exprs = vec![];
         for root_item in root.into_inner() {
             match root_item.as_rule() {
-                Rule::middle_expr => exprs.push(Self::parse_middle_expr(root_item)?),
+                Rule::expr => exprs.push(Self::parse_expr(root_item)?),
                 Rule::weak_expr => exprs.push(Self::parse_weak_expr(root_item)?),
                 _ => unreachable!(),
             }
@@ -682,13 +1554,28 @@ impl FamlExpr {
                 Rule::format_string_part1 => strs.push(root_item.as_str().into_base()),
                 Rule::format_string_part2 => strs.push(root_item.as_str().into_base()),
                 Rule::format_string_part3 => strs.push(root_item.as_str().into_base()),
-                Rule::expr => exprs.push(Self::parse_expr(root_item)?),
+                Rule::format_expr => exprs.push(Self::parse_format_expr(root_item)?),
                 _ => unreachable!(),
             }
         }
         Ok(FamlExprImpl::FormatString((strs, exprs)).to_expr())
     }
 
+    fn parse_format_expr(
+        root: pest::iterators::Pair<'_, Rule>,
+    ) -> anyhow::Result<(FamlExpr, FormatSpec)> {
+        let mut expr = FamlExprImpl::None.to_expr();
+        let mut spec = FormatSpec::default();
+        for root_item in root.into_inner() {
+            match root_item.as_rule() {
+                Rule::expr => expr = Self::parse_expr(root_item)?,
+                Rule::format_spec => spec = FormatSpec::parse(&root_item.as_str()[1..])?,
+                _ => unreachable!(),
+            }
+        }
+        Ok((expr, spec))
+    }
+
     fn parse_ids(root: pest::iterators::Pair<'_, Rule>) -> Vec<String> {
         let mut ret = vec![];
         for root_item in root.into_inner() {
@@ -731,6 +1618,18 @@ impl FamlExpr {
                 }
                 return Ok(());
             }
+            (FamlExprImpl::TypeAnno(anno), FamlExprImpl::TypeAnno(anno2)) => {
+                anno.ty = anno2.ty;
+                anno.path = anno2.path;
+                anno.value.apply(anno2.value)?;
+            }
+            (FamlExprImpl::TypeAnno(anno), val) => anno.value.apply(val.to_expr())?,
+            (self_, FamlExprImpl::TypeAnno(mut anno2)) => {
+                let mut value = std::mem::replace(self_, FamlExprImpl::None).to_expr();
+                value.apply(anno2.value)?;
+                anno2.value = value;
+                *self_ = FamlExprImpl::TypeAnno(anno2);
+            }
             (self_, val) => match self_ {
                 FamlExprImpl::None => *self_ = val,
                 FamlExprImpl::Array(arr) => {
@@ -759,7 +1658,7 @@ impl FamlExpr {
         Ok(())
     }
 
-    fn get_temp_name_expr(&self, names: &Vec<String>) -> anyhow::Result<FamlExpr> {
+    pub(crate) fn get_temp_name_expr(&self, names: &Vec<String>) -> anyhow::Result<FamlExpr> {
         let mut names: Vec<_> = names.iter().map(|p| &p[..]).collect();
         let mut expr = match names.first() {
             Some(&"nan") => {
@@ -806,15 +1705,40 @@ impl FamlExpr {
                 Ok(FamlValue::Array(ret))
             }
             FamlExprImpl::Map(map) => {
-                let mut ret = HashMap::new();
+                let mut ret = IndexMap::new();
                 for (key, item) in map.iter() {
                     ret.insert(key.clone(), item.evaluate()?);
                 }
                 Ok(FamlValue::Map(ret))
             }
+            FamlExprImpl::ArrayEntries(entries) => {
+                let mut ret = Vec::new();
+                for entry in entries.iter() {
+                    entry.eval_array(&mut ret)?;
+                }
+                Ok(FamlValue::Array(ret))
+            }
+            FamlExprImpl::MapEntries(entries) => {
+                let mut ret = IndexMap::new();
+                for entry in entries.iter() {
+                    entry.eval_map(&mut ret)?;
+                }
+                Ok(FamlValue::Map(ret))
+            }
             FamlExprImpl::TempName(names) => {
+                if let Some(val) = LocalScope::get(names) {
+                    return val;
+                }
+                if names.len() >= 2 && names[0] == "native" {
+                    let name = names[1..].join(".");
+                    return match self.resolve_native(&names[1..])? {
+                        Some(HostValue::Value(val)) => Ok(val),
+                        Some(HostValue::Object(_)) => Err(anyhow!("native[{name}] is an object")),
+                        None => Err(anyhow!("native object[{name}] not found")),
+                    };
+                }
                 let expr = self.get_temp_name_expr(names)?;
-                expr.evaluate()
+                LocalScope::isolate(|| expr.evaluate())
             }
             FamlExprImpl::Op1Prefix((op, a)) => {
                 let a = a.evaluate()?;
@@ -826,6 +1750,11 @@ impl FamlExpr {
             }
             FamlExprImpl::Op2((a, op, b)) => {
                 let a = a.evaluate()?;
+                if let FamlExprImpl::Range(range) = &b.base().expr
+                    && (op == "in" || op == "not in")
+                {
+                    return Ok(FamlValue::Bool(range.contains(&a)? == (op == "in")));
+                }
                 let b = b.evaluate()?;
                 Op2Evaluator::eval(a, &op, b)
             }
@@ -834,10 +1763,21 @@ impl FamlExpr {
                 Some(false) => c.evaluate(),
                 None => Err(anyhow!("bool expected"))?,
             },
+            FamlExprImpl::Range(range) => RangeEvaluator::expand(
+                range.start.evaluate()?,
+                range.end.evaluate()?,
+                range.step.evaluate()?,
+                range.inclusive,
+            ),
+            FamlExprImpl::Match(match_expr) => {
+                let (arm, vars) = match_expr.select()?;
+                LocalScope::with(vars, || arm.value.evaluate())
+            }
+            FamlExprImpl::Comprehension(comp) => comp.evaluate(),
             FamlExprImpl::FormatString((strs, exprs)) => {
                 let mut str_exprs = vec![];
-                for expr in exprs {
-                    str_exprs.push(expr.evaluate()?.as_str());
+                for (expr, spec) in exprs {
+                    str_exprs.push(spec.format(&expr.evaluate()?)?);
                 }
                 str_exprs.push("".to_string());
                 let mut ret = "".to_string();
@@ -848,14 +1788,17 @@ impl FamlExpr {
                 Ok(FamlValue::String(ret))
             }
             FamlExprImpl::AccessVar((expr, arg)) => {
-                let expr = expr.evaluate()?;
+                let expr = expr.evaluate()?.normalize();
+                if let FamlExprImpl::Range(range) = &arg.base().expr {
+                    return range.slice(&expr);
+                }
                 let arg = arg.evaluate()?;
                 let val = match arg {
-                    FamlValue::Int64(i) => expr.get_at(i as usize),
-                    FamlValue::String(s) => expr.get(&s),
+                    FamlValue::Int64(i) => IndexEvaluator::index(&expr, i),
+                    FamlValue::String(s) => expr.get(&s).cloned(),
                     _ => Err(anyhow!("unexpected index type"))?,
                 };
-                Ok(val.cloned().unwrap_or(FamlValue::None))
+                Ok(val.unwrap_or(FamlValue::None))
             }
             FamlExprImpl::InvokeFunc((expr, args)) => {
                 if let FamlExprImpl::TempName(names) = &expr.base().expr {
@@ -864,20 +1807,48 @@ impl FamlExpr {
                         arg_vals.push(arg.evaluate()?);
                     }
                     //
-                    if names.len() == 2 && names[0] == "native" {
-                        let func = Native::get_func(&names[1])
-                            .ok_or_else(|| anyhow!("native func not found"))?;
-                        Ok(func.call(arg_vals))
+                    if names.len() >= 2 && names[0] == "native" {
+                        let name = names[1..].join(".");
+                        if let Some(func) = expr.find_func(&name) {
+                            return AsyncCalls::call_sync(
+                                self.id(),
+                                &name,
+                                func.as_ref(),
+                                arg_vals,
+                            );
+                        }
+                        if let Some(func) = expr.find_async_func(&name) {
+                            return AsyncCalls::call(self.id(), &name, func.as_ref(), arg_vals);
+                        }
+                        // methods of host objects, or methods of values read from them
+                        let method = &names[names.len() - 1];
+                        let not_found = || anyhow!("native func[{name}] not found");
+                        match expr.resolve_native(&names[1..names.len() - 1])? {
+                            Some(HostValue::Object(obj)) => obj
+                                .call(method, arg_vals)
+                                .map_err(|err| anyhow!("native func[{name}]: {err}"))?
+                                .ok_or_else(not_found),
+                            Some(HostValue::Value(mut val)) => {
+                                val.invoke_with(method, &arg_vals, |ty, name| {
+                                    expr.find_method(ty, name)
+                                })
+                            }
+                            None => Err(not_found()),
+                        }
                     } else {
                         let mut names = names.clone();
                         let func = names.pop().ok_or_else(|| anyhow!("func name expected"))?;
-                        let mut obj_val = {
+                        let mut obj_val = if names.is_empty() {
+                            // bare calls in `@constraint` are invoked on the constrained value
+                            LocalScope::get(&["value".to_string()])
+                                .ok_or_else(|| anyhow!("func[{func}] not found"))??
+                        } else {
                             let mut obj_expr = FamlExprImpl::TempName(names).to_expr();
                             obj_expr.base_mut().base_expr = expr.base().base_expr.clone();
                             obj_expr.base_mut().super_expr = expr.base().super_expr.clone();
                             obj_expr.evaluate()?
                         };
-                        obj_val.invoke(&func, &arg_vals)
+                        obj_val.invoke_with(&func, &arg_vals, |ty, name| expr.find_method(ty, name))
                     }
                 } else {
                     Err(anyhow!("unsupported invoke type"))?
@@ -891,8 +1862,27 @@ impl FamlExpr {
                 }
                 return if_anno.default_value.evaluate();
             }
-            FamlExprImpl::ConstraintAnno(cst_anno) => cst_anno.value.evaluate(),
+            FamlExprImpl::ConstraintAnno(cst_anno) => {
+                let value = cst_anno.value.evaluate()?;
+                cst_anno.check(&value)?;
+                Ok(value)
+            }
+            FamlExprImpl::TypeAnno(type_anno) => {
+                let value = type_anno.value.evaluate()?;
+                type_anno.ty.cast(value, &self.type_anno_path(type_anno))
+            }
+        }
+    }
+
+    /// Groups of `[[name: Type]]` are reported with the index of the element.
+    fn type_anno_path(&self, type_anno: &FamlExprTypeAnno) -> String {
+        if let Ok(parent) = self.base().super_expr.upgrade()
+            && let FamlExprImpl::Array(items) = &parent.base().expr
+            && let Some(idx) = items.iter().position(|item| item.id() == self.id())
+        {
+            return format!("{}[{idx}]", type_anno.path);
         }
+        type_anno.path.clone()
     }
 
     fn trace_internal(
@@ -914,8 +1904,8 @@ impl FamlExpr {
                 (FamlValue::Array(vals), format!("[{}]", vstrs.join(", ")))
             }
             FamlExprImpl::Map(map) => {
-                let mut vals = HashMap::new();
-                let mut vstrs = HashMap::new();
+                let mut vals = IndexMap::new();
+                let mut vstrs = IndexMap::new();
                 for (key, expr) in map {
                     let (val, vstr) = expr.trace_internal(false, maps)?;
                     vals.insert(key.clone(), val);
@@ -927,10 +1917,30 @@ impl FamlExpr {
                     .collect();
                 (FamlValue::Map(vals), format!("{{{}}}", vstrs.join(", ")))
             }
+            FamlExprImpl::ArrayEntries(entries) => {
+                let mut vstrs = vec![];
+                for entry in entries {
+                    vstrs.push(entry.trace_str(maps)?);
+                }
+                (self.evaluate()?, format!("[{}]", vstrs.join(", ")))
+            }
+            FamlExprImpl::MapEntries(entries) => {
+                let mut vstrs = vec![];
+                for entry in entries {
+                    vstrs.push(entry.trace_str(maps)?);
+                }
+                (self.evaluate()?, format!("{{{}}}", vstrs.join(", ")))
+            }
             FamlExprImpl::TempName(items) => {
-                let expr = self.get_temp_name_expr(items)?;
                 let name = items.join(".");
-                let (val, vstr) = expr.trace_internal(false, maps)?;
+                if let Some(val) = LocalScope::get(items) {
+                    return Ok((val?, name));
+                }
+                if items.len() >= 2 && items[0] == "native" {
+                    return Ok((self.evaluate()?, name));
+                }
+                let expr = self.get_temp_name_expr(items)?;
+                let (val, vstr) = LocalScope::isolate(|| expr.trace_internal(false, maps))?;
                 maps.insert(name.clone(), (val.clone(), vstr));
                 (val, name)
             }
@@ -950,8 +1960,19 @@ impl FamlExpr {
             }
             FamlExprImpl::Op2((a, op, b)) => {
                 let (val_a, vstr_a) = a.trace_internal(true, maps)?;
-                let (val_b, vstr_b) = b.trace_internal(true, maps)?;
-                let val = Op2Evaluator::eval(val_a, &op, val_b)?;
+                let (val, vstr_b) = match &b.base().expr {
+                    FamlExprImpl::Range(range) if op == "in" || op == "not in" => {
+                        let contains = range.contains(&val_a)?;
+                        (
+                            FamlValue::Bool(contains == (op == "in")),
+                            range.trace_str(maps)?,
+                        )
+                    }
+                    _ => {
+                        let (val_b, vstr_b) = b.trace_internal(true, maps)?;
+                        (Op2Evaluator::eval(val_a, &op, val_b)?, vstr_b)
+                    }
+                };
                 let mut vstr = format!("{vstr_a} {op} {vstr_b}");
                 vstr = if atom_str { format!("({vstr})") } else { vstr };
                 (val, vstr)
@@ -966,6 +1987,30 @@ impl FamlExpr {
                 vstr = if atom_str { format!("({vstr})") } else { vstr };
                 (val, vstr)
             }
+            FamlExprImpl::Range(range) => {
+                let mut vstr = range.trace_str(maps)?;
+                vstr = if atom_str { format!("({vstr})") } else { vstr };
+                (self.evaluate()?, vstr)
+            }
+            FamlExprImpl::Match(match_expr) => {
+                let (_, vstr_value) = match_expr.value.trace_internal(true, maps)?;
+                let (arm, vars) = match_expr.select()?;
+                let mut patterns = vec![];
+                for pattern in &arm.patterns {
+                    patterns.push(pattern.trace_str(maps)?);
+                }
+                let (val, vstr_arm) = LocalScope::with(vars.clone(), || {
+                    let mut vstr_arm = patterns.join(" | ");
+                    if !arm.guard.is_none() {
+                        let (_, vstr_guard) = arm.guard.trace_internal(false, maps)?;
+                        vstr_arm = format!("{vstr_arm} if {vstr_guard}");
+                    }
+                    let (val, vstr_val) = arm.value.trace_internal(false, maps)?;
+                    anyhow::Ok((val, format!("{vstr_arm} => {vstr_val}")))
+                })?;
+                (val, format!("match {vstr_value} {{ {vstr_arm} }}"))
+            }
+            FamlExprImpl::Comprehension(comp) => (comp.evaluate()?, comp.trace_str(maps)?),
             FamlExprImpl::FormatString((strs, exprs)) => {
                 if strs.len() == 1 && exprs.is_empty() {
                     let vstr = format!("$\"{}\"", strs[0].escape(true));
@@ -973,10 +2018,13 @@ impl FamlExpr {
                 } else {
                     let mut rval = "".to_string();
                     let mut rvstr = "".to_string();
-                    for (idx, expr) in exprs.iter().enumerate() {
-                        let (val, vstr) = expr.trace_internal(false, maps)?;
+                    for (idx, (expr, spec)) in exprs.iter().enumerate() {
+                        let (val, mut vstr) = expr.trace_internal(false, maps)?;
                         rval.push_str(&strs[idx]);
-                        rval.push_str(&val.as_str());
+                        rval.push_str(&spec.format(&val)?);
+                        if !spec.is_empty() {
+                            vstr = format!("{vstr}:{spec}");
+                        }
                         let vprefix = if idx == 0 { "$\"" } else { "}" };
                         let cur_str = strs[idx].escape(true);
                         rvstr.push_str(&format!("{vprefix}{cur_str}{{{vstr}"));
@@ -989,8 +2037,21 @@ impl FamlExpr {
             FamlExprImpl::AccessVar((a, b)) => {
                 let val = self.evaluate()?;
                 let (_, a) = a.trace_internal(true, maps)?;
-                let (_, b) = b.trace_internal(true, maps)?;
-                (val, format!("{a}.{b}"))
+                match &b.base().expr {
+                    FamlExprImpl::Range(range) if range.inclusive => {
+                        (val, format!("{a}[{}]", range.trace_str(maps)?))
+                    }
+                    FamlExprImpl::Range(range) => match range.trace_parts(maps)? {
+                        [start, end, step] if step.is_empty() => {
+                            (val, format!("{a}[{start}:{end}]"))
+                        }
+                        [start, end, step] => (val, format!("{a}[{start}:{end}:{step}]")),
+                    },
+                    _ => {
+                        let (_, b) = b.trace_internal(true, maps)?;
+                        (val, format!("{a}.{b}"))
+                    }
+                }
             }
             FamlExprImpl::InvokeFunc((expr, args)) => {
                 if let FamlExprImpl::TempName(names) = &expr.base().expr {
@@ -1020,9 +2081,17 @@ impl FamlExpr {
             FamlExprImpl::ConstraintAnno(cst_anno) => {
                 cst_anno.value.trace_internal(atom_str, maps)?
             }
+            FamlExprImpl::TypeAnno(type_anno) => {
+                let (_, vstr) = type_anno.value.trace_internal(atom_str, maps)?;
+                (self.evaluate()?, vstr)
+            }
         })
     }
 
+    pub(crate) fn trace_expr_str(&self) -> anyhow::Result<String> {
+        Ok(self.trace_internal(false, &mut HashMap::new())?.1)
+    }
+
     pub fn trace(&self, name: &str) -> anyhow::Result<String> {
         let format_vstr = |name: &str, val: FamlValue, vstr: &str| {
             let val_str = val.as_str();
@@ -1044,31 +2113,32 @@ impl FamlExpr {
     }
 
     pub fn set_null(&mut self) {
-        self.base_mut().expr.set_null();
+        self.skip_type_anno_mut().base_mut().expr.set_null();
     }
 
     pub fn set_bool(&mut self, val: bool) {
-        self.base_mut().expr.set_bool(val);
+        self.skip_type_anno_mut().base_mut().expr.set_bool(val);
     }
 
     pub fn set_int(&mut self, val: i64) {
-        self.base_mut().expr.set_int(val);
+        self.skip_type_anno_mut().base_mut().expr.set_int(val);
     }
 
     pub fn set_float(&mut self, val: f64) {
-        self.base_mut().expr.set_float(val);
+        self.skip_type_anno_mut().base_mut().expr.set_float(val);
     }
 
     pub fn set_string(&mut self, val: impl Into<String>) {
-        self.base_mut().expr.set_string(val);
+        self.skip_type_anno_mut().base_mut().expr.set_string(val);
     }
 
     pub fn set_value(&mut self, val: FamlValue) {
-        self.base_mut().expr = FamlExprImpl::Value(val);
+        self.skip_type_anno_mut().base_mut().expr = FamlExprImpl::Value(val);
     }
 
+    /// Deserialize without evaluating the fields `T` doesn't ask for.
     pub fn deserialize<T: for<'a> Deserialize<'a>>(&self) -> anyhow::Result<T> {
-        Ok(serde_json::from_value(self.evaluate()?.to_json())?)
+        Ok(T::deserialize(self)?)
     }
 
     pub fn init_weak_expr(&mut self, base_expr: WeakFamlExpr, super_expr: WeakFamlExpr) {
@@ -1100,6 +2170,11 @@ impl FamlExprBase {
                     item.init_weak_expr(base_expr.clone(), self_expr.clone());
                 }
             }
+            FamlExprImpl::ArrayEntries(entries) | FamlExprImpl::MapEntries(entries) => {
+                for entry in entries {
+                    entry.init_weak_expr(base_expr.clone(), self_expr.clone());
+                }
+            }
             FamlExprImpl::TempName(_) => (),
             FamlExprImpl::Op1Prefix((_, expr)) => {
                 expr.init_weak_expr(base_expr.clone(), super_expr.clone());
@@ -1117,12 +2192,22 @@ impl FamlExprBase {
                 expr3.init_weak_expr(base_expr.clone(), super_expr.clone());
             }
             FamlExprImpl::FormatString((_, exprs)) => {
-                for expr in exprs {
+                for (expr, _) in exprs {
                     expr.init_weak_expr(base_expr.clone(), super_expr.clone());
                 }
             }
-            FamlExprImpl::AccessVar((expr, _)) => {
+            FamlExprImpl::Range(range) => {
+                range.init_weak_expr(base_expr.clone(), super_expr.clone());
+            }
+            FamlExprImpl::Match(match_expr) => {
+                match_expr.init_weak_expr(base_expr.clone(), super_expr.clone());
+            }
+            FamlExprImpl::Comprehension(comp) => {
+                comp.init_weak_expr(base_expr.clone(), super_expr.clone());
+            }
+            FamlExprImpl::AccessVar((expr, arg)) => {
                 expr.init_weak_expr(base_expr.clone(), super_expr.clone());
+                arg.init_weak_expr(base_expr.clone(), super_expr.clone());
             }
             FamlExprImpl::InvokeFunc((expr, args)) => {
                 expr.init_weak_expr(base_expr.clone(), super_expr.clone());
@@ -1134,9 +2219,10 @@ impl FamlExprBase {
                 if_anno.init_weak_expr(base_expr.clone(), super_expr.clone());
             }
             FamlExprImpl::ConstraintAnno(cst_anno) => {
-                cst_anno
-                    .value
-                    .init_weak_expr(base_expr.clone(), super_expr.clone());
+                cst_anno.init_weak_expr(base_expr.clone(), super_expr.clone());
+            }
+            FamlExprImpl::TypeAnno(type_anno) => {
+                type_anno.init_weak_expr(base_expr.clone(), super_expr.clone());
             }
         }
     }
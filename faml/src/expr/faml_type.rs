@@ -0,0 +1,144 @@
+use super::faml_value::FamlValue;
+use anyhow::anyhow;
+use indexmap::IndexMap;
+use std::fmt;
+
+/// Type declared with `type Name = ...`, or annotated on fields and groups like `port: int = 80`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FamlType {
+    Bool,
+    Int,
+    Float,
+    String,
+    Duration,
+    Distance,
+    Any,
+    Option(Box<FamlType>),
+    Array(Box<FamlType>),
+    /// Struct name and fields in declaration order, the name is empty for inline structs.
+    Struct((String, Vec<(String, FamlType)>)),
+    /// Enum name and string variants, declared with `enum Name { a, b }`.
+    Enum((String, Vec<String>)),
+}
+
+impl FamlType {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "bool" => FamlType::Bool,
+            "int" => FamlType::Int,
+            "float" => FamlType::Float,
+            "string" => FamlType::String,
+            "duration" => FamlType::Duration,
+            "distance" => FamlType::Distance,
+            "any" => FamlType::Any,
+            _ => return None,
+        })
+    }
+
+    pub fn is_optional(&self) -> bool {
+        matches!(self, FamlType::Option(_) | FamlType::Any)
+    }
+
+    /// Check the value against the type, `int` values are converted for `float` types.
+    pub fn cast(&self, value: FamlValue, path: &str) -> anyhow::Result<FamlValue> {
+        Ok(match (self, value) {
+            (FamlType::Any, value) => value,
+            (FamlType::Option(_), FamlValue::None) => FamlValue::None,
+            (FamlType::Option(ty), value) => ty.cast(value, path)?,
+            (ty, FamlValue::Json(root)) => ty.cast(FamlValue::from_json(root)?, path)?,
+            (ty, FamlValue::Yaml(root)) => ty.cast(FamlValue::from_yaml(root)?, path)?,
+            (FamlType::Bool, value @ FamlValue::Bool(_)) => value,
+            (FamlType::Int, value @ FamlValue::Int64(_)) => value,
+            (FamlType::Float, FamlValue::Int64(n)) => FamlValue::Float64(n as f64),
+            (FamlType::Float, value @ FamlValue::Float64(_)) => value,
+            (FamlType::String, value @ FamlValue::String(_)) => value,
+            (FamlType::Duration, value @ FamlValue::Duration(_)) => value,
+            (FamlType::Distance, value @ FamlValue::Distance(_)) => value,
+            (FamlType::Enum((name, variants)), FamlValue::String(s)) => {
+                if !variants.contains(&s) {
+                    let hint = match Self::nearest_variant(&s, variants) {
+                        Some(variant) => format!("did you mean \"{variant}\"?"),
+                        None => format!("expected one of [{}]", variants.join(", ")),
+                    };
+                    Err(anyhow!(
+                        "{path}: value[\"{s}\"] is not a variant of enum[{name}], {hint}"
+                    ))?;
+                }
+                FamlValue::String(s)
+            }
+            (FamlType::Array(ty), FamlValue::Array(items)) => FamlValue::Array(
+                items
+                    .into_iter()
+                    .enumerate()
+                    .map(|(idx, item)| ty.cast(item, &format!("{path}[{idx}]")))
+                    .collect::<anyhow::Result<_>>()?,
+            ),
+            (FamlType::Struct((_, fields)), FamlValue::Map(mut map)) => {
+                let mut ret = IndexMap::new();
+                for (key, ty) in fields {
+                    let field_path = match path.is_empty() {
+                        true => key.clone(),
+                        false => format!("{path}.{key}"),
+                    };
+                    match map.shift_remove(key) {
+                        Some(value) => _ = ret.insert(key.clone(), ty.cast(value, &field_path)?),
+                        None if ty.is_optional() => (),
+                        None => Err(anyhow!("{field_path}: missing field of type[{ty}]"))?,
+                    }
+                }
+                if let Some(key) = map.keys().min() {
+                    Err(anyhow!("{path}: unknown field[{key}] for type[{self}]"))?;
+                }
+                FamlValue::Map(ret)
+            }
+            (ty, value) => Err(anyhow!(
+                "{path}: expected type[{ty}], got value[{}]",
+                value.as_print_str()
+            ))?,
+        })
+    }
+
+    fn nearest_variant<'a>(value: &str, variants: &'a [String]) -> Option<&'a String> {
+        let (variant, dist) = variants
+            .iter()
+            .map(|variant| (variant, Self::edit_distance(value, variant)))
+            .min_by_key(|(_, dist)| *dist)?;
+        (dist <= (value.chars().count() / 3).max(2)).then_some(variant)
+    }
+
+    fn edit_distance(a: &str, b: &str) -> usize {
+        let b: Vec<_> = b.chars().collect();
+        let mut prev: Vec<_> = (0..=b.len()).collect();
+        for (i, ca) in a.chars().enumerate() {
+            let mut cur = vec![i + 1];
+            for (j, cb) in b.iter().enumerate() {
+                let cost = if ca == *cb { 0 } else { 1 };
+                cur.push((prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1));
+            }
+            prev = cur;
+        }
+        prev[b.len()]
+    }
+}
+
+impl fmt::Display for FamlType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FamlType::Bool => write!(f, "bool"),
+            FamlType::Int => write!(f, "int"),
+            FamlType::Float => write!(f, "float"),
+            FamlType::String => write!(f, "string"),
+            FamlType::Duration => write!(f, "duration"),
+            FamlType::Distance => write!(f, "distance"),
+            FamlType::Any => write!(f, "any"),
+            FamlType::Option(ty) => write!(f, "{ty}?"),
+            FamlType::Array(ty) => write!(f, "[{ty}]"),
+            FamlType::Struct((name, _)) if !name.is_empty() => write!(f, "{name}"),
+            FamlType::Enum((name, _)) => write!(f, "{name}"),
+            FamlType::Struct((_, fields)) => {
+                let fields: Vec<_> = fields.iter().map(|(k, ty)| format!("{k}: {ty}")).collect();
+                write!(f, "{{ {} }}", fields.join(", "))
+            }
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use crate::expr::invoke::DurationExt;
 use crate::string_utils::IntoBaseExt;
 use crate::{FamlExpr, FamlExprImpl};
+use anyhow::anyhow;
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::ops::{Index, IndexMut};
@@ -14,7 +16,7 @@ pub enum FamlValue {
     Float64(f64),
     String(String),
     Array(Vec<FamlValue>),
-    Map(HashMap<String, FamlValue>),
+    Map(IndexMap<String, FamlValue>),
     Duration(Duration),
     Distance(Distance),
     Json(serde_json::Value),
@@ -43,6 +45,23 @@ impl Serialize for FamlValue {
 }
 
 impl FamlValue {
+    /// Name of the value type, as used by `Native::add_method`.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            FamlValue::None => "null",
+            FamlValue::Bool(_) => "bool",
+            FamlValue::Int64(_) => "int",
+            FamlValue::Float64(_) => "float",
+            FamlValue::String(_) => "string",
+            FamlValue::Array(_) => "array",
+            FamlValue::Map(_) => "map",
+            FamlValue::Duration(_) => "duration",
+            FamlValue::Distance(_) => "distance",
+            FamlValue::Json(_) => "json",
+            FamlValue::Yaml(_) => "yaml",
+        }
+    }
+
     pub fn to_expr(self) -> FamlExpr {
         FamlExprImpl::Value(match self {
             FamlValue::None => FamlValue::None,
@@ -58,7 +77,7 @@ impl FamlValue {
                 return FamlExprImpl::Array(ret).to_expr();
             }
             FamlValue::Map(map) => {
-                let mut ret = HashMap::new();
+                let mut ret = IndexMap::new();
                 for (key, val) in map {
                     ret.insert(key, val.to_expr());
                 }
@@ -66,12 +85,27 @@ impl FamlValue {
             }
             FamlValue::Duration(dur) => FamlValue::Duration(dur),
             FamlValue::Distance(dist) => FamlValue::Distance(dist),
-            FamlValue::Json(root) => FamlValue::Json(root),
-            FamlValue::Yaml(root) => FamlValue::Yaml(root),
+            FamlValue::Json(root) => match Self::from_json(root.clone()) {
+                Ok(value) => return value.to_expr(),
+                Err(_) => FamlValue::Json(root),
+            },
+            FamlValue::Yaml(root) => match Self::from_yaml(root.clone()) {
+                Ok(value) => return value.to_expr(),
+                Err(_) => FamlValue::Yaml(root),
+            },
         })
         .to_expr()
     }
 
+    /// converts json and yaml values into native arrays, maps and scalars
+    pub fn normalize(self) -> Self {
+        match self {
+            FamlValue::Json(root) => Self::from_json(root.clone()).unwrap_or(FamlValue::Json(root)),
+            FamlValue::Yaml(root) => Self::from_yaml(root.clone()).unwrap_or(FamlValue::Yaml(root)),
+            value => value,
+        }
+    }
+
     pub fn is_none(&self) -> bool {
         match self {
             FamlValue::None => true,
@@ -212,7 +246,7 @@ impl FamlValue {
         }
     }
 
-    pub fn as_map(&self) -> Option<HashMap<String, FamlValue>> {
+    pub fn as_map(&self) -> Option<IndexMap<String, FamlValue>> {
         match self {
             FamlValue::Map(map) => Some(map.clone()),
             _ => None,
@@ -286,7 +320,7 @@ impl FamlValue {
                 Ok(FamlValue::Array(ret))
             }
             serde_json::Value::Object(map) => {
-                let mut ret = HashMap::new();
+                let mut ret = IndexMap::new();
                 for (k, v) in map {
                     ret.insert(k, FamlValue::from_json(v)?);
                 }
@@ -348,12 +382,9 @@ impl FamlValue {
                 Ok(FamlValue::Array(ret))
             }
             serde_yaml::Value::Mapping(map) => {
-                let mut ret = HashMap::new();
+                let mut ret = IndexMap::new();
                 for (k, v) in map {
-                    ret.insert(
-                        k.as_str().unwrap_or("").to_string(),
-                        FamlValue::from_yaml(v)?,
-                    );
+                    ret.insert(Self::yaml_key(k)?, FamlValue::from_yaml(v)?);
                 }
                 Ok(FamlValue::Map(ret))
             }
@@ -361,8 +392,46 @@ impl FamlValue {
         }
     }
 
+    /// Scalar keys like `1` or `true` are converted into strings, maps only have string keys.
+    fn yaml_key(key: serde_yaml::Value) -> anyhow::Result<String> {
+        match key {
+            serde_yaml::Value::String(s) => Ok(s),
+            serde_yaml::Value::Number(n) => Ok(n.to_string()),
+            serde_yaml::Value::Bool(b) => Ok(b.to_string()),
+            serde_yaml::Value::Null => Ok("null".to_string()),
+            serde_yaml::Value::Tagged(tag) => Self::yaml_key(tag.value),
+            serde_yaml::Value::Sequence(_) | serde_yaml::Value::Mapping(_) => {
+                Err(anyhow::anyhow!("only scalar yaml keys are supported"))
+            }
+        }
+    }
+
+    pub fn from_toml(root: toml::Value) -> anyhow::Result<Self> {
+        match root {
+            toml::Value::String(s) => Ok(FamlValue::String(s)),
+            toml::Value::Integer(i) => Ok(FamlValue::Int64(i)),
+            toml::Value::Float(f) => Ok(FamlValue::Float64(f)),
+            toml::Value::Boolean(b) => Ok(FamlValue::Bool(b)),
+            toml::Value::Datetime(dt) => Ok(FamlValue::String(dt.to_string())),
+            toml::Value::Array(arr) => {
+                let mut ret = vec![];
+                for val in arr {
+                    ret.push(FamlValue::from_toml(val)?);
+                }
+                Ok(FamlValue::Array(ret))
+            }
+            toml::Value::Table(map) => {
+                let mut ret = IndexMap::new();
+                for (k, v) in map {
+                    ret.insert(k, FamlValue::from_toml(v)?);
+                }
+                Ok(FamlValue::Map(ret))
+            }
+        }
+    }
+
     pub fn deserialize<T: for<'a> Deserialize<'a>>(&self) -> anyhow::Result<T> {
-        Ok(serde_json::from_value(self.to_json())?)
+        Ok(T::deserialize(self.clone())?)
     }
 }
 
@@ -419,7 +488,7 @@ impl IndexMut<&str> for FamlValue {
             return self;
         } else {
             if !self.is_map() {
-                *self = FamlValue::Map(HashMap::new());
+                *self = FamlValue::Map(IndexMap::new());
             }
             if let FamlValue::Map(map) = self {
                 if map.get(index).is_none() {
@@ -490,6 +559,7 @@ impl FamlValue {
                 let num: usize = num.parse().unwrap();
                 if let Some(obj) = obj_ref.get_at_mut(num) {
                     obj_ref = obj;
+                    continue;
                 } else {
                     return None;
                 }
@@ -512,6 +582,7 @@ impl FamlValue {
                 let num: usize = num.parse().unwrap();
                 if let Some(obj) = obj_ref.get_at(num) {
                     obj_ref = obj;
+                    continue;
                 } else {
                     return None;
                 }
@@ -530,7 +601,7 @@ pub trait ApplyExt {
     fn apply(&mut self, val: Self);
 }
 
-impl ApplyExt for HashMap<String, FamlValue> {
+impl ApplyExt for IndexMap<String, FamlValue> {
     fn apply(&mut self, val: Self) {
         for (key, val) in val.into_iter() {
             if let Some(self_k) = self.get_mut(&key) {
@@ -606,38 +677,160 @@ impl Into<FamlValue> for Distance {
     }
 }
 
-impl From<FamlValue> for bool {
-    fn from(value: FamlValue) -> Self {
+fn type_error(ty: &str, value: &FamlValue) -> anyhow::Error {
+    anyhow!("expected type[{ty}], got value[{}]", value.as_print_str())
+}
+
+impl TryFrom<FamlValue> for bool {
+    type Error = anyhow::Error;
+
+    fn try_from(value: FamlValue) -> anyhow::Result<Self> {
         match value {
-            FamlValue::Bool(b) => b,
-            _ => false,
+            FamlValue::Bool(b) => Ok(b),
+            value => Err(type_error("bool", &value)),
+        }
+    }
+}
+
+impl TryFrom<FamlValue> for i64 {
+    type Error = anyhow::Error;
+
+    fn try_from(value: FamlValue) -> anyhow::Result<Self> {
+        match value {
+            FamlValue::Int64(i) => Ok(i),
+            FamlValue::Float64(f) if f.fract() == 0.0 && f.abs() < i64::MAX as f64 => Ok(f as i64),
+            value => Err(type_error("int", &value)),
+        }
+    }
+}
+
+macro_rules! impl_try_from_int {
+    ($($ty:ident),*) => {
+        $(
+            impl TryFrom<FamlValue> for $ty {
+                type Error = anyhow::Error;
+
+                fn try_from(value: FamlValue) -> anyhow::Result<Self> {
+                    let n = i64::try_from(value)?;
+                    $ty::try_from(n)
+                        .map_err(|_| anyhow!("value[{n}] is out of range for type[{}]", stringify!($ty)))
+                }
+            }
+        )*
+    };
+}
+
+impl_try_from_int!(i8, i16, i32, u8, u16, u32, u64, usize);
+
+/// Distances are converted to meters.
+impl TryFrom<FamlValue> for f64 {
+    type Error = anyhow::Error;
+
+    fn try_from(value: FamlValue) -> anyhow::Result<Self> {
+        match value {
+            FamlValue::Float64(f) => Ok(f),
+            FamlValue::Int64(i) => Ok(i as f64),
+            FamlValue::Distance(dist) => Ok(dist.to_meters()),
+            value => Err(type_error("float", &value)),
+        }
+    }
+}
+
+impl TryFrom<FamlValue> for Duration {
+    type Error = anyhow::Error;
+
+    fn try_from(value: FamlValue) -> anyhow::Result<Self> {
+        match value {
+            FamlValue::Duration(dur) => Ok(dur),
+            value => Err(type_error("duration", &value)),
         }
     }
 }
 
-impl From<FamlValue> for i64 {
-    fn from(value: FamlValue) -> Self {
+impl TryFrom<FamlValue> for Distance {
+    type Error = anyhow::Error;
+
+    fn try_from(value: FamlValue) -> anyhow::Result<Self> {
         match value {
-            FamlValue::Float64(f) => f.round() as i64,
-            FamlValue::Int64(i) => i,
-            _ => 0,
+            FamlValue::Distance(dist) => Ok(dist),
+            value => Err(type_error("distance", &value)),
         }
     }
 }
 
-impl From<FamlValue> for f64 {
-    fn from(value: FamlValue) -> Self {
+fn try_from_option<T: TryFrom<FamlValue, Error: Into<anyhow::Error>>>(
+    value: FamlValue,
+) -> anyhow::Result<Option<T>> {
+    match value {
+        FamlValue::None => Ok(None),
+        value => Ok(Some(T::try_from(value).map_err(Into::into)?)),
+    }
+}
+
+// a generic `Option<T>` impl overlaps with `From<T> for Option<T>` when `T` is `FamlValue`
+macro_rules! impl_try_from_option {
+    ($($ty:ty $(, $param:ident)?);*) => {
+        $(
+            impl$(<$param: TryFrom<FamlValue, Error: Into<anyhow::Error>>>)? TryFrom<FamlValue>
+                for Option<$ty>
+            {
+                type Error = anyhow::Error;
+
+                fn try_from(value: FamlValue) -> anyhow::Result<Self> {
+                    try_from_option(value)
+                }
+            }
+        )*
+    };
+}
+
+impl_try_from_option!(
+    bool; i8; i16; i32; i64; u8; u16; u32; u64; usize; f64; String; Duration; Distance;
+    Vec<T>, T; HashMap<String, T>, T
+);
+
+impl<T: TryFrom<FamlValue, Error: Into<anyhow::Error>>> TryFrom<FamlValue> for Vec<T> {
+    type Error = anyhow::Error;
+
+    fn try_from(value: FamlValue) -> anyhow::Result<Self> {
         match value {
-            FamlValue::Float64(f) => f,
-            FamlValue::Int64(i) => i as f64,
-            _ => 0.0,
+            FamlValue::Array(items) => items
+                .into_iter()
+                .enumerate()
+                .map(|(idx, item)| {
+                    T::try_from(item).map_err(|err| anyhow!("[{idx}]: {}", err.into()))
+                })
+                .collect(),
+            value => Err(type_error("array", &value)),
         }
     }
 }
 
-impl From<FamlValue> for String {
-    fn from(value: FamlValue) -> Self {
-        value.as_str()
+impl<T: TryFrom<FamlValue, Error: Into<anyhow::Error>>> TryFrom<FamlValue> for HashMap<String, T> {
+    type Error = anyhow::Error;
+
+    fn try_from(value: FamlValue) -> anyhow::Result<Self> {
+        match value {
+            FamlValue::Map(map) => map
+                .into_iter()
+                .map(|(key, item)| match T::try_from(item) {
+                    Ok(item) => Ok((key, item)),
+                    Err(err) => Err(anyhow!("{key}: {}", err.into())),
+                })
+                .collect(),
+            value => Err(type_error("map", &value)),
+        }
+    }
+}
+
+impl TryFrom<FamlValue> for String {
+    type Error = anyhow::Error;
+
+    fn try_from(value: FamlValue) -> anyhow::Result<Self> {
+        match value {
+            FamlValue::String(s) => Ok(s),
+            value => Err(type_error("string", &value)),
+        }
     }
 }
 
@@ -0,0 +1,211 @@
+use super::eval::{DISTANCE_UNITS, DURATION_UNITS};
+use crate::FamlValue;
+use anyhow::anyhow;
+
+/// Format spec of an embedded expression in `$"..."`, e.g. `{ratio:.2}` or `{port:05}`.
+///
+/// Syntax: `[[fill]align][sign]['#']['0'][width]['.' precision][type]`, where `type` is one
+/// of `x`, `X`, `o`, `b`, `e`, `E`, or a duration/distance unit such as `secs` or `km`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FormatSpec {
+    pub fill: Option<char>,
+    pub align: Option<char>,
+    pub sign: bool,
+    pub alternate: bool,
+    pub zero: bool,
+    pub width: Option<usize>,
+    pub precision: Option<usize>,
+    pub kind: String,
+}
+
+impl FormatSpec {
+    pub fn parse(spec: &str) -> anyhow::Result<Self> {
+        let mut ret = FormatSpec::default();
+        let chars: Vec<_> = spec.chars().collect();
+        let mut idx = 0;
+        let is_align = |c: Option<&char>| matches!(c, Some('<' | '>' | '^'));
+        if is_align(chars.get(1)) {
+            ret.fill = Some(chars[0]);
+            ret.align = Some(chars[1]);
+            idx = 2;
+        } else if is_align(chars.first()) {
+            ret.align = Some(chars[0]);
+            idx = 1;
+        }
+        if chars.get(idx) == Some(&'+') {
+            ret.sign = true;
+            idx += 1;
+        }
+        if chars.get(idx) == Some(&'#') {
+            ret.alternate = true;
+            idx += 1;
+        }
+        if chars.get(idx) == Some(&'0') {
+            ret.zero = true;
+            idx += 1;
+        }
+        let read_num = |idx: &mut usize| {
+            let start = *idx;
+            while chars.get(*idx).is_some_and(|c| c.is_ascii_digit()) {
+                *idx += 1;
+            }
+            chars[start..*idx].iter().collect::<String>().parse().ok()
+        };
+        ret.width = read_num(&mut idx);
+        if chars.get(idx) == Some(&'.') {
+            idx += 1;
+            ret.precision = Some(
+                read_num(&mut idx).ok_or_else(|| anyhow!("precision expected in spec[{spec}]"))?,
+            );
+        }
+        ret.kind = chars[idx..].iter().collect();
+        let is_unit = Self::find_unit(&DURATION_UNITS, &ret.kind, "duration").is_ok()
+            || Self::find_unit(&DISTANCE_UNITS, &ret.kind, "distance").is_ok();
+        match &ret.kind[..] {
+            "" | "x" | "X" | "o" | "b" | "e" | "E" => (),
+            _ if is_unit => (),
+            kind => Err(anyhow!("unknown format type[{kind}] in spec[{spec}]"))?,
+        }
+        Ok(ret)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == FormatSpec::default()
+    }
+
+    pub fn format(&self, value: &FamlValue) -> anyhow::Result<String> {
+        if self.is_empty() {
+            return Ok(value.as_str());
+        }
+        let (prefix, body, is_num) = match (&self.kind[..], value) {
+            ("", FamlValue::Int64(n)) if self.precision.is_none() => {
+                self.split_sign(n.is_negative(), n.unsigned_abs().to_string())
+            }
+            ("", FamlValue::Int64(_) | FamlValue::Float64(_)) => {
+                let f = value.as_float().unwrap_or_default();
+                self.split_sign(f.is_sign_negative(), self.format_float(f.abs()))
+            }
+            ("", _) => {
+                let mut s = value.as_str();
+                if let Some(precision) = self.precision {
+                    s = s.chars().take(precision).collect();
+                }
+                ("".to_string(), s, false)
+            }
+            ("x" | "X" | "o" | "b", FamlValue::Int64(n)) => {
+                let (prefix, digits) = match &self.kind[..] {
+                    "x" => ("0x", format!("{:x}", n)),
+                    "X" => ("0x", format!("{:X}", n)),
+                    "o" => ("0o", format!("{:o}", n)),
+                    _ => ("0b", format!("{:b}", n)),
+                };
+                let (mut sign, digits, is_num) = self.split_sign(false, digits);
+                if self.alternate {
+                    sign.push_str(prefix);
+                }
+                (sign, digits, is_num)
+            }
+            ("e" | "E", FamlValue::Int64(_) | FamlValue::Float64(_)) => {
+                let f = value.as_float().unwrap_or_default();
+                let mut s = match self.precision {
+                    Some(precision) => format!("{:.*e}", precision, f.abs()),
+                    None => format!("{:e}", f.abs()),
+                };
+                if self.kind == "E" {
+                    s = s.to_uppercase();
+                }
+                self.split_sign(f.is_sign_negative(), s)
+            }
+            (unit, FamlValue::Duration(dur)) => {
+                let scale = Self::find_unit(&DURATION_UNITS, unit, "duration")?;
+                let f = dur.as_nanos() as f64 / scale;
+                self.split_sign(false, self.format_float(f))
+            }
+            (unit, FamlValue::Distance(dist)) => {
+                let scale = Self::find_unit(&DISTANCE_UNITS, unit, "distance")?;
+                let f = dist.to_meters() / scale;
+                self.split_sign(f.is_sign_negative(), self.format_float(f.abs()))
+            }
+            (kind, _) => Err(anyhow!(
+                "format type[{kind}] is not supported for value[{}]",
+                value.as_str()
+            ))?,
+        };
+        Ok(self.pad(prefix, body, is_num))
+    }
+
+    fn find_unit(
+        units: &[(&str, &[&str], f64)],
+        unit: &str,
+        type_name: &str,
+    ) -> anyhow::Result<f64> {
+        units
+            .iter()
+            .find(|(name, aliases, _)| *name == unit || aliases.contains(&unit))
+            .map(|(.., scale)| *scale)
+            .ok_or_else(|| anyhow!("unit[{unit}] is not supported for type[{type_name}]"))
+    }
+
+    fn format_float(&self, f: f64) -> String {
+        match self.precision {
+            Some(precision) => format!("{:.*}", precision, f),
+            None => f.to_string(),
+        }
+    }
+
+    fn split_sign(&self, negative: bool, body: String) -> (String, String, bool) {
+        let sign = match (negative, self.sign) {
+            (true, _) => "-",
+            (false, true) => "+",
+            (false, false) => "",
+        };
+        (sign.to_string(), body, true)
+    }
+
+    fn pad(&self, prefix: String, body: String, is_num: bool) -> String {
+        let len = prefix.chars().count() + body.chars().count();
+        let width = self.width.unwrap_or(0);
+        if len >= width {
+            return format!("{prefix}{body}");
+        }
+        let padding = width - len;
+        if self.zero && is_num && self.align.is_none() {
+            return format!("{prefix}{}{body}", "0".repeat(padding));
+        }
+        let fill = self.fill.unwrap_or(' ').to_string();
+        let align = self.align.unwrap_or(if is_num { '>' } else { '<' });
+        let (left, right) = match align {
+            '<' => (0, padding),
+            '^' => (padding / 2, padding - padding / 2),
+            _ => (padding, 0),
+        };
+        format!("{}{prefix}{body}{}", fill.repeat(left), fill.repeat(right))
+    }
+}
+
+impl std::fmt::Display for FormatSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(fill) = self.fill {
+            write!(f, "{fill}")?;
+        }
+        if let Some(align) = self.align {
+            write!(f, "{align}")?;
+        }
+        if self.sign {
+            f.write_str("+")?;
+        }
+        if self.alternate {
+            f.write_str("#")?;
+        }
+        if self.zero {
+            f.write_str("0")?;
+        }
+        if let Some(width) = self.width {
+            write!(f, "{width}")?;
+        }
+        if let Some(precision) = self.precision {
+            write!(f, ".{precision}")?;
+        }
+        f.write_str(&self.kind)
+    }
+}
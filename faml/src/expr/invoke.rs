@@ -1,13 +1,66 @@
-use crate::{FamlValue, expr::faml_value::Distance};
+use crate::expr::eval::{IndexEvaluator, Op2Evaluator};
+use crate::native::FasCallable;
+use crate::string_utils::IntoBaseExt;
+use crate::{FamlValue, Native, expr::faml_value::Distance};
 use anyhow::anyhow;
-use std::{collections::HashMap, f64::consts::PI, time::Duration};
+use indexmap::IndexMap;
+use std::cmp::Ordering;
+use std::{f64::consts::PI, time::Duration};
 
 pub trait InvokeExt {
     fn invoke(&mut self, func: &str, args: &Vec<FamlValue>) -> anyhow::Result<FamlValue>;
 }
 
+/// Built-in methods fail with this error for names or arguments they don't accept, only then
+/// extension methods are tried.
+#[derive(Debug)]
+pub(crate) struct UnknownMethod(String);
+
+impl std::fmt::Display for UnknownMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for UnknownMethod {}
+
+fn unknown_method(ty: &str, func: &str, args: &[FamlValue]) -> anyhow::Error {
+    let msg = format!("unknown {ty}.{func} with args[count: {}]", args.len());
+    anyhow::Error::new(UnknownMethod(msg))
+}
+
 impl InvokeExt for FamlValue {
     fn invoke(&mut self, func: &str, args: &Vec<FamlValue>) -> anyhow::Result<FamlValue> {
+        self.invoke_with(func, args, Native::get_method)
+    }
+}
+
+impl FamlValue {
+    /// Call a built-in method, or the extension method found by `find_method` with the type
+    /// name and method name if no built-in method accepts them.
+    pub(crate) fn invoke_with(
+        &mut self,
+        func: &str,
+        args: &Vec<FamlValue>,
+        find_method: impl Fn(&str, &str) -> Option<Box<dyn FasCallable + 'static>>,
+    ) -> anyhow::Result<FamlValue> {
+        let err = match self.invoke_builtin(func, args) {
+            Ok(val) => return Ok(val),
+            Err(err) if err.is::<UnknownMethod>() => err,
+            Err(err) => return Err(err),
+        };
+        let ty = self.type_name();
+        let Some(method) = find_method(ty, func) else {
+            return Err(err);
+        };
+        let mut method_args = vec![self.clone()];
+        method_args.extend(args.iter().cloned());
+        method
+            .call(method_args)
+            .map_err(|err| anyhow!("method[{ty}.{func}]: {err}"))
+    }
+
+    fn invoke_builtin(&mut self, func: &str, args: &Vec<FamlValue>) -> anyhow::Result<FamlValue> {
         match self {
             FamlValue::None => ().invoke(func, args),
             FamlValue::Bool(b) => b.invoke(func, args),
@@ -28,10 +81,7 @@ impl InvokeExt for () {
     fn invoke(&mut self, func: &str, args: &Vec<FamlValue>) -> anyhow::Result<FamlValue> {
         match func {
             "to_str" if args.len() == 0 => Ok("null".to_string().into()),
-            _ => Err(anyhow!(
-                "unknown ().{func} with args[count: {}]",
-                args.len()
-            )),
+            _ => Err(unknown_method("()", func, args)),
         }
     }
 }
@@ -40,10 +90,7 @@ impl InvokeExt for bool {
     fn invoke(&mut self, func: &str, args: &Vec<FamlValue>) -> anyhow::Result<FamlValue> {
         match func {
             "to_str" if args.len() == 0 => Ok(self.to_string().into()),
-            _ => Err(anyhow!(
-                "unknown bool.{func} with args[count: {}]",
-                args.len()
-            )),
+            _ => Err(unknown_method("bool", func, args)),
         }
     }
 }
@@ -80,20 +127,14 @@ impl InvokeExt for i64 {
                 "to_float" => (*self as f64).into(),
                 "to_quantified" => (*self as f64).to_quantified().into(),
                 "to_str" => self.to_string().into(),
-                _ => Err(anyhow!(
-                    "unknown i64.{func} with args[count: {}]",
-                    args.len()
-                ))?,
+                _ => Err(unknown_method("i64", func, args))?,
             })
         } else if args.len() == 1 {
             if let Some(arg) = args[0].as_int() {
                 Ok(match func {
                     "max" => (*self).max(arg).into(),
                     "min" => (*self).min(arg).into(),
-                    _ => Err(anyhow!(
-                        "unknown i64.{func} with args[count: {}]",
-                        args.len()
-                    ))?,
+                    _ => Err(unknown_method("i64", func, args))?,
                 })
             } else if let Some(arg) = args[0].as_float() {
                 Ok(match func {
@@ -103,22 +144,13 @@ impl InvokeExt for i64 {
                     "max" => (*self as f64).max(arg).into(),
                     "min" => (*self as f64).min(arg).into(),
                     "pow" => (*self as f64).powf(arg).into(),
-                    _ => Err(anyhow!(
-                        "unknown i64.{func} with args[count: {}]",
-                        args.len()
-                    ))?,
+                    _ => Err(unknown_method("i64", func, args))?,
                 })
             } else {
-                Err(anyhow!(
-                    "unknown i64.{func} with args[count: {}]",
-                    args.len()
-                ))
+                Err(unknown_method("i64", func, args))
             }
         } else {
-            Err(anyhow!(
-                "unknown i64.{func} with args[count: {}]",
-                args.len()
-            ))
+            Err(unknown_method("i64", func, args))
         }
     }
 }
@@ -171,10 +203,7 @@ impl InvokeExt for f64 {
                 "to_degrees" => self.to_degrees().into(),
                 "to_radians" => self.to_radians().into(),
                 "to_str" => self.to_string().into(),
-                _ => Err(anyhow!(
-                    "unknown f64.{func} with args[count: {}]",
-                    args.len()
-                ))?,
+                _ => Err(unknown_method("f64", func, args))?,
             })
         } else if args.len() == 1 {
             if let Some(arg) = args[0].as_float() {
@@ -185,41 +214,63 @@ impl InvokeExt for f64 {
                     "max" => self.max(arg).into(),
                     "min" => self.min(arg).into(),
                     "pow" => self.powf(arg).into(),
-                    _ => Err(anyhow!(
-                        "unknown f64.{func} with args[count: {}]",
-                        args.len()
-                    ))?,
+                    _ => Err(unknown_method("f64", func, args))?,
                 })
             } else {
-                Err(anyhow!(
-                    "unknown f64.{func} with args[count: {}]",
-                    args.len()
-                ))
+                Err(unknown_method("f64", func, args))
             }
         } else {
-            Err(anyhow!(
-                "unknown f64.{func} with args[count: {}]",
-                args.len()
-            ))
+            Err(unknown_method("f64", func, args))
         }
     }
 }
 
 impl InvokeExt for String {
     fn invoke(&mut self, func: &str, args: &Vec<FamlValue>) -> anyhow::Result<FamlValue> {
+        let int_arg = |idx: usize| {
+            args[idx]
+                .as_int()
+                .ok_or(anyhow!("only type[int] arg for method[{func}]"))
+        };
+        if func == "format" {
+            return Ok(FamlValue::String(self.format_args(args)?));
+        }
         if args.len() == 0 {
             Ok(match func {
+                "capitalize" => {
+                    let mut chars = self.chars();
+                    match chars.next() {
+                        Some(first) => {
+                            FamlValue::String(first.to_uppercase().chain(chars).collect())
+                        }
+                        None => FamlValue::String("".to_string()),
+                    }
+                }
                 "is_empty" => FamlValue::Bool(self.is_empty()),
+                "kebab_case" => FamlValue::String(self.to_snake_case().replace('_', "-")),
                 "len" => FamlValue::Int64(self.len() as i64),
                 "lines" => self.invoke("split", &vec![FamlValue::String("\n".to_string())])?,
+                "snake_case" => FamlValue::String(self.to_snake_case()),
+                "to_bool" => match &self.trim().to_lowercase()[..] {
+                    "true" => FamlValue::Bool(true),
+                    "false" => FamlValue::Bool(false),
+                    _ => FamlValue::None,
+                },
+                "to_float" => match self.trim().parse::<f64>() {
+                    Ok(f) => FamlValue::Float64(f),
+                    Err(_) => FamlValue::None,
+                },
+                "to_int" => match self.trim().parse::<i64>() {
+                    Ok(i) => FamlValue::Int64(i),
+                    Err(_) => FamlValue::None,
+                },
                 "to_lowercase" => FamlValue::String(self.to_lowercase()),
                 "to_str" => FamlValue::String(self.clone()),
                 "to_uppercase" => FamlValue::String(self.to_uppercase()),
                 "trim" => FamlValue::String(self.trim().to_string()),
-                _ => Err(anyhow!(
-                    "unknown string.{func} with args[count: {}]",
-                    args.len()
-                ))?,
+                "trim_end" => FamlValue::String(self.trim_end().to_string()),
+                "trim_start" => FamlValue::String(self.trim_start().to_string()),
+                _ => Err(unknown_method("string", func, args))?,
             })
         } else {
             if func == "split" || func == "split_once" || func == "split_without_empty" {
@@ -251,10 +302,19 @@ impl InvokeExt for String {
                 Ok(FamlValue::Array(ret))
             } else if args.len() == 1 {
                 Ok(match func {
+                    "char_at" => IndexEvaluator::index(&self.clone().into(), int_arg(0)?)
+                        .unwrap_or(FamlValue::None),
                     "contains" => {
                         let arg = args[0].as_str();
                         FamlValue::Bool(self.contains(&arg))
                     }
+                    "count" => {
+                        let arg = args[0].as_str();
+                        if arg.is_empty() {
+                            return Err(anyhow!("empty pattern for method[count]"));
+                        }
+                        FamlValue::Int64(self.matches(&arg).count() as i64)
+                    }
                     "ends_with" => {
                         let arg = args[0].as_str();
                         FamlValue::Bool(self.ends_with(&arg))
@@ -266,6 +326,7 @@ impl InvokeExt for String {
                             None => FamlValue::None,
                         }
                     }
+                    "pad_left" | "pad_right" => self.pad(func, int_arg(0)?, " ")?,
                     "repeat" => {
                         let arg = args[0]
                             .as_int()
@@ -287,10 +348,11 @@ impl InvokeExt for String {
                         let arg = args[0]
                             .as_int()
                             .ok_or(anyhow!("only type[int] arg for method[split_at]"))?;
-                        match self.len() >= arg as usize && arg >= 0 {
+                        let chars: Vec<_> = self.chars().collect();
+                        match chars.len() >= arg as usize && arg >= 0 {
                             true => FamlValue::Array(vec![
-                                FamlValue::String(self[..arg as usize].to_string()),
-                                FamlValue::String(self[arg as usize..].to_string()),
+                                FamlValue::String(chars[..arg as usize].iter().collect()),
+                                FamlValue::String(chars[arg as usize..].iter().collect()),
                             ]),
                             false => FamlValue::Array(vec![FamlValue::String(self.to_string())]),
                         }
@@ -299,32 +361,181 @@ impl InvokeExt for String {
                         let arg = args[0].as_str();
                         FamlValue::Bool(self.starts_with(&arg))
                     }
-                    _ => Err(anyhow!(
-                        "unknown string.{func} with args[count: {}]",
-                        args.len()
-                    ))?,
+                    "strip_prefix" => match self.strip_prefix(&args[0].as_str()) {
+                        Some(s) => FamlValue::String(s.to_string()),
+                        None => FamlValue::None,
+                    },
+                    "strip_suffix" => match self.strip_suffix(&args[0].as_str()) {
+                        Some(s) => FamlValue::String(s.to_string()),
+                        None => FamlValue::None,
+                    },
+                    "substr" => self.substr(int_arg(0)?, None),
+                    _ => Err(unknown_method("string", func, args))?,
                 })
             } else if args.len() == 2 {
-                let pre = args[0].as_str();
-                let post = args[1].as_str();
-                Ok(FamlValue::String(match func {
-                    "replace_once" => self.replacen(&pre, &post, 1),
-                    "replace" => self.replace(&pre, &post),
-                    _ => panic!("unreachable"),
-                }))
+                Ok(match func {
+                    "pad_left" | "pad_right" => self.pad(func, int_arg(0)?, &args[1].as_str())?,
+                    "replace_once" => {
+                        FamlValue::String(self.replacen(&args[0].as_str(), &args[1].as_str(), 1))
+                    }
+                    "replace" => {
+                        FamlValue::String(self.replace(&args[0].as_str(), &args[1].as_str()))
+                    }
+                    "substr" => self.substr(int_arg(0)?, Some(int_arg(1)?)),
+                    _ => Err(unknown_method("string", func, args))?,
+                })
+            } else if args.len() == 3 && func == "replace" {
+                let count = int_arg(2)?.max(0) as usize;
+                let (pre, post) = (args[0].as_str(), args[1].as_str());
+                Ok(FamlValue::String(self.replacen(&pre, &post, count)))
             } else {
-                Err(anyhow!(
-                    "unknown string.{func} with args[count: {}]",
-                    args.len()
-                ))?
+                Err(unknown_method("string", func, args))?
             }
         }
     }
 }
 
+/// Helpers of string methods, positions and widths count characters like string indexing.
+trait StringMethodExt {
+    fn pad(&self, func: &str, width: i64, fill: &str) -> anyhow::Result<FamlValue>;
+    fn substr(&self, start: i64, len: Option<i64>) -> FamlValue;
+    fn format_args(&self, args: &[FamlValue]) -> anyhow::Result<String>;
+}
+
+impl StringMethodExt for String {
+    fn pad(&self, func: &str, width: i64, fill: &str) -> anyhow::Result<FamlValue> {
+        let mut fills = fill.chars();
+        let (Some(fill), None) = (fills.next(), fills.next()) else {
+            return Err(anyhow!("only single char fill for method[{func}]"));
+        };
+        let count = (width.max(0) as usize).saturating_sub(self.chars().count());
+        let padding: String = std::iter::repeat_n(fill, count).collect();
+        Ok(FamlValue::String(match func {
+            "pad_left" => format!("{padding}{self}"),
+            _ => format!("{self}{padding}"),
+        }))
+    }
+
+    /// Negative `start` counts from the end, out of range parts are clamped.
+    fn substr(&self, start: i64, len: Option<i64>) -> FamlValue {
+        let count = self.chars().count() as i64;
+        let start = match start < 0 {
+            true => (start + count).max(0),
+            false => start.min(count),
+        };
+        let len = len.unwrap_or(count).max(0);
+        let ret = self.chars().skip(start as usize).take(len as usize);
+        FamlValue::String(ret.collect())
+    }
+
+    /// Replace `{}` with the next argument and `{n}` with the n-th, `{{` and `}}` are escapes.
+    fn format_args(&self, args: &[FamlValue]) -> anyhow::Result<String> {
+        let mut ret = String::new();
+        let mut next = 0;
+        let mut chars = self.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    ret.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    ret.push('}');
+                }
+                '{' => {
+                    let mut index = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => index.push(c),
+                            None => return Err(anyhow!("unclosed placeholder in format string")),
+                        }
+                    }
+                    let index = match index.trim() {
+                        "" => {
+                            next += 1;
+                            next - 1
+                        }
+                        index => index
+                            .parse::<usize>()
+                            .map_err(|_| anyhow!("invalid placeholder[{{{index}}}]"))?,
+                    };
+                    let arg = args
+                        .get(index)
+                        .ok_or_else(|| anyhow!("missing arg[{index}] for format string"))?;
+                    ret.push_str(&arg.as_str());
+                }
+                c => ret.push(c),
+            }
+        }
+        Ok(ret)
+    }
+}
+
 impl InvokeExt for Vec<FamlValue> {
     fn invoke(&mut self, func: &str, args: &Vec<FamlValue>) -> anyhow::Result<FamlValue> {
         match func {
+            "avg" if args.is_empty() => match self.len() {
+                0 => Ok(FamlValue::None),
+                len => {
+                    let sum = self.invoke("sum", args)?;
+                    Op2Evaluator::eval(sum, "/", FamlValue::Float64(len as f64))
+                }
+            },
+            "chunk" if args.len() == 1 => {
+                let size = match args[0].as_int() {
+                    Some(size) if size > 0 => size as usize,
+                    _ => Err(anyhow!("only positive type[int] arg for method[chunk]"))?,
+                };
+                let chunks = self.chunks(size).map(|c| FamlValue::Array(c.to_vec()));
+                Ok(FamlValue::Array(chunks.collect()))
+            }
+            "concat" => {
+                let mut ret = self.clone();
+                for arg in args {
+                    match arg {
+                        FamlValue::Array(arr) => ret.extend(arr.iter().cloned()),
+                        _ => Err(anyhow!("only type[array] arg for method[concat]"))?,
+                    }
+                }
+                Ok(FamlValue::Array(ret))
+            }
+            "contains" if args.len() == 1 => Ok(FamlValue::Bool(
+                self.iter()
+                    .any(|item| Op2Evaluator::is_equal(item, &args[0])),
+            )),
+            "difference" | "intersect" if args.len() == 1 => {
+                let FamlValue::Array(other) = &args[0] else {
+                    Err(anyhow!("only type[array] arg for method[{func}]"))?
+                };
+                let keep = func == "intersect";
+                let mut ret: Vec<FamlValue> = vec![];
+                for item in self.iter() {
+                    let in_other = other.iter().any(|o| Op2Evaluator::is_equal(item, o));
+                    if in_other == keep && !ret.iter().any(|r| Op2Evaluator::is_equal(item, r)) {
+                        ret.push(item.clone());
+                    }
+                }
+                Ok(FamlValue::Array(ret))
+            }
+            "first" if args.is_empty() => Ok(self.first().cloned().unwrap_or(FamlValue::None)),
+            "flatten" if args.is_empty() => {
+                let mut ret = vec![];
+                for item in self.iter() {
+                    match item {
+                        FamlValue::Array(arr) => ret.extend(arr.iter().cloned()),
+                        item => ret.push(item.clone()),
+                    }
+                }
+                Ok(FamlValue::Array(ret))
+            }
+            "index_of" if args.len() == 1 => {
+                let idx = self
+                    .iter()
+                    .position(|item| Op2Evaluator::is_equal(item, &args[0]));
+                Ok(idx.map_or(FamlValue::None, |idx| FamlValue::Int64(idx as i64)))
+            }
             "join" if args.len() == 1 => {
                 let sep = args[0].as_str();
                 let mut ret = "".to_string();
@@ -336,7 +547,25 @@ impl InvokeExt for Vec<FamlValue> {
                 }
                 Ok(FamlValue::String(ret))
             }
+            "last" if args.is_empty() => Ok(self.last().cloned().unwrap_or(FamlValue::None)),
             "len" if args.len() == 0 => Ok(FamlValue::Int64(self.len() as i64)),
+            "max" | "min" if args.is_empty() => {
+                let mut ret: Option<&FamlValue> = None;
+                for item in self.iter() {
+                    ret = match ret {
+                        Some(cur) => {
+                            let ord = compare_values(item, cur)?;
+                            let better = match func {
+                                "max" => ord.is_gt(),
+                                _ => ord.is_lt(),
+                            };
+                            Some(if better { item } else { cur })
+                        }
+                        None => Some(item),
+                    };
+                }
+                Ok(ret.cloned().unwrap_or(FamlValue::None))
+            }
             "pop" if args.len() == 0 => self.pop().ok_or(anyhow!("Array is empty")),
             "push" => {
                 for arg in args {
@@ -349,6 +578,51 @@ impl InvokeExt for Vec<FamlValue> {
                 ret.reverse();
                 Ok(FamlValue::Array(ret))
             }
+            "slice" if args.len() == 1 || args.len() == 2 => {
+                let mut bounds = vec![];
+                for arg in args {
+                    let idx = arg
+                        .as_int()
+                        .ok_or(anyhow!("only type[int] arg for method[slice]"))?;
+                    let len = self.len() as i64;
+                    let idx = if idx < 0 { idx + len } else { idx };
+                    bounds.push(idx.clamp(0, len) as usize);
+                }
+                let (start, end) = (bounds[0], bounds.get(1).copied().unwrap_or(self.len()));
+                Ok(FamlValue::Array(match start < end {
+                    true => self[start..end].to_vec(),
+                    false => vec![],
+                }))
+            }
+            "sort" | "sort_desc" if args.is_empty() => {
+                let mut ret = self.clone();
+                let mut err = None;
+                // both are stable, equal values keep their order
+                ret.sort_by(|a, b| {
+                    let ord = match func {
+                        "sort" => compare_values(a, b),
+                        _ => compare_values(b, a),
+                    };
+                    ord.unwrap_or_else(|e| {
+                        err.get_or_insert(e);
+                        Ordering::Equal
+                    })
+                });
+                if let Some(err) = err {
+                    return Err(err);
+                }
+                Ok(FamlValue::Array(ret))
+            }
+            "sum" if args.is_empty() => {
+                let mut items = self.iter().cloned();
+                let Some(mut ret) = items.next() else {
+                    return Ok(FamlValue::Int64(0));
+                };
+                for item in items {
+                    ret = Op2Evaluator::eval(ret, "+", item)?;
+                }
+                Ok(ret)
+            }
             "to_str" if args.len() == 0 => {
                 let mut s = "[ ".to_string();
                 for (i, item) in self.iter_mut().enumerate() {
@@ -360,18 +634,121 @@ impl InvokeExt for Vec<FamlValue> {
                 s += " ]";
                 Ok(FamlValue::String(s))
             }
-            _ => Err(anyhow!(
-                "unknown vec.{func} with args[count: {}]",
-                args.len()
-            )),
+            "unique" if args.is_empty() => {
+                let mut ret: Vec<FamlValue> = vec![];
+                for item in self.iter() {
+                    if !ret.iter().any(|r| Op2Evaluator::is_equal(item, r)) {
+                        ret.push(item.clone());
+                    }
+                }
+                Ok(FamlValue::Array(ret))
+            }
+            "zip" if args.len() == 1 => {
+                let FamlValue::Array(other) = &args[0] else {
+                    Err(anyhow!("only type[array] arg for method[zip]"))?
+                };
+                let pairs = self.iter().zip(other.iter());
+                let pairs = pairs.map(|(a, b)| FamlValue::Array(vec![a.clone(), b.clone()]));
+                Ok(FamlValue::Array(pairs.collect()))
+            }
+            _ => Err(unknown_method("vec", func, args)),
         }
     }
 }
 
-impl InvokeExt for HashMap<String, FamlValue> {
+/// Order of values comparable with `<`, like numbers, strings, durations or distances.
+fn compare_values(a: &FamlValue, b: &FamlValue) -> anyhow::Result<Ordering> {
+    let less = |a: &FamlValue, b: &FamlValue| match Op2Evaluator::eval(a.clone(), "<", b.clone()) {
+        Ok(FamlValue::Bool(less)) => Ok(less),
+        _ => Err(anyhow!(
+            "cannot compare value[{}] with value[{}]",
+            a.as_print_str(),
+            b.as_print_str()
+        )),
+    };
+    let equal = |a: &FamlValue, b: &FamlValue| {
+        matches!(
+            Op2Evaluator::eval(a.clone(), "==", b.clone()),
+            Ok(FamlValue::Bool(true))
+        )
+    };
+    match (less(a, b)?, less(b, a)?) {
+        (true, _) => Ok(Ordering::Less),
+        (_, true) => Ok(Ordering::Greater),
+        // NaN is neither less, greater nor equal
+        _ if !equal(a, b) => Err(anyhow!(
+            "cannot compare value[{}] with value[{}]",
+            a.as_print_str(),
+            b.as_print_str()
+        )),
+        _ => Ok(Ordering::Equal),
+    }
+}
+
+impl InvokeExt for IndexMap<String, FamlValue> {
     fn invoke(&mut self, func: &str, args: &Vec<FamlValue>) -> anyhow::Result<FamlValue> {
         match func {
+            "contains_key" if args.len() == 1 => {
+                Ok(FamlValue::Bool(self.contains_key(&args[0].as_str())))
+            }
+            "entries" if args.is_empty() => {
+                let entries = self.iter().map(|(key, val)| {
+                    FamlValue::Array(vec![FamlValue::String(key.clone()), val.clone()])
+                });
+                Ok(FamlValue::Array(entries.collect()))
+            }
+            "get" if args.len() == 1 || args.len() == 2 => {
+                let default = args.get(1).cloned().unwrap_or(FamlValue::None);
+                Ok(self.get(&args[0].as_str()).cloned().unwrap_or(default))
+            }
+            "invert" if args.is_empty() => {
+                let mut ret = IndexMap::new();
+                for (key, val) in self.iter() {
+                    let new_key = match val {
+                        FamlValue::String(s) => s.clone(),
+                        FamlValue::Bool(_) | FamlValue::Int64(_) | FamlValue::Float64(_) => {
+                            val.as_str()
+                        }
+                        _ => Err(anyhow!(
+                            "value[{}] cannot be used as key for method[invert]",
+                            val.as_print_str()
+                        ))?,
+                    };
+                    ret.insert(new_key, FamlValue::String(key.clone()));
+                }
+                Ok(FamlValue::Map(ret))
+            }
+            "keys" if args.is_empty() => {
+                let keys = self.keys().map(|key| FamlValue::String(key.clone()));
+                Ok(FamlValue::Array(keys.collect()))
+            }
             "len" if args.len() == 0 => Ok(FamlValue::Int64(self.len() as i64)),
+            "merge" if !args.is_empty() => {
+                let mut ret = FamlValue::Map(self.clone());
+                for arg in args {
+                    if !arg.is_map() {
+                        Err(anyhow!("only type[map] arg for method[merge]"))?;
+                    }
+                    merge_value(&mut ret, arg.clone());
+                }
+                Ok(ret)
+            }
+            "omit" | "pick" if !args.is_empty() => {
+                let keys = key_args(func, args)?;
+                let keep = func == "pick";
+                let ret = self
+                    .iter()
+                    .filter(|(key, _)| keys.contains(key) == keep)
+                    .map(|(key, val)| (key.clone(), val.clone()));
+                Ok(FamlValue::Map(ret.collect()))
+            }
+            "remove" if !args.is_empty() => {
+                let mut ret = self.clone();
+                for key in key_args(func, args)? {
+                    ret.shift_remove(&key);
+                }
+                Ok(FamlValue::Map(ret))
+            }
             "to_str" if args.len() == 0 => {
                 let mut s = "{ ".to_string();
                 for (i, (key, item)) in self.iter_mut().enumerate() {
@@ -385,11 +762,44 @@ impl InvokeExt for HashMap<String, FamlValue> {
                 s += " }";
                 Ok(FamlValue::String(s))
             }
-            _ => Err(anyhow!(
-                "unknown map.{func} with args[count: {}]",
-                args.len()
-            )),
+            "values" if args.is_empty() => Ok(FamlValue::Array(self.values().cloned().collect())),
+            _ => Err(unknown_method("map", func, args)),
+        }
+    }
+}
+
+/// Keys passed as strings or arrays of strings.
+fn key_args(func: &str, args: &[FamlValue]) -> anyhow::Result<Vec<String>> {
+    let mut keys = vec![];
+    for arg in args {
+        match arg {
+            FamlValue::String(key) => keys.push(key.clone()),
+            FamlValue::Array(arr) if arr.iter().all(|item| item.is_str()) => {
+                keys.extend(arr.iter().map(|item| item.as_str()))
+            }
+            _ => Err(anyhow!("only type[string] keys for method[{func}]"))?,
+        }
+    }
+    Ok(keys)
+}
+
+/// Deep merge with the semantics of `FamlExpr::apply`: maps are merged by key, arrays are
+/// extended by arrays, other values are replaced.
+fn merge_value(target: &mut FamlValue, val: FamlValue) {
+    match (target, val) {
+        (FamlValue::Map(map), FamlValue::Map(map2)) => {
+            for (key, val) in map2 {
+                match map.get_mut(&key) {
+                    Some(item) => merge_value(item, val),
+                    None => {
+                        map.insert(key, val);
+                    }
+                }
+            }
         }
+        (FamlValue::Array(arr), FamlValue::Array(arr2)) => arr.extend(arr2),
+        (FamlValue::Array(_), _) => (),
+        (target, val) => *target = val,
     }
 }
 
@@ -410,16 +820,10 @@ impl InvokeExt for Duration {
                 "as_months" => (self.as_nanos() as f64 * G * D * 30.0).into(),
                 "as_years" => (self.as_nanos() as f64 * G * D * 365.0).into(),
                 "to_str" => self.to_str().into(),
-                _ => Err(anyhow!(
-                    "unknown duration.{func} with args[count: {}]",
-                    args.len()
-                ))?,
+                _ => Err(unknown_method("duration", func, args))?,
             })
         } else {
-            Err(anyhow!(
-                "unknown duration.{func} with args[count: {}]",
-                args.len()
-            ))
+            Err(unknown_method("duration", func, args))
         }
     }
 }
@@ -435,35 +839,24 @@ impl InvokeExt for Distance {
                 "to_micrometers" => self.to_micrometers().into(),
                 "to_nanometers" => self.to_nanometers().into(),
                 "to_str" => FamlValue::String(self.to_str()),
-                _ => Err(anyhow!(
-                    "unknown distance.{func} with args[count: {}]",
-                    args.len()
-                ))?,
+                _ => Err(unknown_method("distance", func, args))?,
             })
         } else {
-            Err(anyhow!(
-                "unknown distance.{func} with args[count: {}]",
-                args.len()
-            ))
+            Err(unknown_method("distance", func, args))
         }
     }
 }
 
+/// `json###` and `yaml###` values are inspected with the methods of the converted values.
 impl InvokeExt for serde_json::Value {
     fn invoke(&mut self, func: &str, args: &Vec<FamlValue>) -> anyhow::Result<FamlValue> {
-        Err(anyhow!(
-            "unknown json.{func} with args[count: {}]",
-            args.len()
-        ))?
+        FamlValue::from_json(self.clone())?.invoke_builtin(func, args)
     }
 }
 
 impl InvokeExt for serde_yaml::Value {
     fn invoke(&mut self, func: &str, args: &Vec<FamlValue>) -> anyhow::Result<FamlValue> {
-        Err(anyhow!(
-            "unknown yaml.{func} with args[count: {}]",
-            args.len()
-        ))?
+        FamlValue::from_yaml(self.clone())?.invoke_builtin(func, args)
     }
 }
 
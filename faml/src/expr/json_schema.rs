@@ -0,0 +1,778 @@
+use super::faml_expr::{FamlExpr, FamlExprConstraintAnno, FamlExprImpl};
+use super::faml_type::FamlType;
+use super::faml_value::FamlValue;
+use super::scope::LocalScope;
+use anyhow::anyhow;
+use serde_json::{Map, Value, json};
+use std::collections::HashMap;
+
+const SCHEMA_DRAFT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+type NumberCheck = (&'static str, fn(f64, f64) -> bool, &'static str);
+
+/// Keywords understood by the validator, schemas using any other keyword are rejected.
+const SUPPORTED_KEYWORDS: &[&str] = &[
+    // annotations
+    "title",
+    "description",
+    "default",
+    "examples",
+    "deprecated",
+    "readOnly",
+    "writeOnly",
+    "$comment",
+    // references, only local `#` pointers
+    "$ref",
+    "$defs",
+    "definitions",
+    // any instance
+    "type",
+    "enum",
+    "const",
+    // numbers
+    "minimum",
+    "maximum",
+    "exclusiveMinimum",
+    "exclusiveMaximum",
+    "multipleOf",
+    // strings
+    "minLength",
+    "maxLength",
+    "pattern",
+    // arrays
+    "minItems",
+    "maxItems",
+    "uniqueItems",
+    "prefixItems",
+    "items",
+    "contains",
+    // objects
+    "minProperties",
+    "maxProperties",
+    "required",
+    "properties",
+    "patternProperties",
+    "additionalProperties",
+    // combinators
+    "allOf",
+    "anyOf",
+    "oneOf",
+    "not",
+    "if",
+    "then",
+    "else",
+];
+
+impl FamlExpr {
+    /// Export a JSON Schema, types are inferred from evaluated values and simple `@constraint`
+    /// expressions are translated into schema keywords.
+    pub fn to_json_schema(&self) -> anyhow::Result<Value> {
+        let mut schema = JsonSchemaExporter::export(self)?;
+        if let Value::Object(obj) = &mut schema {
+            obj.insert("$schema".to_string(), SCHEMA_DRAFT.into());
+        }
+        Ok(schema)
+    }
+
+    pub fn validate_json_schema(&self, schema: &Value) -> anyhow::Result<()> {
+        self.evaluate()?.validate_json_schema(schema)
+    }
+}
+
+impl FamlValue {
+    /// Validate the value against a JSON Schema, errors are reported with the key path.
+    /// Schemas using keywords outside the supported subset are rejected before validating.
+    pub fn validate_json_schema(&self, schema: &Value) -> anyhow::Result<()> {
+        let mut unsupported = vec![];
+        JsonSchemaValidator::check_keywords(schema, "#", &mut unsupported);
+        if !unsupported.is_empty() {
+            return Err(anyhow!(
+                "unsupported json schema:\n{}",
+                unsupported.join("\n")
+            ));
+        }
+        let mut validator = JsonSchemaValidator {
+            root: schema,
+            errors: vec![],
+        };
+        validator.validate(schema, &self.to_json(), "");
+        match validator.errors.is_empty() {
+            true => Ok(()),
+            false => Err(anyhow!(
+                "json schema validation failed:\n{}",
+                validator.errors.join("\n")
+            )),
+        }
+    }
+}
+
+struct JsonSchemaExporter {}
+
+impl JsonSchemaExporter {
+    fn export(expr: &FamlExpr) -> anyhow::Result<Value> {
+        Ok(match &expr.base().expr {
+            FamlExprImpl::Map(map) => {
+                let mut keys: Vec<_> = map.keys().collect();
+                keys.sort();
+                let mut properties = Map::new();
+                for key in &keys {
+                    properties.insert(key.to_string(), Self::export(&map[*key])?);
+                }
+                json!({ "type": "object", "properties": properties, "required": keys })
+            }
+            FamlExprImpl::Array(items) => {
+                let mut schemas = vec![];
+                for item in items {
+                    schemas.push(Self::export(item)?);
+                }
+                json!({ "type": "array", "items": Self::merge(schemas) })
+            }
+            FamlExprImpl::ConstraintAnno(anno) => {
+                let value = anno.value.evaluate()?;
+                let mut schema = Self::export(&anno.value)?;
+                if let Value::Object(obj) = &mut schema {
+                    Self::apply_constraints(obj, anno, &value)?;
+                }
+                schema
+            }
+            FamlExprImpl::TypeAnno(anno) => Self::export_type(&anno.ty),
+            _ => Self::export_value(&expr.evaluate()?),
+        })
+    }
+
+    fn export_type(ty: &FamlType) -> Value {
+        match ty {
+            FamlType::Bool => json!({ "type": "boolean" }),
+            FamlType::Int => json!({ "type": "integer" }),
+            FamlType::Float => json!({ "type": "number" }),
+            FamlType::String => json!({ "type": "string" }),
+            FamlType::Duration => {
+                json!({ "type": "string", "description": "duration, e.g. \"2.5 mins\"" })
+            }
+            FamlType::Distance => {
+                json!({ "type": "string", "description": "distance, e.g. \"1.5 kilometers\"" })
+            }
+            FamlType::Any => json!({}),
+            FamlType::Option(ty) => {
+                let mut schema = Self::export_type(ty);
+                match &schema["type"] {
+                    Value::String(name) => schema["type"] = json!([name, "null"]),
+                    _ => schema = json!({ "anyOf": [schema, { "type": "null" }] }),
+                }
+                schema
+            }
+            FamlType::Array(ty) => json!({ "type": "array", "items": Self::export_type(ty) }),
+            FamlType::Enum((name, variants)) => {
+                json!({ "type": "string", "title": name, "enum": variants })
+            }
+            FamlType::Struct((name, fields)) => {
+                let properties: Map<_, _> = fields
+                    .iter()
+                    .map(|(key, ty)| (key.clone(), Self::export_type(ty)))
+                    .collect();
+                let required: Vec<_> = fields
+                    .iter()
+                    .filter(|(_, ty)| !ty.is_optional())
+                    .map(|(key, _)| key)
+                    .collect();
+                let mut schema = json!({
+                    "type": "object",
+                    "properties": properties,
+                    "required": required,
+                    "additionalProperties": false,
+                });
+                if !name.is_empty() {
+                    schema["title"] = name.clone().into();
+                }
+                schema
+            }
+        }
+    }
+
+    fn export_value(value: &FamlValue) -> Value {
+        match value {
+            FamlValue::None => json!({ "type": "null" }),
+            FamlValue::Bool(_) => json!({ "type": "boolean" }),
+            FamlValue::Int64(_) => json!({ "type": "integer" }),
+            FamlValue::Float64(_) => json!({ "type": "number" }),
+            FamlValue::String(_) => json!({ "type": "string" }),
+            FamlValue::Array(items) => {
+                let schemas = items.iter().map(Self::export_value).collect();
+                json!({ "type": "array", "items": Self::merge(schemas) })
+            }
+            FamlValue::Map(map) => {
+                let mut keys: Vec<_> = map.keys().collect();
+                keys.sort();
+                let properties: Map<_, _> = keys
+                    .iter()
+                    .map(|key| (key.to_string(), Self::export_value(&map[*key])))
+                    .collect();
+                json!({ "type": "object", "properties": properties, "required": keys })
+            }
+            FamlValue::Duration(_) => Self::export_type(&FamlType::Duration),
+            FamlValue::Distance(_) => Self::export_type(&FamlType::Distance),
+            FamlValue::Json(_) | FamlValue::Yaml(_) => json!({}),
+        }
+    }
+
+    /// Merge schemas of array items, objects are merged by properties.
+    fn merge(schemas: Vec<Value>) -> Value {
+        let Some(first) = schemas.first() else {
+            return json!({});
+        };
+        if schemas.iter().all(|schema| schema == first) {
+            return first.clone();
+        }
+        let types: Vec<_> = schemas.iter().map(|schema| &schema["type"]).collect();
+        if types.iter().all(|ty| *ty == "object") {
+            let mut properties: Map<String, Value> = Map::new();
+            let mut required: Option<Vec<Value>> = None;
+            for schema in &schemas {
+                if let Some(props) = schema["properties"].as_object() {
+                    for (key, prop) in props {
+                        let prop = match properties.get(key) {
+                            Some(exist) => Self::merge(vec![exist.clone(), prop.clone()]),
+                            None => prop.clone(),
+                        };
+                        properties.insert(key.clone(), prop);
+                    }
+                }
+                let keys = schema["required"].as_array().cloned().unwrap_or_default();
+                required = Some(match required {
+                    Some(required) => required.into_iter().filter(|k| keys.contains(k)).collect(),
+                    None => keys,
+                });
+            }
+            json!({ "type": "object", "properties": properties, "required": required })
+        } else if types.iter().all(|ty| *ty == "integer" || *ty == "number") {
+            json!({ "type": "number" })
+        } else if types.iter().all(|ty| *ty == types[0]) {
+            json!({ "type": types[0] })
+        } else {
+            json!({})
+        }
+    }
+
+    fn apply_constraints(
+        schema: &mut Map<String, Value>,
+        anno: &FamlExprConstraintAnno,
+        value: &FamlValue,
+    ) -> anyhow::Result<()> {
+        let vars: HashMap<_, _> = [("value".to_string(), value.clone())].into();
+        let mut comments = vec![];
+        for constraint in &anno.constraints {
+            LocalScope::with(vars.clone(), || {
+                Self::apply_constraint(schema, constraint, value, &mut comments)
+            })?;
+        }
+        if !comments.is_empty() {
+            let comment = format!("constraint: {}", comments.join(" && "));
+            schema.insert("$comment".to_string(), comment.into());
+        }
+        Ok(())
+    }
+
+    fn apply_constraint(
+        schema: &mut Map<String, Value>,
+        constraint: &FamlExpr,
+        value: &FamlValue,
+        comments: &mut Vec<String>,
+    ) -> anyhow::Result<()> {
+        if let FamlExprImpl::Op2((left, op, right)) = &constraint.base().expr {
+            if op == "&&" {
+                Self::apply_constraint(schema, left, value, comments)?;
+                return Self::apply_constraint(schema, right, value, comments);
+            }
+            if let Some(n) = Self::multiple_of(left, op, right)? {
+                schema.insert("multipleOf".to_string(), Self::number(n));
+                return Ok(());
+            }
+            let keywords = match (Self::subject(left), Self::subject(right)) {
+                (Some(subject), None) => Self::translate(subject, op, right, value)?,
+                (None, Some(subject)) => match Self::flip(op) {
+                    Some(op) => Self::translate(subject, op, left, value)?,
+                    None => None,
+                },
+                _ => None,
+            };
+            if let Some(keywords) = keywords {
+                schema.extend(keywords);
+                return Ok(());
+            }
+        }
+        comments.push(constraint.trace_expr_str()?);
+        Ok(())
+    }
+
+    fn subject(expr: &FamlExpr) -> Option<&'static str> {
+        match &expr.base().expr {
+            FamlExprImpl::TempName(names) if names == &["value"] => Some("value"),
+            FamlExprImpl::InvokeFunc((func, args)) if args.is_empty() => match &func.base().expr {
+                FamlExprImpl::TempName(names) if names == &["len"] => Some("len"),
+                FamlExprImpl::TempName(names) if names == &["value", "len"] => Some("len"),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// `value % n == 0`
+    fn multiple_of(left: &FamlExpr, op: &str, right: &FamlExpr) -> anyhow::Result<Option<f64>> {
+        if let FamlExprImpl::Op2((a, mod_op, b)) = &left.base().expr
+            && mod_op == "%"
+            && op == "=="
+            && Self::subject(a) == Some("value")
+            && right.evaluate()?.as_float() == Some(0.0)
+        {
+            return Ok(b.evaluate()?.as_float().filter(|n| *n > 0.0));
+        }
+        Ok(None)
+    }
+
+    fn flip(op: &str) -> Option<&'static str> {
+        Some(match op {
+            "<" => ">",
+            "<=" => ">=",
+            ">" => "<",
+            ">=" => "<=",
+            "==" => "==",
+            "!=" => "!=",
+            _ => return None,
+        })
+    }
+
+    fn translate(
+        subject: &str,
+        op: &str,
+        other: &FamlExpr,
+        value: &FamlValue,
+    ) -> anyhow::Result<Option<Map<String, Value>>> {
+        let mut ret = Map::new();
+        if let FamlExprImpl::Range(range) = &other.base().expr {
+            let (start, end) = (range.start.evaluate()?, range.end.evaluate()?);
+            let (Some(start), Some(end)) = (start.as_float(), end.as_float()) else {
+                return Ok(None);
+            };
+            if subject != "value" || op != "in" || value.as_float().is_none() {
+                return Ok(None);
+            }
+            ret.insert("minimum".to_string(), Self::number(start));
+            let keyword = if range.inclusive {
+                "maximum"
+            } else {
+                "exclusiveMaximum"
+            };
+            ret.insert(keyword.to_string(), Self::number(end));
+            return Ok(Some(ret));
+        }
+        let other = other.evaluate()?;
+        match (subject, op, &other) {
+            ("value", "in", FamlValue::Array(items)) => {
+                let items: Vec<_> = items.iter().map(|item| item.to_json()).collect();
+                ret.insert("enum".to_string(), items.into());
+            }
+            ("value", "not in", FamlValue::Array(items)) => {
+                let items: Vec<_> = items.iter().map(|item| item.to_json()).collect();
+                ret.insert("not".to_string(), json!({ "enum": items }));
+            }
+            ("value", "==", _) => _ = ret.insert("const".to_string(), other.to_json()),
+            ("value", "!=", _) => {
+                _ = ret.insert("not".to_string(), json!({ "const": other.to_json() }))
+            }
+            ("value", _, _) if value.as_float().is_some() => {
+                let Some(n) = other.as_float() else {
+                    return Ok(None);
+                };
+                let keyword = match op {
+                    ">" => "exclusiveMinimum",
+                    ">=" => "minimum",
+                    "<" => "exclusiveMaximum",
+                    "<=" => "maximum",
+                    _ => return Ok(None),
+                };
+                ret.insert(keyword.to_string(), Self::number(n));
+            }
+            ("len", _, FamlValue::Int64(n)) => {
+                let (min_keyword, max_keyword) = match value {
+                    FamlValue::String(_) => ("minLength", "maxLength"),
+                    FamlValue::Array(_) => ("minItems", "maxItems"),
+                    FamlValue::Map(_) => ("minProperties", "maxProperties"),
+                    _ => return Ok(None),
+                };
+                let n = *n;
+                let bounds = match op {
+                    ">" => (Some(n + 1), None),
+                    ">=" => (Some(n), None),
+                    "<" => (None, Some(n - 1)),
+                    "<=" => (None, Some(n)),
+                    "==" => (Some(n), Some(n)),
+                    _ => return Ok(None),
+                };
+                if let Some(min) = bounds.0 {
+                    ret.insert(min_keyword.to_string(), min.max(0).into());
+                }
+                if let Some(max) = bounds.1 {
+                    ret.insert(max_keyword.to_string(), max.max(0).into());
+                }
+            }
+            _ => return Ok(None),
+        }
+        Ok(Some(ret))
+    }
+
+    fn number(n: f64) -> Value {
+        match n.fract() == 0.0 && n.abs() < i64::MAX as f64 {
+            true => (n as i64).into(),
+            false => n.into(),
+        }
+    }
+}
+
+struct JsonSchemaValidator<'a> {
+    root: &'a Value,
+    errors: Vec<String>,
+}
+
+impl<'a> JsonSchemaValidator<'a> {
+    fn check_keywords(schema: &Value, pointer: &str, errors: &mut Vec<String>) {
+        let Value::Object(obj) = schema else {
+            return;
+        };
+        let is_root = pointer == "#";
+        for (keyword, value) in obj {
+            let sub_pointer = format!("{pointer}/{keyword}");
+            match &keyword[..] {
+                "$schema" | "$id" if is_root => (),
+                "$ref" => match value.as_str() {
+                    Some(reference) if reference.starts_with('#') => (),
+                    _ => errors.push(format!("{sub_pointer}: only local $ref is supported")),
+                },
+                "properties" | "patternProperties" | "$defs" | "definitions" => {
+                    for (key, schema) in value.as_object().into_iter().flatten() {
+                        Self::check_keywords(schema, &format!("{sub_pointer}/{key}"), errors);
+                    }
+                }
+                "allOf" | "anyOf" | "oneOf" | "prefixItems" | "items" if value.is_array() => {
+                    for (idx, schema) in value.as_array().into_iter().flatten().enumerate() {
+                        Self::check_keywords(schema, &format!("{sub_pointer}/{idx}"), errors);
+                    }
+                }
+                "items" | "additionalProperties" | "contains" | "not" | "if" | "then" | "else" => {
+                    Self::check_keywords(value, &sub_pointer, errors)
+                }
+                keyword if SUPPORTED_KEYWORDS.contains(&keyword) => (),
+                keyword => errors.push(format!("{pointer}: keyword[{keyword}] is not supported")),
+            }
+        }
+    }
+
+    fn validate(&mut self, schema: &'a Value, inst: &Value, path: &str) {
+        let obj = match schema {
+            Value::Bool(true) => return,
+            Value::Bool(false) => return self.error(path, "no value is allowed".to_string()),
+            Value::Object(obj) => obj,
+            _ => return,
+        };
+        if let Some(Value::String(reference)) = obj.get("$ref") {
+            match reference
+                .strip_prefix('#')
+                .and_then(|pointer| self.root.pointer(pointer))
+            {
+                Some(target) => self.validate(target, inst, path),
+                None => self.error(path, format!("unresolved $ref[{reference}]")),
+            }
+        }
+        if let Some(ty) = obj.get("type") {
+            let types: Vec<_> = match ty {
+                Value::Array(types) => types.iter().filter_map(|ty| ty.as_str()).collect(),
+                ty => ty.as_str().into_iter().collect(),
+            };
+            if !types.iter().any(|ty| Self::is_type(inst, ty)) {
+                let msg = format!(
+                    "expected type[{}], got {}",
+                    types.join("|"),
+                    Self::describe(inst)
+                );
+                return self.error(path, msg);
+            }
+        }
+        if let Some(Value::Array(items)) = obj.get("enum")
+            && !items.iter().any(|item| Self::equals(item, inst))
+        {
+            let items: Vec<_> = items.iter().map(|item| item.to_string()).collect();
+            self.error(
+                path,
+                format!("value[{inst}] is not one of [{}]", items.join(", ")),
+            );
+        }
+        if let Some(item) = obj.get("const")
+            && !Self::equals(item, inst)
+        {
+            self.error(path, format!("value[{inst}] is not equal to {item}"));
+        }
+        self.validate_number(obj, inst, path);
+        self.validate_string(obj, inst, path);
+        self.validate_array(obj, inst, path);
+        self.validate_object(obj, inst, path);
+        self.validate_combinators(obj, inst, path);
+    }
+
+    fn validate_number(&mut self, obj: &Map<String, Value>, inst: &Value, path: &str) {
+        let Some(n) = inst.as_f64() else {
+            return;
+        };
+        let checks: [NumberCheck; 4] = [
+            ("minimum", |n, m| n >= m, ">="),
+            ("maximum", |n, m| n <= m, "<="),
+            ("exclusiveMinimum", |n, m| n > m, ">"),
+            ("exclusiveMaximum", |n, m| n < m, "<"),
+        ];
+        for (keyword, check, op) in checks {
+            if let Some(m) = obj.get(keyword).and_then(|m| m.as_f64())
+                && !check(n, m)
+            {
+                self.error(path, format!("value[{inst}] is not {op} {m}"));
+            }
+        }
+        if let Some(m) = obj.get("multipleOf").and_then(|m| m.as_f64())
+            && (n / m).fract() != 0.0
+        {
+            self.error(path, format!("value[{inst}] is not a multiple of {m}"));
+        }
+    }
+
+    fn validate_string(&mut self, obj: &Map<String, Value>, inst: &Value, path: &str) {
+        let Some(s) = inst.as_str() else {
+            return;
+        };
+        let len = s.chars().count() as u64;
+        if let Some(min) = obj.get("minLength").and_then(|m| m.as_u64())
+            && len < min
+        {
+            self.error(path, format!("length {len} is shorter than {min}"));
+        }
+        if let Some(max) = obj.get("maxLength").and_then(|m| m.as_u64())
+            && len > max
+        {
+            self.error(path, format!("length {len} is longer than {max}"));
+        }
+        if let Some(pattern) = obj.get("pattern").and_then(|p| p.as_str()) {
+            match regex::Regex::new(pattern) {
+                Ok(re) if re.is_match(s) => (),
+                Ok(_) => self.error(
+                    path,
+                    format!("value[{s}] does not match pattern[{pattern}]"),
+                ),
+                Err(err) => self.error(path, format!("invalid pattern[{pattern}]: {err}")),
+            }
+        }
+    }
+
+    fn validate_array(&mut self, obj: &'a Map<String, Value>, inst: &Value, path: &str) {
+        let Some(items) = inst.as_array() else {
+            return;
+        };
+        let len = items.len() as u64;
+        if let Some(min) = obj.get("minItems").and_then(|m| m.as_u64())
+            && len < min
+        {
+            self.error(path, format!("array has {len} items, fewer than {min}"));
+        }
+        if let Some(max) = obj.get("maxItems").and_then(|m| m.as_u64())
+            && len > max
+        {
+            self.error(path, format!("array has {len} items, more than {max}"));
+        }
+        if obj.get("uniqueItems") == Some(&Value::Bool(true)) {
+            for (idx, item) in items.iter().enumerate() {
+                if items[..idx].iter().any(|prev| Self::equals(prev, item)) {
+                    self.error(path, format!("item {item} is not unique"));
+                    break;
+                }
+            }
+        }
+        let prefix_len = match obj.get("prefixItems") {
+            Some(Value::Array(schemas)) => {
+                for (idx, (schema, item)) in schemas.iter().zip(items).enumerate() {
+                    self.validate(schema, item, &format!("{path}[{idx}]"));
+                }
+                schemas.len()
+            }
+            _ => 0,
+        };
+        match obj.get("items") {
+            Some(Value::Array(schemas)) => {
+                for (idx, (schema, item)) in schemas.iter().zip(items).enumerate() {
+                    self.validate(schema, item, &format!("{path}[{idx}]"));
+                }
+            }
+            Some(schema) => {
+                for (idx, item) in items.iter().enumerate().skip(prefix_len) {
+                    self.validate(schema, item, &format!("{path}[{idx}]"));
+                }
+            }
+            None => (),
+        }
+        if let Some(schema) = obj.get("contains")
+            && !items.iter().any(|item| self.is_valid(schema, item))
+        {
+            self.error(path, "array does not contain a matching item".to_string());
+        }
+    }
+
+    fn validate_object(&mut self, obj: &'a Map<String, Value>, inst: &Value, path: &str) {
+        let Some(map) = inst.as_object() else {
+            return;
+        };
+        let len = map.len() as u64;
+        if let Some(min) = obj.get("minProperties").and_then(|m| m.as_u64())
+            && len < min
+        {
+            self.error(
+                path,
+                format!("object has {len} properties, fewer than {min}"),
+            );
+        }
+        if let Some(max) = obj.get("maxProperties").and_then(|m| m.as_u64())
+            && len > max
+        {
+            self.error(
+                path,
+                format!("object has {len} properties, more than {max}"),
+            );
+        }
+        if let Some(Value::Array(required)) = obj.get("required") {
+            for key in required.iter().filter_map(|key| key.as_str()) {
+                if !map.contains_key(key) {
+                    self.error(
+                        &Self::join(path, key),
+                        "required property is missing".to_string(),
+                    );
+                }
+            }
+        }
+        let properties = obj.get("properties").and_then(|p| p.as_object());
+        let mut pattern_properties = vec![];
+        if let Some(Value::Object(patterns)) = obj.get("patternProperties") {
+            for (pattern, schema) in patterns {
+                match regex::Regex::new(pattern) {
+                    Ok(re) => pattern_properties.push((re, schema)),
+                    Err(err) => self.error(path, format!("invalid pattern[{pattern}]: {err}")),
+                }
+            }
+        }
+        for (key, item) in map {
+            let item_path = Self::join(path, key);
+            let mut matched = false;
+            if let Some(schema) = properties.and_then(|p| p.get(key)) {
+                self.validate(schema, item, &item_path);
+                matched = true;
+            }
+            for (re, schema) in &pattern_properties {
+                if re.is_match(key) {
+                    self.validate(schema, item, &item_path);
+                    matched = true;
+                }
+            }
+            if !matched {
+                match obj.get("additionalProperties") {
+                    Some(Value::Bool(false)) => {
+                        self.error(&item_path, "additional property is not allowed".to_string())
+                    }
+                    Some(schema) => self.validate(schema, item, &item_path),
+                    None => (),
+                }
+            }
+        }
+    }
+
+    fn validate_combinators(&mut self, obj: &'a Map<String, Value>, inst: &Value, path: &str) {
+        if let Some(Value::Array(schemas)) = obj.get("allOf") {
+            for schema in schemas {
+                self.validate(schema, inst, path);
+            }
+        }
+        if let Some(Value::Array(schemas)) = obj.get("anyOf")
+            && !schemas.iter().any(|schema| self.is_valid(schema, inst))
+        {
+            self.error(path, "value does not match any schema of anyOf".to_string());
+        }
+        if let Some(Value::Array(schemas)) = obj.get("oneOf") {
+            let count = schemas
+                .iter()
+                .filter(|schema| self.is_valid(schema, inst))
+                .count();
+            if count != 1 {
+                self.error(path, format!("value matches {count} schemas of oneOf"));
+            }
+        }
+        if let Some(schema) = obj.get("not")
+            && self.is_valid(schema, inst)
+        {
+            self.error(path, format!("value[{inst}] matches the schema of not"));
+        }
+        if let Some(schema) = obj.get("if") {
+            let branch = match self.is_valid(schema, inst) {
+                true => obj.get("then"),
+                false => obj.get("else"),
+            };
+            if let Some(branch) = branch {
+                self.validate(branch, inst, path);
+            }
+        }
+    }
+
+    fn is_valid(&self, schema: &'a Value, inst: &Value) -> bool {
+        let mut validator = JsonSchemaValidator {
+            root: self.root,
+            errors: vec![],
+        };
+        validator.validate(schema, inst, "");
+        validator.errors.is_empty()
+    }
+
+    fn is_type(inst: &Value, ty: &str) -> bool {
+        match ty {
+            "null" => inst.is_null(),
+            "boolean" => inst.is_boolean(),
+            "integer" => inst.as_f64().is_some_and(|n| n.fract() == 0.0),
+            "number" => inst.is_number(),
+            "string" => inst.is_string(),
+            "array" => inst.is_array(),
+            "object" => inst.is_object(),
+            _ => false,
+        }
+    }
+
+    fn describe(inst: &Value) -> String {
+        let ty = match inst {
+            Value::Null => "null",
+            Value::Bool(_) => "boolean",
+            Value::Number(n) if n.is_f64() => "number",
+            Value::Number(_) => "integer",
+            Value::String(_) => "string",
+            Value::Array(_) => "array",
+            Value::Object(_) => "object",
+        };
+        format!("type[{ty}]")
+    }
+
+    fn equals(a: &Value, b: &Value) -> bool {
+        match (a.as_f64(), b.as_f64()) {
+            (Some(a), Some(b)) => a == b,
+            _ => a == b,
+        }
+    }
+
+    fn join(path: &str, key: &str) -> String {
+        match path.is_empty() {
+            true => key.to_string(),
+            false => format!("{path}.{key}"),
+        }
+    }
+
+    fn error(&mut self, path: &str, msg: String) {
+        let path = if path.is_empty() { "(root)" } else { path };
+        self.errors.push(format!("{path}: {msg}"));
+    }
+}
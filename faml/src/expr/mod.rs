@@ -1,4 +1,10 @@
+mod async_eval;
+mod check;
 pub mod eval;
 pub mod faml_expr;
+pub mod faml_type;
 pub mod faml_value;
+pub mod format_spec;
 pub mod invoke;
+mod json_schema;
+mod scope;
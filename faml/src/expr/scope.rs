@@ -0,0 +1,52 @@
+use crate::FamlValue;
+use anyhow::anyhow;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+thread_local! {
+    // `None` frames isolate the locals of the caller, so that a referenced field never
+    // observes the loop/pattern variables of the expression that referenced it.
+    static LOCAL_SCOPES: RefCell<Vec<Option<HashMap<String, FamlValue>>>> = const { RefCell::new(vec![]) };
+}
+
+pub(crate) struct LocalScope {}
+
+impl LocalScope {
+    pub fn with<R>(vars: HashMap<String, FamlValue>, f: impl FnOnce() -> R) -> R {
+        Self::push(Some(vars), f)
+    }
+
+    pub fn isolate<R>(f: impl FnOnce() -> R) -> R {
+        Self::push(None, f)
+    }
+
+    fn push<R>(frame: Option<HashMap<String, FamlValue>>, f: impl FnOnce() -> R) -> R {
+        LOCAL_SCOPES.with(|scopes| scopes.borrow_mut().push(frame));
+        let ret = f();
+        LOCAL_SCOPES.with(|scopes| scopes.borrow_mut().pop());
+        ret
+    }
+
+    pub fn get(names: &[String]) -> Option<anyhow::Result<FamlValue>> {
+        let name = names.first()?;
+        let mut val = LOCAL_SCOPES.with(|scopes| {
+            for frame in scopes.borrow().iter().rev() {
+                match frame {
+                    Some(frame) => match frame.get(name) {
+                        Some(val) => return Some(val.clone()),
+                        None => continue,
+                    },
+                    None => return None,
+                }
+            }
+            None
+        })?;
+        for name in &names[1..] {
+            val = match val.get(name) {
+                Some(val) => val.clone(),
+                None => return Some(Err(anyhow!("node has no field1[{name}]"))),
+            };
+        }
+        Some(Ok(val))
+    }
+}
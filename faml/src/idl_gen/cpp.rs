@@ -0,0 +1,131 @@
+use super::{IDLModel, IDLType};
+use std::collections::HashSet;
+
+const KEYWORDS: &str = "\
+    alignas alignof and asm auto bool break case catch char class const constexpr continue \
+    decltype default delete do double else enum explicit export extern false float for \
+    friend goto if inline int long mutable namespace new noexcept not nullptr operator or \
+    private protected public register return short signed sizeof static struct switch \
+    template this throw true try typedef typename union unsigned using virtual void";
+
+pub(crate) struct CppGenerator {}
+
+impl CppGenerator {
+    pub fn generate(model: &IDLModel) -> String {
+        let mut s = "// Generated by faml IDLGenerator, do not edit.\n".to_string();
+        s.push_str("#pragma once\n\n");
+        for header in [
+            "chrono", "cstdint", "format", "optional", "string", "variant", "vector",
+        ] {
+            s.push_str(&format!("#include <{header}>\n"));
+        }
+        s.push_str("\n#include \"faml/faml.hpp\"\n");
+        let root_name = &model.structs.last().unwrap().name;
+        for st in &model.structs {
+            s.push_str(&format!("\nclass {} {{\npublic:\n", st.name));
+            s.push_str(&format!(
+                "  explicit {}(faml::FamlValue value) : value_(value) {{}}\n",
+                st.name
+            ));
+            if &st.name == root_name {
+                s.push_str(&Self::gen_from_str(&st.name));
+            }
+            s.push('\n');
+            let mut names = HashSet::new();
+            for field in &st.fields {
+                let name = Self::field_name(&field.key, &mut names);
+                let value = format!("value_[{:?}]", field.key);
+                let ty = Self::type_str(&field.ty);
+                let value = Self::accessor(&field.ty, &value, 0);
+                s.push_str(&format!("  {ty} {name}() {{ return {value}; }}\n"));
+            }
+            s.push_str("\nprivate:\n  faml::FamlValue value_;\n};\n");
+        }
+        s
+    }
+
+    fn gen_from_str(name: &str) -> String {
+        let mut s = format!(
+            "\n  inline static std::variant<{name}, std::string>\n  from_str(const std::string &str) {{\n"
+        );
+        s.push_str("    auto expr = faml::FamlExpr::from_str(str);\n");
+        s.push_str("    if (auto err = std::get_if<std::string>(&expr))\n      return *err;\n");
+        s.push_str("    auto value = std::get<faml::FamlExpr>(expr).evaluate();\n");
+        s.push_str("    if (auto err = std::get_if<std::string>(&value))\n      return *err;\n");
+        s.push_str(&format!(
+            "    return {name}(std::get<faml::FamlValue>(value));\n  }}\n"
+        ));
+        s
+    }
+
+    fn type_str(ty: &IDLType) -> String {
+        match ty {
+            IDLType::Bool => "bool".to_string(),
+            IDLType::Int => "int64_t".to_string(),
+            IDLType::Float | IDLType::Distance => "double".to_string(),
+            IDLType::String | IDLType::Enum(_) => "std::string".to_string(),
+            IDLType::Duration => "std::chrono::nanoseconds".to_string(),
+            IDLType::Any => "faml::FamlValue".to_string(),
+            IDLType::Option(ty) => format!("std::optional<{}>", Self::type_str(ty)),
+            IDLType::Array(ty) => format!("std::vector<{}>", Self::type_str(ty)),
+            IDLType::Struct(name) => name.clone(),
+        }
+    }
+
+    fn accessor(ty: &IDLType, value: &str, depth: usize) -> String {
+        match ty {
+            IDLType::Bool => format!("{value}.as_bool()"),
+            IDLType::Int => format!("{value}.as_int()"),
+            IDLType::Float => format!("{value}.as_float()"),
+            IDLType::String | IDLType::Enum(_) => format!("{value}.as_str()"),
+            IDLType::Duration => format!("{value}.as_duration()"),
+            IDLType::Distance => format!("{value}.as_distance()"),
+            IDLType::Any => value.to_string(),
+            IDLType::Option(ty) => format!(
+                "{} ? {}({}) : std::nullopt",
+                Self::checker(ty, value),
+                Self::type_str(&IDLType::Option(ty.clone())),
+                Self::accessor(ty, value, depth)
+            ),
+            IDLType::Array(ty) => {
+                let (ret, arr, idx) = (
+                    format!("ret{depth}"),
+                    format!("arr{depth}"),
+                    format!("i{depth}"),
+                );
+                let item = Self::accessor(
+                    ty,
+                    &format!("{arr}[std::format(\"[{{}}]\", {idx})]"),
+                    depth + 1,
+                );
+                format!(
+                    "[&] {{ {} {ret}; auto {arr} = {value}; for (int32_t {idx} = 0; {idx} < {arr}.get_array_length(); {idx}++) {ret}.push_back({item}); return {ret}; }}()",
+                    Self::type_str(&IDLType::Array(ty.clone())),
+                )
+            }
+            IDLType::Struct(name) => format!("{name}({value})"),
+        }
+    }
+
+    fn checker(ty: &IDLType, value: &str) -> String {
+        match ty {
+            IDLType::Bool => format!("{value}.is_bool()"),
+            IDLType::Int => format!("{value}.is_int()"),
+            IDLType::Float => format!("({value}.is_float() || {value}.is_int())"),
+            IDLType::String | IDLType::Enum(_) => format!("{value}.is_str()"),
+            IDLType::Duration => format!("{value}.is_duration()"),
+            IDLType::Distance => format!("{value}.is_distance()"),
+            IDLType::Array(_) => format!("{value}.is_array()"),
+            IDLType::Struct(_) => format!("{value}.is_map()"),
+            IDLType::Any | IDLType::Option(_) => format!("!{value}.is_none()"),
+        }
+    }
+
+    fn field_name(key: &str, names: &mut HashSet<String>) -> String {
+        let name = super::field_name(key, names);
+        match KEYWORDS.split_whitespace().any(|kw| kw == name) {
+            true => format!("{name}_"),
+            false => name,
+        }
+    }
+}
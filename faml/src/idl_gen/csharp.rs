@@ -0,0 +1,106 @@
+use super::{IDLModel, IDLType};
+use std::collections::HashSet;
+
+const KEYWORDS: &str = "\
+    abstract as base bool break byte case catch char checked class const continue decimal \
+    default delegate do double else enum event explicit extern false finally fixed float for \
+    foreach goto if implicit in int interface internal is lock long namespace new null \
+    object operator out override params private protected public readonly ref return sbyte \
+    sealed short sizeof stackalloc static string struct switch this throw true try typeof \
+    uint ulong unchecked unsafe ushort using virtual void volatile while";
+
+pub(crate) struct CSharpGenerator {}
+
+impl CSharpGenerator {
+    pub fn generate(model: &IDLModel) -> String {
+        let mut s = "// Generated by faml IDLGenerator, do not edit.\n".to_string();
+        s.push_str("using faml;\n");
+        let root_name = &model.structs.last().unwrap().name;
+        for st in &model.structs {
+            s.push_str(&format!("\npublic class {}\n{{\n", st.name));
+            s.push_str(&format!(
+                "    public {}(FamlValue pval) => this.pval = pval;\n",
+                st.name
+            ));
+            if &st.name == root_name {
+                s.push_str(&format!(
+                    "    public static {0} from_str(string src) => new {0}(FamlExpr.from_str(src).evaluate());\n",
+                    st.name
+                ));
+            }
+            s.push('\n');
+            let mut names = HashSet::from(["pval".to_string()]);
+            for field in &st.fields {
+                let name = Self::field_name(&field.key, &mut names);
+                let pval = format!("pval[{:?}]", field.key);
+                let ty = Self::type_str(&field.ty);
+                let value = Self::accessor(&field.ty, &pval, 0);
+                s.push_str(&format!("    public {ty} {name} => {value};\n"));
+            }
+            s.push_str("\n    private FamlValue pval;\n}\n");
+        }
+        s
+    }
+
+    fn type_str(ty: &IDLType) -> String {
+        match ty {
+            IDLType::Bool => "bool".to_string(),
+            IDLType::Int => "long".to_string(),
+            IDLType::Float | IDLType::Distance => "double".to_string(),
+            IDLType::String | IDLType::Enum(_) => "string".to_string(),
+            IDLType::Duration => "TimeSpan".to_string(),
+            IDLType::Any => "FamlValue".to_string(),
+            IDLType::Option(ty) => format!("{}?", Self::type_str(ty)),
+            IDLType::Array(ty) => format!("List<{}>", Self::type_str(ty)),
+            IDLType::Struct(name) => name.clone(),
+        }
+    }
+
+    fn accessor(ty: &IDLType, pval: &str, depth: usize) -> String {
+        match ty {
+            IDLType::Bool => format!("{pval}.as_bool()"),
+            IDLType::Int => format!("{pval}.as_int()"),
+            IDLType::Float => format!("{pval}.as_float()"),
+            IDLType::String | IDLType::Enum(_) => format!("{pval}.as_str()"),
+            IDLType::Duration => format!("{pval}.as_duration()"),
+            IDLType::Distance => format!("{pval}.as_distance()"),
+            IDLType::Any => pval.to_string(),
+            IDLType::Option(ty) => format!(
+                "{} ? ({}?){} : null",
+                Self::checker(ty, pval),
+                Self::type_str(ty),
+                Self::accessor(ty, pval, depth)
+            ),
+            IDLType::Array(ty) => {
+                let idx = format!("i{depth}");
+                let item = Self::accessor(ty, &format!("{pval}[{idx}]"), depth + 1);
+                format!(
+                    "Enumerable.Range(0, {pval}.get_array_length()).Select({idx} => {item}).ToList()"
+                )
+            }
+            IDLType::Struct(name) => format!("new {name}({pval})"),
+        }
+    }
+
+    fn checker(ty: &IDLType, pval: &str) -> String {
+        match ty {
+            IDLType::Bool => format!("{pval}.is_bool()"),
+            IDLType::Int => format!("{pval}.is_int()"),
+            IDLType::Float => format!("({pval}.is_float() || {pval}.is_int())"),
+            IDLType::String | IDLType::Enum(_) => format!("{pval}.is_str()"),
+            IDLType::Duration => format!("{pval}.is_duration()"),
+            IDLType::Distance => format!("{pval}.is_distance()"),
+            IDLType::Array(_) => format!("{pval}.is_array()"),
+            IDLType::Struct(_) => format!("{pval}.is_map()"),
+            IDLType::Any | IDLType::Option(_) => format!("!{pval}.is_none()"),
+        }
+    }
+
+    fn field_name(key: &str, names: &mut HashSet<String>) -> String {
+        let name = super::field_name(key, names);
+        match KEYWORDS.split_whitespace().any(|kw| kw == name) {
+            true => format!("@{name}"),
+            false => name,
+        }
+    }
+}
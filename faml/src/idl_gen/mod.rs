@@ -1,16 +1,313 @@
-use crate::{FamlExpr, FamlExprImpl, string_utils::IntoBaseExt};
+mod cpp;
+mod csharp;
+mod rust;
+mod typescript;
+
+use crate::expr::faml_type::FamlType;
+use crate::{FamlExpr, FamlExprImpl, FamlValue, string_utils::IntoBaseExt};
+use anyhow::anyhow;
+use indexmap::IndexSet;
+use std::collections::{HashMap, HashSet};
 
 pub struct IDLGenerator {}
 
 impl IDLGenerator {
+    /// Generate `serde::Deserialize` structs for a config, the root struct is named `Config`.
     pub fn gen_rust(expr: &FamlExpr) -> anyhow::Result<String> {
-        let mut s = "".to_string();
-        if let FamlExprImpl::Map(map) = &expr.base().expr {
-            for (group_name, group) in map {
-                s.push_str(&format!("pub struct {} {{\n", group_name.to_pascal_case()));
-                s.push_str("}\n\n");
+        let model = IDLModel::infer(expr, "Config")?;
+        Ok(rust::RustGenerator::generate(&model))
+    }
+
+    /// Generate C# classes with typed accessors over `faml.FamlValue`.
+    pub fn gen_csharp(expr: &FamlExpr) -> anyhow::Result<String> {
+        let model = IDLModel::infer(expr, "Config")?;
+        Ok(csharp::CSharpGenerator::generate(&model))
+    }
+
+    /// Generate C++ classes with typed accessors over `faml::FamlValue`.
+    pub fn gen_cpp(expr: &FamlExpr) -> anyhow::Result<String> {
+        let model = IDLModel::infer(expr, "Config")?;
+        Ok(cpp::CppGenerator::generate(&model))
+    }
+
+    /// Generate TypeScript interfaces matching the JSON of an evaluated config.
+    pub fn gen_typescript(expr: &FamlExpr) -> anyhow::Result<String> {
+        let model = IDLModel::infer(expr, "Config")?;
+        Ok(typescript::TypeScriptGenerator::generate(&model))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum IDLType {
+    Bool,
+    Int,
+    Float,
+    String,
+    Duration,
+    Distance,
+    Any,
+    Option(Box<IDLType>),
+    Array(Box<IDLType>),
+    Struct(String),
+    Enum(String),
+}
+
+impl IDLType {
+    pub fn has_unit(&self) -> bool {
+        self.has(&|ty| matches!(ty, IDLType::Duration | IDLType::Distance))
+    }
+
+    pub fn has_distance(&self) -> bool {
+        self.has(&|ty| matches!(ty, IDLType::Distance))
+    }
+
+    fn has(&self, f: &impl Fn(&IDLType) -> bool) -> bool {
+        match self {
+            IDLType::Option(ty) | IDLType::Array(ty) => ty.has(f),
+            ty => f(ty),
+        }
+    }
+}
+
+/// Snake case field name that is unique in `names`, keywords of the target language are not handled.
+pub(crate) fn field_name(key: &str, names: &mut HashSet<String>) -> String {
+    let mut name = match key.to_snake_case() {
+        s if s.is_empty() => "field".to_string(),
+        s if s.starts_with(|c: char| c.is_numeric()) => format!("_{s}"),
+        s => s,
+    };
+    if names.contains(&name) {
+        let idx = (2..).find(|idx| !names.contains(&format!("{name}_{idx}")));
+        name = format!("{name}_{}", idx.unwrap());
+    }
+    names.insert(name.clone());
+    name
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct IDLField {
+    pub key: String,
+    pub ty: IDLType,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct IDLStruct {
+    pub name: String,
+    pub fields: Vec<IDLField>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct IDLEnum {
+    pub name: String,
+    pub variants: Vec<String>,
+}
+
+/// Types inferred from an evaluated config, nested structs come before the structs using them.
+/// Fields with declared types use them instead of inferring from values.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct IDLModel {
+    pub enums: Vec<IDLEnum>,
+    pub structs: Vec<IDLStruct>,
+    names: HashSet<String>,
+    declared: HashMap<String, String>,
+}
+
+impl IDLModel {
+    pub fn infer(expr: &FamlExpr, root_name: &str) -> anyhow::Result<Self> {
+        let value = expr.evaluate()?;
+        if !matches!(value, FamlValue::Map(_)) {
+            return Err(anyhow!("only type[map] can be generated as struct"));
+        }
+        let mut model = IDLModel::default();
+        model.infer_struct(root_name, &[&value], true, Some(expr));
+        Ok(model)
+    }
+
+    fn infer_expr(
+        &mut self,
+        name: &str,
+        expr: &FamlExpr,
+        values: &[Option<&FamlValue>],
+    ) -> IDLType {
+        if let Some(ty) = expr.declared_type() {
+            return self.declare(name, ty);
+        }
+        match (&expr.base().expr, values) {
+            (FamlExprImpl::Map(_), [Some(value @ FamlValue::Map(_))]) => {
+                self.infer_struct(name, &[value], false, Some(expr))
+            }
+            (FamlExprImpl::Array(items), _) => {
+                let item_ty = items.first().and_then(|item| item.declared_type());
+                match item_ty {
+                    Some(ty) if items.iter().all(|item| item.declared_type() == Some(ty)) => {
+                        IDLType::Array(Box::new(self.declare(&format!("{name}Item"), ty)))
+                    }
+                    _ => self.infer_values(name, values),
+                }
+            }
+            _ => self.infer_values(name, values),
+        }
+    }
+
+    fn declare(&mut self, name: &str, ty: &FamlType) -> IDLType {
+        match ty {
+            FamlType::Bool => IDLType::Bool,
+            FamlType::Int => IDLType::Int,
+            FamlType::Float => IDLType::Float,
+            FamlType::String => IDLType::String,
+            FamlType::Duration => IDLType::Duration,
+            FamlType::Distance => IDLType::Distance,
+            FamlType::Any => IDLType::Any,
+            FamlType::Option(ty) => match self.declare(name, ty) {
+                IDLType::Any => IDLType::Any,
+                ty => IDLType::Option(Box::new(ty)),
+            },
+            FamlType::Array(ty) => {
+                IDLType::Array(Box::new(self.declare(&format!("{name}Item"), ty)))
+            }
+            FamlType::Enum((type_name, variants)) => {
+                if let Some(name) = self.declared.get(type_name) {
+                    return IDLType::Enum(name.clone());
+                }
+                let name = self.unique_name(type_name);
+                self.declared.insert(type_name.clone(), name.clone());
+                self.enums.push(IDLEnum {
+                    name: name.clone(),
+                    variants: variants.clone(),
+                });
+                IDLType::Enum(name)
+            }
+            FamlType::Struct((type_name, fields)) => {
+                if let Some(name) = self.declared.get(type_name) {
+                    return IDLType::Struct(name.clone());
+                }
+                let name = match type_name.is_empty() {
+                    true => self.unique_name(name),
+                    false => self.unique_name(type_name),
+                };
+                let mut ret = vec![];
+                for (key, ty) in fields {
+                    let ty = self.declare(&format!("{name}{}", key.to_pascal_case()), ty);
+                    ret.push(IDLField {
+                        key: key.clone(),
+                        ty,
+                    });
+                }
+                if !type_name.is_empty() {
+                    self.declared.insert(type_name.clone(), name.clone());
+                }
+                self.structs.push(IDLStruct {
+                    name: name.clone(),
+                    fields: ret,
+                });
+                IDLType::Struct(name)
+            }
+        }
+    }
+
+    fn infer_values(&mut self, name: &str, values: &[Option<&FamlValue>]) -> IDLType {
+        let mut optional = false;
+        let mut present = vec![];
+        for value in values {
+            match value {
+                None | Some(FamlValue::None) => optional = true,
+                Some(value) => present.push(*value),
             }
         }
-        todo!()
+        let ty = if present.is_empty() {
+            IDLType::Any
+        } else if present.iter().all(|v| matches!(v, FamlValue::Map(_))) {
+            self.infer_struct(name, &present, false, None)
+        } else if present.iter().all(|v| matches!(v, FamlValue::Array(_))) {
+            let mut items = vec![];
+            for value in &present {
+                if let FamlValue::Array(arr) = value {
+                    items.extend(arr.iter().map(Some));
+                }
+            }
+            let item_ty = self.infer_values(&format!("{name}Item"), &items);
+            IDLType::Array(Box::new(item_ty))
+        } else {
+            present
+                .iter()
+                .map(|v| Self::scalar_type(v))
+                .reduce(|a, b| match (a, b) {
+                    (a, b) if a == b => a,
+                    (IDLType::Int, IDLType::Float) | (IDLType::Float, IDLType::Int) => {
+                        IDLType::Float
+                    }
+                    _ => IDLType::Any,
+                })
+                .unwrap_or(IDLType::Any)
+        };
+        match (optional, ty) {
+            (true, IDLType::Any) => IDLType::Any,
+            (true, ty) => IDLType::Option(Box::new(ty)),
+            (false, ty) => ty,
+        }
+    }
+
+    fn infer_struct(
+        &mut self,
+        name: &str,
+        maps: &[&FamlValue],
+        is_root: bool,
+        expr: Option<&FamlExpr>,
+    ) -> IDLType {
+        let exprs = match expr.map(|expr| &expr.base().expr) {
+            Some(FamlExprImpl::Map(exprs)) => Some(exprs),
+            _ => None,
+        };
+        let name = self.unique_name(name);
+        let maps: Vec<_> = maps
+            .iter()
+            .filter_map(|map| match map {
+                FamlValue::Map(map) => Some(map),
+                _ => None,
+            })
+            .collect();
+        let keys: IndexSet<_> = maps.iter().flat_map(|map| map.keys().cloned()).collect();
+        let prefix = if is_root { "" } else { &name[..] };
+        let mut fields = vec![];
+        for key in keys {
+            let values: Vec<_> = maps.iter().map(|map| map.get(&key)).collect();
+            let child_name = format!("{prefix}{}", key.to_pascal_case());
+            let ty = match exprs.and_then(|exprs| exprs.get(&key)) {
+                Some(expr) => self.infer_expr(&child_name, expr, &values),
+                None => self.infer_values(&child_name, &values),
+            };
+            fields.push(IDLField { key, ty });
+        }
+        self.structs.push(IDLStruct {
+            name: name.clone(),
+            fields,
+        });
+        IDLType::Struct(name)
+    }
+
+    fn scalar_type(value: &FamlValue) -> IDLType {
+        match value {
+            FamlValue::Bool(_) => IDLType::Bool,
+            FamlValue::Int64(_) => IDLType::Int,
+            FamlValue::Float64(_) => IDLType::Float,
+            FamlValue::String(_) => IDLType::String,
+            FamlValue::Duration(_) => IDLType::Duration,
+            FamlValue::Distance(_) => IDLType::Distance,
+            _ => IDLType::Any,
+        }
+    }
+
+    fn unique_name(&mut self, name: &str) -> String {
+        let mut name = match name.to_pascal_case() {
+            s if s.is_empty() => "Item".to_string(),
+            s if s.starts_with(|c: char| c.is_numeric()) => format!("T{s}"),
+            s => s,
+        };
+        if self.names.contains(&name) {
+            let idx = (2..).find(|idx| !self.names.contains(&format!("{name}{idx}")));
+            name = format!("{name}{}", idx.unwrap());
+        }
+        self.names.insert(name.clone());
+        name
     }
 }
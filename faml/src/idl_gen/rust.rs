@@ -0,0 +1,93 @@
+use super::{IDLModel, IDLType};
+use crate::string_utils::IntoBaseExt;
+use std::collections::HashSet;
+
+const KEYWORDS: [&str; 38] = [
+    "as", "async", "await", "break", "const", "continue", "dyn", "else", "enum", "extern", "false",
+    "fn", "for", "gen", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "static", "struct", "trait", "true", "type", "unsafe", "use", "where",
+    "while", "abstract", "final", "try",
+];
+
+pub(crate) struct RustGenerator {}
+
+impl RustGenerator {
+    pub fn generate(model: &IDLModel) -> String {
+        let mut s = "// Generated by faml IDLGenerator, do not edit.\n".to_string();
+        for en in &model.enums {
+            s.push_str("\n#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]\n");
+            s.push_str(&format!("pub enum {} {{\n", en.name));
+            let mut names = HashSet::new();
+            for variant in &en.variants {
+                let name = Self::variant_name(variant, &mut names);
+                if &name != variant {
+                    s.push_str(&format!("    #[serde(rename = {variant:?})]\n"));
+                }
+                s.push_str(&format!("    {name},\n"));
+            }
+            s.push_str("}\n");
+        }
+        for st in &model.structs {
+            s.push_str("\n#[derive(Debug, Clone, serde::Deserialize)]\n");
+            s.push_str(&format!("pub struct {} {{\n", st.name));
+            let mut names = HashSet::new();
+            for field in &st.fields {
+                let name = Self::field_name(&field.key, &mut names);
+                if field.ty.has_distance() {
+                    s.push_str("    /// distance in meters\n");
+                }
+                if name.trim_start_matches("r#") != field.key {
+                    s.push_str(&format!("    #[serde(rename = {:?})]\n", field.key));
+                }
+                if let IDLType::Option(_) = field.ty {
+                    s.push_str("    #[serde(default)]\n");
+                }
+                if field.ty.has_unit() {
+                    s.push_str(
+                        "    #[serde(deserialize_with = \"faml::serde_units::deserialize\")]\n",
+                    );
+                }
+                s.push_str(&format!("    pub {name}: {},\n", Self::type_str(&field.ty)));
+            }
+            s.push_str("}\n");
+        }
+        s
+    }
+
+    fn type_str(ty: &IDLType) -> String {
+        match ty {
+            IDLType::Bool => "bool".to_string(),
+            IDLType::Int => "i64".to_string(),
+            IDLType::Float | IDLType::Distance => "f64".to_string(),
+            IDLType::String => "String".to_string(),
+            IDLType::Duration => "std::time::Duration".to_string(),
+            IDLType::Any => "serde_json::Value".to_string(),
+            IDLType::Option(ty) => format!("Option<{}>", Self::type_str(ty)),
+            IDLType::Array(ty) => format!("Vec<{}>", Self::type_str(ty)),
+            IDLType::Struct(name) | IDLType::Enum(name) => name.clone(),
+        }
+    }
+
+    fn variant_name(variant: &str, names: &mut HashSet<String>) -> String {
+        let mut name = match variant.to_pascal_case() {
+            s if s.is_empty() || s.starts_with(|c: char| c.is_numeric()) => format!("V{s}"),
+            s if s == "Self" => "Self_".to_string(),
+            s => s,
+        };
+        if names.contains(&name) {
+            let idx = (2..).find(|idx| !names.contains(&format!("{name}{idx}")));
+            name = format!("{name}{}", idx.unwrap());
+        }
+        names.insert(name.clone());
+        name
+    }
+
+    fn field_name(key: &str, names: &mut HashSet<String>) -> String {
+        let name = super::field_name(key, names);
+        match &name[..] {
+            "self" | "super" | "crate" => format!("{name}_"),
+            name if KEYWORDS.contains(&name) => format!("r#{name}"),
+            name => name.to_string(),
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use super::{IDLModel, IDLType};
+
+pub(crate) struct TypeScriptGenerator {}
+
+impl TypeScriptGenerator {
+    pub fn generate(model: &IDLModel) -> String {
+        let mut s = "// Generated by faml IDLGenerator, do not edit.\n".to_string();
+        for en in &model.enums {
+            let variants: Vec<_> = en.variants.iter().map(|v| format!("{v:?}")).collect();
+            s.push_str(&format!(
+                "\nexport type {} = {};\n",
+                en.name,
+                variants.join(" | ")
+            ));
+        }
+        for st in &model.structs {
+            s.push_str(&format!("\nexport interface {} {{\n", st.name));
+            for field in &st.fields {
+                let key = match Self::is_identifier(&field.key) {
+                    true => field.key.clone(),
+                    false => format!("{:?}", field.key),
+                };
+                if let Some(comment) = Self::comment(&field.ty) {
+                    s.push_str(&format!("  /** {comment} */\n"));
+                }
+                match &field.ty {
+                    IDLType::Option(ty) => {
+                        s.push_str(&format!("  {key}?: {} | null;\n", Self::type_str(ty)))
+                    }
+                    ty => s.push_str(&format!("  {key}: {};\n", Self::type_str(ty))),
+                }
+            }
+            s.push_str("}\n");
+        }
+        s
+    }
+
+    fn type_str(ty: &IDLType) -> String {
+        match ty {
+            IDLType::Bool => "boolean".to_string(),
+            IDLType::Int | IDLType::Float => "number".to_string(),
+            IDLType::String | IDLType::Duration | IDLType::Distance => "string".to_string(),
+            IDLType::Any => "unknown".to_string(),
+            IDLType::Option(ty) => format!("{} | null", Self::type_str(ty)),
+            IDLType::Array(ty) => match &**ty {
+                IDLType::Option(_) => format!("({})[]", Self::type_str(ty)),
+                ty => format!("{}[]", Self::type_str(ty)),
+            },
+            IDLType::Struct(name) | IDLType::Enum(name) => name.clone(),
+        }
+    }
+
+    fn comment(ty: &IDLType) -> Option<&'static str> {
+        match ty {
+            IDLType::Duration => Some("duration, e.g. \"2.5 mins\""),
+            IDLType::Distance => Some("distance, e.g. \"1.5 kilometers\""),
+            IDLType::Option(ty) | IDLType::Array(ty) => Self::comment(ty),
+            _ => None,
+        }
+    }
+
+    fn is_identifier(key: &str) -> bool {
+        let mut chars = key.chars();
+        chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+    }
+}
@@ -1,11 +1,23 @@
 #[cfg(test)]
 pub mod test;
 
+extern crate self as faml;
+
+pub mod bind;
+pub mod de;
 pub mod expr;
+#[cfg(feature = "generate")]
 pub mod idl_gen;
 pub mod native;
+pub mod ser;
+pub mod serde_units;
 mod string_utils;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 pub use expr::faml_expr::{FamlExpr, FamlExprImpl};
 pub use expr::faml_value::FamlValue;
+#[cfg(feature = "derive")]
+pub use faml_derive::Faml;
 pub use native::Native;
+pub use ser::{to_expr, to_value};
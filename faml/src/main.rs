@@ -9,7 +9,30 @@ fn read_line(tip: &str) -> anyhow::Result<String> {
     Ok(input)
 }
 
+fn run_cli(args: &[String]) -> anyhow::Result<()> {
+    match args {
+        [cmd, file] if cmd == "schema" => {
+            let expr = faml::FamlExpr::from_str(&std::fs::read_to_string(file)?)?;
+            println!("{}", serde_json::to_string_pretty(&expr.to_json_schema()?)?);
+        }
+        [cmd, file, flag, schema] if cmd == "check" && flag == "--schema" => {
+            let expr = faml::FamlExpr::from_str(&std::fs::read_to_string(file)?)?;
+            let schema = serde_json::from_str(&std::fs::read_to_string(schema)?)?;
+            expr.validate_json_schema(&schema)?;
+            println!("{file}: ok");
+        }
+        _ => anyhow::bail!(
+            "usage:\n  faml\n  faml schema <file.faml>\n  faml check <file.faml> --schema <schema.json>"
+        ),
+    }
+    Ok(())
+}
+
 fn main() -> anyhow::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        return run_cli(&args);
+    }
     println!(
         "{} {}",
         std::env!("CARGO_PKG_NAME"),
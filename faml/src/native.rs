@@ -1,10 +1,13 @@
 use crate::FamlValue;
+use anyhow::anyhow;
 use std::collections::HashMap;
+use std::future::Future;
 use std::marker::PhantomData;
-use std::sync::{LazyLock, Mutex};
+use std::pin::Pin;
+use std::sync::{Arc, LazyLock, Mutex, RwLock};
 
 pub trait FasCallable: Send + Sync {
-    fn call(&self, args: Vec<FamlValue>) -> FamlValue;
+    fn call(&self, args: Vec<FamlValue>) -> anyhow::Result<FamlValue>;
     fn clone_(&self) -> Box<dyn FasCallable + 'static>;
     fn get_arg_count(&self) -> usize;
 }
@@ -15,11 +18,34 @@ impl std::fmt::Debug for dyn FasCallable {
     }
 }
 
+/// Return type of native functions, errors of `Result` fail the evaluation.
+pub trait FasReturn {
+    fn into_result(self) -> anyhow::Result<FamlValue>;
+}
+
+impl<T: Into<FamlValue>> FasReturn for T {
+    fn into_result(self) -> anyhow::Result<FamlValue> {
+        Ok(self.into())
+    }
+}
+
+impl<T: Into<FamlValue>, E: Into<anyhow::Error>> FasReturn for Result<T, E> {
+    fn into_result(self) -> anyhow::Result<FamlValue> {
+        self.map(Into::into).map_err(Into::into)
+    }
+}
+
 pub trait FasToWrapper<T> {
     type Output: FasCallable + 'static;
     fn convert(self) -> Self::Output;
 }
 
+/// Like `FasToWrapper`, for `FnMut` closures keeping state between calls.
+pub trait FasToMutWrapper<T> {
+    type Output: FasCallable + 'static;
+    fn convert(self) -> Self::Output;
+}
+
 //
 
 macro_rules! calc_arg_count {
@@ -31,122 +57,524 @@ macro_rules! calc_arg_count {
 	};
 }
 
+fn check_arg_count(count: usize, args: &[FamlValue]) -> anyhow::Result<()> {
+    match args.len() == count {
+        true => Ok(()),
+        false => Err(anyhow!("expected {count} args, got {}", args.len())),
+    }
+}
+
+/// `Fn` functions are shared without a lock, so they may run concurrently or call back into
+/// faml.
+fn share_func<T>(func: &Arc<T>) -> anyhow::Result<&T> {
+    Ok(func)
+}
+
+fn new_shared<T>(func: T) -> Arc<T> {
+    Arc::new(func)
+}
+
+/// `FnMut` functions are behind a lock, so calls of the same function are serialized.
+fn lock_func<T>(func: &Mutex<T>) -> anyhow::Result<std::sync::MutexGuard<'_, T>> {
+    func.lock()
+        .map_err(|_| anyhow!("func is poisoned by a previous panic"))
+}
+
+fn new_locked<T>(func: T) -> Arc<Mutex<T>> {
+    Arc::new(Mutex::new(func))
+}
+
 macro_rules! FasWrapper {
-    ($sname:ident) => {
-        pub struct $sname<T: Fn() -> R, R>(T, PhantomData<R>);
+    ($sname:ident $smname:ident $($name1:ident) *) => {
+        FasWrapper!(@impl $sname FasToWrapper Fn [Sync] Arc<T>, new_shared, share_func; $($name1)*);
+        FasWrapper!(@impl $smname FasToMutWrapper FnMut [] Arc<Mutex<T>>, new_locked, lock_func; $($name1)*);
+    };
+    (@impl $sname:ident $to:ident $fn:ident [$($sync:ident)?] $store:ty, $new:ident, $get:ident; $($name1:ident) *) => {
+        pub struct $sname<T, $($name1,)* R>($store, PhantomData<fn($($name1),*) -> R>);
 
-        impl<T, R> FasCallable for $sname<T, R>
+        impl<T, $($name1,)* R> FasCallable for $sname<T, $($name1,)* R>
         where
-            T: Fn() -> R + Clone + Send + Sync + 'static,
-            R: Into<FamlValue>  + Send + Sync + 'static,
+            T: $fn($($name1,)*) -> R + Send $(+ $sync)* + 'static,
+            $($name1: TryFrom<FamlValue, Error: Into<anyhow::Error>> + 'static,)*
+            R: FasReturn + 'static,
         {
-            fn call(&self, _args: Vec<FamlValue>) -> FamlValue {
-                let r = (self.0)();
-                r.into()
+            #[allow(non_snake_case, unused_mut, unused_assignments)]
+            fn call(&self, args: Vec<FamlValue>) -> anyhow::Result<FamlValue> {
+                check_arg_count(self.get_arg_count(), &args)?;
+                let mut index = 0;
+                $(
+                    let arg = args.get(index).cloned().unwrap_or(FamlValue::None);
+                    let $name1 = $name1::try_from(arg)
+                        .map_err(|err| anyhow!("arg[{index}]: {}", err.into()))?;
+                    index += 1;
+                )*
+                let _ = index;
+                let r = ($get(&self.0)?)($($name1),*);
+                r.into_result()
             }
 
             fn clone_(&self) -> Box<dyn FasCallable + 'static> {
-                let copy = Self(self.0.clone(), PhantomData);
-                Box::new(copy)
+                Box::new(Self(Arc::clone(&self.0), PhantomData))
             }
 
             fn get_arg_count(&self) -> usize {
-                0
+                calc_arg_count!($($name1)*)
             }
         }
 
-        impl<T, R> FasToWrapper<R> for T
+        impl<T, $($name1,)* R> $to<fn($($name1),*) -> R> for T
         where
-            T: Fn() -> R + Clone + Send + Sync + 'static,
-            R: Into<FamlValue>  + Send + Sync + 'static,
+            T: $fn($($name1,)*) -> R + Send $(+ $sync)* + 'static,
+            $($name1: TryFrom<FamlValue, Error: Into<anyhow::Error>> + 'static,)*
+            R: FasReturn + 'static,
         {
-            type Output = $sname<T,  R>;
+            type Output = $sname<T, $($name1,)* R>;
 
             fn convert(self) -> Self::Output {
-                $sname(self, PhantomData)
+                $sname($new(self), PhantomData)
             }
         }
     };
+}
+
+FasWrapper!(FuncWrapper0 MutFuncWrapper0);
+FasWrapper!(FuncWrapper1 MutFuncWrapper1 T0);
+FasWrapper!(FuncWrapper2 MutFuncWrapper2 T0 T1);
+FasWrapper!(FuncWrapper3 MutFuncWrapper3 T0 T1 T2);
+FasWrapper!(FuncWrapper4 MutFuncWrapper4 T0 T1 T2 T3);
+FasWrapper!(FuncWrapper5 MutFuncWrapper5 T0 T1 T2 T3 T4);
+FasWrapper!(FuncWrapper6 MutFuncWrapper6 T0 T1 T2 T3 T4 T5);
+FasWrapper!(FuncWrapper7 MutFuncWrapper7 T0 T1 T2 T3 T4 T5 T6);
+FasWrapper!(FuncWrapper8 MutFuncWrapper8 T0 T1 T2 T3 T4 T5 T6 T7);
+FasWrapper!(FuncWrapper9 MutFuncWrapper9 T0 T1 T2 T3 T4 T5 T6 T7 T8);
+
+/// Wrapper of functions receiving all arguments as `Vec<FamlValue>`.
+pub struct VariadicWrapper<T>(Arc<T>);
+
+impl<T, R> FasCallable for VariadicWrapper<T>
+where
+    T: Fn(Vec<FamlValue>) -> R + Send + Sync + 'static,
+    R: FasReturn,
+{
+    fn call(&self, args: Vec<FamlValue>) -> anyhow::Result<FamlValue> {
+        (self.0)(args).into_result()
+    }
+
+    fn clone_(&self) -> Box<dyn FasCallable + 'static> {
+        Box::new(VariadicWrapper(Arc::clone(&self.0)))
+    }
+
+    fn get_arg_count(&self) -> usize {
+        0
+    }
+}
+
+impl Clone for Box<dyn FasCallable + 'static> {
+    fn clone(&self) -> Self {
+        self.clone_()
+    }
+}
+
+pub type FasFuture = Pin<Box<dyn Future<Output = anyhow::Result<FamlValue>> + Send + 'static>>;
+
+/// Native function returning a future, only callable from `FamlExpr::evaluate_async`.
+pub trait FasAsyncCallable: Send + Sync {
+    fn call(&self, args: Vec<FamlValue>) -> FasFuture;
+    fn clone_(&self) -> Box<dyn FasAsyncCallable + 'static>;
+    fn get_arg_count(&self) -> usize;
+}
+
+impl Clone for Box<dyn FasAsyncCallable + 'static> {
+    fn clone(&self) -> Self {
+        self.clone_()
+    }
+}
+
+pub trait FasToAsyncWrapper<T> {
+    type Output: FasAsyncCallable + 'static;
+    fn convert(self) -> Self::Output;
+}
+
+macro_rules! FasAsyncWrapper {
     ($sname:ident $($name1:ident) *) => {
-        pub struct $sname<T: Fn($($name1),*) -> R, $($name1),*, R>(T, PhantomData<($($name1),*, R)>);
+        pub struct $sname<T, $($name1,)* Fut>(Arc<T>, PhantomData<fn($($name1),*) -> Fut>);
 
-        impl<T, $($name1), * ,R> FasCallable for $sname<T, $($name1),*, R>
+        impl<T, $($name1,)* Fut, R> FasAsyncCallable for $sname<T, $($name1,)* Fut>
         where
-            T: Fn($($name1), *) -> R + Clone + Send + Sync + 'static,
-            $($name1: From<FamlValue> + Send + Sync + 'static),*,
-            R: Into<FamlValue> + Send + Sync + 'static,
+            T: Fn($($name1,)*) -> Fut + Send + Sync + 'static,
+            $($name1: TryFrom<FamlValue, Error: Into<anyhow::Error>> + 'static,)*
+            Fut: Future<Output = R> + Send + 'static,
+            R: FasReturn + 'static,
         {
-			#[allow(non_snake_case)]
-            fn call(&self, args: Vec<FamlValue>) -> FamlValue {
-				let r = {
-					let mut index = 0;
-					$(let $name1 = $name1::from(args[index].clone());index+=1;)*
-					let _ = index;
-					(self.0)($($name1),*)
-				};
-                r.into()
+            #[allow(non_snake_case, unused_mut, unused_assignments)]
+            fn call(&self, args: Vec<FamlValue>) -> FasFuture {
+                let converted = (|| {
+                    check_arg_count(self.get_arg_count(), &args)?;
+                    let mut index = 0;
+                    $(
+                        let arg = args.get(index).cloned().unwrap_or(FamlValue::None);
+                        let $name1 = $name1::try_from(arg)
+                            .map_err(|err| anyhow!("arg[{index}]: {}", err.into()))?;
+                        index += 1;
+                    )*
+                    let _ = index;
+                    anyhow::Ok(($($name1,)*))
+                })();
+                match converted {
+                    Ok(($($name1,)*)) => {
+                        let fut = (self.0)($($name1),*);
+                        Box::pin(async move { fut.await.into_result() })
+                    }
+                    Err(err) => Box::pin(std::future::ready(Err(err))),
+                }
             }
 
-            fn clone_(&self) -> Box<dyn FasCallable + 'static> {
-                let copy = Self(self.0.clone(), PhantomData);
-                Box::new(copy)
+            fn clone_(&self) -> Box<dyn FasAsyncCallable + 'static> {
+                Box::new(Self(Arc::clone(&self.0), PhantomData))
             }
 
             fn get_arg_count(&self) -> usize {
-				calc_arg_count!($($name1)*)
+                calc_arg_count!($($name1)*)
             }
         }
 
-        impl<T, $($name1,)* R> FasToWrapper<($($name1,)* R)> for T
+        impl<T, $($name1,)* Fut, R> FasToAsyncWrapper<fn($($name1),*) -> Fut> for T
         where
-            T: Fn($($name1,)*) -> R + Clone + Send + Sync + 'static,
-            $($name1: From<FamlValue> + Send + Sync + 'static,)*
-            R: Into<FamlValue> + Send + Sync + 'static,
+            T: Fn($($name1,)*) -> Fut + Send + Sync + 'static,
+            $($name1: TryFrom<FamlValue, Error: Into<anyhow::Error>> + 'static,)*
+            Fut: Future<Output = R> + Send + 'static,
+            R: FasReturn + 'static,
         {
-            type Output = $sname<T, $($name1,)* R>;
+            type Output = $sname<T, $($name1,)* Fut>;
 
             fn convert(self) -> Self::Output {
-                $sname(self, PhantomData)
+                $sname(Arc::new(self), PhantomData)
             }
         }
     };
 }
 
-FasWrapper!(FuncWrapper0);
-FasWrapper!(FuncWrapper1 T0);
-FasWrapper!(FuncWrapper2 T0 T1);
-FasWrapper!(FuncWrapper3 T0 T1 T2);
-FasWrapper!(FuncWrapper4 T0 T1 T2 T3);
-FasWrapper!(FuncWrapper5 T0 T1 T2 T3 T4);
-FasWrapper!(FuncWrapper6 T0 T1 T2 T3 T4 T5);
-FasWrapper!(FuncWrapper7 T0 T1 T2 T3 T4 T5 T6 );
-FasWrapper!(FuncWrapper8 T0 T1 T2 T3 T4 T5 T6 T7);
-FasWrapper!(FuncWrapper9 T0 T1 T2 T3 T4 T5 T6 T7 T8);
+FasAsyncWrapper!(AsyncFuncWrapper0);
+FasAsyncWrapper!(AsyncFuncWrapper1 T0);
+FasAsyncWrapper!(AsyncFuncWrapper2 T0 T1);
+FasAsyncWrapper!(AsyncFuncWrapper3 T0 T1 T2);
+FasAsyncWrapper!(AsyncFuncWrapper4 T0 T1 T2 T3);
+FasAsyncWrapper!(AsyncFuncWrapper5 T0 T1 T2 T3 T4);
+FasAsyncWrapper!(AsyncFuncWrapper6 T0 T1 T2 T3 T4 T5);
+FasAsyncWrapper!(AsyncFuncWrapper7 T0 T1 T2 T3 T4 T5 T6);
+FasAsyncWrapper!(AsyncFuncWrapper8 T0 T1 T2 T3 T4 T5 T6 T7);
+FasAsyncWrapper!(AsyncFuncWrapper9 T0 T1 T2 T3 T4 T5 T6 T7 T8);
 
-impl Clone for Box<dyn FasCallable + 'static> {
-    fn clone(&self) -> Self {
-        self.clone_()
+/// Object provided by the host, `native.robot.battery.voltage` reads the property `battery` of
+/// the object `robot` and then `voltage` of it. Properties are resolved again on every read.
+pub trait HostObject: Send + Sync {
+    /// Property `name`, `None` if the object has no such property.
+    fn get(&self, name: &str) -> anyhow::Result<Option<HostValue>>;
+
+    /// Invoke the method `name`, `None` if the object has no such method.
+    fn call(&self, name: &str, args: Vec<FamlValue>) -> anyhow::Result<Option<FamlValue>> {
+        let _ = (name, args);
+        Ok(None)
     }
 }
 
-pub struct Native {}
+/// Property of a `HostObject`.
+#[derive(Clone)]
+pub enum HostValue {
+    Value(FamlValue),
+    Object(Arc<dyn HostObject>),
+}
 
-impl Native {
-    pub fn add_func<T: FasToWrapper<U> + 'static, U>(name: impl Into<String>, func: T) {
+impl std::fmt::Debug for HostValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HostValue::Value(val) => f.debug_tuple("Value").field(val).finish(),
+            HostValue::Object(_) => f.write_str("Object"),
+        }
+    }
+}
+
+/// `HostObject` built from closures, property getters are functions without arguments.
+#[derive(Default)]
+pub struct NativeObject {
+    props: HashMap<String, Box<dyn FasCallable + 'static>>,
+    objects: HashMap<String, Arc<dyn HostObject>>,
+    methods: HashMap<String, Box<dyn FasCallable + 'static>>,
+}
+
+impl NativeObject {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_property<T: FasToWrapper<U> + 'static, U>(
+        &mut self,
+        name: impl Into<String>,
+        getter: T,
+    ) {
+        self.props.insert(name.into(), Box::new(getter.convert()));
+    }
+
+    pub fn add_object(&mut self, name: impl Into<String>, obj: impl HostObject + 'static) {
+        self.objects.insert(name.into(), Arc::new(obj));
+    }
+
+    pub fn add_method<T: FasToWrapper<U> + 'static, U>(
+        &mut self,
+        name: impl Into<String>,
+        func: T,
+    ) {
+        self.methods.insert(name.into(), Box::new(func.convert()));
+    }
+}
+
+impl HostObject for NativeObject {
+    fn get(&self, name: &str) -> anyhow::Result<Option<HostValue>> {
+        if let Some(obj) = self.objects.get(name) {
+            return Ok(Some(HostValue::Object(Arc::clone(obj))));
+        }
+        match self.props.get(name) {
+            Some(getter) => Ok(Some(HostValue::Value(getter.call(vec![])?))),
+            None => Ok(None),
+        }
+    }
+
+    fn call(&self, name: &str, args: Vec<FamlValue>) -> anyhow::Result<Option<FamlValue>> {
+        match self.methods.get(name) {
+            Some(func) => Ok(Some(func.call(args)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Registered {
+    funcs: HashMap<String, Box<dyn FasCallable + 'static>>,
+    async_funcs: HashMap<String, Box<dyn FasAsyncCallable + 'static>>,
+    objects: HashMap<String, Arc<dyn HostObject>>,
+    /// Extension methods keyed by value type and method name.
+    methods: HashMap<(String, String), Box<dyn FasCallable + 'static>>,
+}
+
+/// Native functions called as `native.name(...)` and host objects read as `native.name.prop`
+/// from faml, names may be namespaced like `geo.distance`. Clones share the same functions.
+#[derive(Clone, Default)]
+pub struct FunctionRegistry(Arc<RwLock<Registered>>);
+
+impl FunctionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry used by `Native` and as fallback of attached registries.
+    pub fn global() -> &'static FunctionRegistry {
+        static GLOBAL: LazyLock<FunctionRegistry> = LazyLock::new(FunctionRegistry::new);
+        &GLOBAL
+    }
+
+    /// Register a function, calls are not locked so it may run concurrently or re-enter.
+    pub fn add_func<T: FasToWrapper<U> + 'static, U>(&self, name: impl Into<String>, func: T) {
         let func: Box<dyn FasCallable + 'static> = Box::new(func.convert());
-        if let Ok(mut funcs) = NATIVE_FUNCS.lock() {
-            funcs.insert(name.into(), func);
+        if let Ok(mut reg) = self.0.write() {
+            reg.funcs.insert(name.into(), func);
         }
     }
 
-    pub fn get_func(name: &str) -> Option<Box<dyn FasCallable + 'static>> {
-        if let Ok(funcs) = NATIVE_FUNCS.lock() {
-            funcs.get(name).cloned()
-        } else {
-            None
+    /// Register a `FnMut` closure keeping state between calls, calls of it are serialized by a
+    /// lock and must not call it again.
+    pub fn add_func_mut<T: FasToMutWrapper<U> + 'static, U>(
+        &self,
+        name: impl Into<String>,
+        func: T,
+    ) {
+        let func: Box<dyn FasCallable + 'static> = Box::new(func.convert());
+        if let Ok(mut reg) = self.0.write() {
+            reg.funcs.insert(name.into(), func);
+        }
+    }
+
+    /// Register a function receiving any number of arguments.
+    pub fn add_variadic_func<T, R>(&self, name: impl Into<String>, func: T)
+    where
+        T: Fn(Vec<FamlValue>) -> R + Send + Sync + 'static,
+        R: FasReturn + 'static,
+    {
+        if let Ok(mut reg) = self.0.write() {
+            let func = VariadicWrapper(Arc::new(func));
+            reg.funcs.insert(name.into(), Box::new(func));
+        }
+    }
+
+    /// Register an async function, formulas calling it must be evaluated with
+    /// `FamlExpr::evaluate_async`.
+    pub fn add_async_func<T: FasToAsyncWrapper<U> + 'static, U>(
+        &self,
+        name: impl Into<String>,
+        func: T,
+    ) {
+        let func: Box<dyn FasAsyncCallable + 'static> = Box::new(func.convert());
+        if let Ok(mut reg) = self.0.write() {
+            reg.async_funcs.insert(name.into(), func);
+        }
+    }
+
+    /// Remove a sync or async function.
+    pub fn remove_func(&self, name: &str) -> bool {
+        match self.0.write() {
+            Ok(mut reg) => {
+                let sync = reg.funcs.remove(name).is_some();
+                reg.async_funcs.remove(name).is_some() || sync
+            }
+            Err(_) => false,
+        }
+    }
+
+    pub fn get_func(&self, name: &str) -> Option<Box<dyn FasCallable + 'static>> {
+        self.0.read().ok()?.funcs.get(name).cloned()
+    }
+
+    pub fn get_async_func(&self, name: &str) -> Option<Box<dyn FasAsyncCallable + 'static>> {
+        self.0.read().ok()?.async_funcs.get(name).cloned()
+    }
+
+    /// Sorted names of the registered sync and async functions.
+    pub fn func_names(&self) -> Vec<String> {
+        let mut names: Vec<_> = match self.0.read() {
+            Ok(reg) => reg
+                .funcs
+                .keys()
+                .chain(reg.async_funcs.keys())
+                .cloned()
+                .collect(),
+            Err(_) => vec![],
+        };
+        names.sort();
+        names
+    }
+
+    pub fn add_object(&self, name: impl Into<String>, obj: impl HostObject + 'static) {
+        if let Ok(mut reg) = self.0.write() {
+            reg.objects.insert(name.into(), Arc::new(obj));
+        }
+    }
+
+    pub fn remove_object(&self, name: &str) -> bool {
+        match self.0.write() {
+            Ok(mut reg) => reg.objects.remove(name).is_some(),
+            Err(_) => false,
+        }
+    }
+
+    pub fn get_object(&self, name: &str) -> Option<Arc<dyn HostObject>> {
+        self.0.read().ok()?.objects.get(name).cloned()
+    }
+
+    /// Register the method `name` of values of type `ty`, see `Native::add_method`.
+    pub fn add_method<T: FasToWrapper<U> + 'static, U>(&self, ty: &str, name: &str, func: T) {
+        let func: Box<dyn FasCallable + 'static> = Box::new(func.convert());
+        if let Ok(mut reg) = self.0.write() {
+            reg.methods.insert((ty.to_string(), name.to_string()), func);
         }
     }
+
+    pub fn remove_method(&self, ty: &str, name: &str) -> bool {
+        match self.0.write() {
+            Ok(mut reg) => {
+                let key = (ty.to_string(), name.to_string());
+                reg.methods.remove(&key).is_some()
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Extension method of the value type, or of `any`.
+    pub fn get_method(&self, ty: &str, name: &str) -> Option<Box<dyn FasCallable + 'static>> {
+        let reg = self.0.read().ok()?;
+        [ty, "any"].into_iter().find_map(|ty| {
+            let key = (ty.to_string(), name.to_string());
+            reg.methods.get(&key).cloned()
+        })
+    }
+
+    /// Sorted names of the registered host objects.
+    pub fn object_names(&self) -> Vec<String> {
+        let mut names: Vec<_> = match self.0.read() {
+            Ok(reg) => reg.objects.keys().cloned().collect(),
+            Err(_) => vec![],
+        };
+        names.sort();
+        names
+    }
 }
 
-pub static NATIVE_FUNCS: LazyLock<Mutex<HashMap<String, Box<dyn FasCallable + 'static>>>> =
-    LazyLock::new(|| Mutex::new(HashMap::new()));
+impl std::fmt::Debug for FunctionRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FunctionRegistry")
+            .field("funcs", &self.func_names())
+            .field("objects", &self.object_names())
+            .finish()
+    }
+}
+
+/// Functions and host objects of the global registry, visible to every `FamlExpr`.
+pub struct Native {}
+
+impl Native {
+    pub fn add_func<T: FasToWrapper<U> + 'static, U>(name: impl Into<String>, func: T) {
+        FunctionRegistry::global().add_func(name, func);
+    }
+
+    pub fn add_func_mut<T: FasToMutWrapper<U> + 'static, U>(name: impl Into<String>, func: T) {
+        FunctionRegistry::global().add_func_mut(name, func);
+    }
+
+    pub fn add_variadic_func<T, R>(name: impl Into<String>, func: T)
+    where
+        T: Fn(Vec<FamlValue>) -> R + Send + Sync + 'static,
+        R: FasReturn + 'static,
+    {
+        FunctionRegistry::global().add_variadic_func(name, func);
+    }
+
+    pub fn add_async_func<T: FasToAsyncWrapper<U> + 'static, U>(name: impl Into<String>, func: T) {
+        FunctionRegistry::global().add_async_func(name, func);
+    }
+
+    pub fn remove_func(name: &str) -> bool {
+        FunctionRegistry::global().remove_func(name)
+    }
+
+    pub fn get_func(name: &str) -> Option<Box<dyn FasCallable + 'static>> {
+        FunctionRegistry::global().get_func(name)
+    }
+
+    pub fn add_object(name: impl Into<String>, obj: impl HostObject + 'static) {
+        FunctionRegistry::global().add_object(name, obj);
+    }
+
+    /// Register the method `name` of values of type `ty`, like `string`, `int`, `array` or `any`
+    /// for all types. The value is passed as the first argument, and built-in methods with the
+    /// same name are tried first.
+    pub fn add_method<T: FasToWrapper<U> + 'static, U>(ty: &str, name: &str, func: T) {
+        FunctionRegistry::global().add_method(ty, name, func);
+    }
+
+    pub fn remove_method(ty: &str, name: &str) -> bool {
+        FunctionRegistry::global().remove_method(ty, name)
+    }
+
+    /// Extension method of the value type, or of `any`.
+    pub fn get_method(ty: &str, name: &str) -> Option<Box<dyn FasCallable + 'static>> {
+        FunctionRegistry::global().get_method(ty, name)
+    }
+
+    pub fn remove_object(name: &str) -> bool {
+        FunctionRegistry::global().remove_object(name)
+    }
+
+    /// Load a `.wasm` plugin and register its functions as `<plugin>.<func>`.
+    #[cfg(feature = "wasm")]
+    pub fn add_plugin(path: impl AsRef<std::path::Path>) -> anyhow::Result<()> {
+        FunctionRegistry::global().add_plugin(path)
+    }
+}
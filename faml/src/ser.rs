@@ -0,0 +1,522 @@
+//! `serde::Serializer` building `FamlValue` and `FamlExpr` from Rust values.
+//!
+//! Struct fields and map entries keep their serialization order, and `std::time::Duration`
+//! becomes `FamlValue::Duration`, so defaults defined in Rust can be overridden with
+//! `FamlExpr::apply` like any parsed config.
+
+use crate::de::Error;
+use crate::{FamlExpr, FamlValue};
+use indexmap::IndexMap;
+use serde::Serialize;
+use serde::ser::{self, Impossible};
+use std::fmt;
+use std::time::Duration;
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        <Error as serde::de::Error>::custom(msg)
+    }
+}
+
+/// Serialize `value` into an evaluated value.
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> anyhow::Result<FamlValue> {
+    Ok(value.serialize(ValueSerializer)?)
+}
+
+/// Serialize `value` into an expression, formulas can then be applied on it with `FamlExpr::apply`.
+pub fn to_expr<T: Serialize + ?Sized>(value: &T) -> anyhow::Result<FamlExpr> {
+    let mut expr = to_value(value)?.to_expr();
+    let base_expr = expr.to_weak();
+    expr.init_weak_expr(base_expr.clone(), base_expr);
+    Ok(expr)
+}
+
+fn single_entry(key: &str, value: FamlValue) -> FamlValue {
+    FamlValue::Map([(key.to_string(), value)].into_iter().collect())
+}
+
+pub struct ValueSerializer;
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = FamlValue;
+    type Error = Error;
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = SeqSerializer;
+    type SerializeMap = MapSerializer;
+    type SerializeStruct = MapSerializer;
+    type SerializeStructVariant = MapSerializer;
+
+    fn serialize_bool(self, v: bool) -> Result<FamlValue, Error> {
+        Ok(FamlValue::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<FamlValue, Error> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<FamlValue, Error> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<FamlValue, Error> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<FamlValue, Error> {
+        Ok(FamlValue::Int64(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<FamlValue, Error> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<FamlValue, Error> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<FamlValue, Error> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<FamlValue, Error> {
+        let n = i64::try_from(v)
+            .map_err(|_| ser::Error::custom(format!("value[{v}] is out of range of type[int]")))?;
+        Ok(FamlValue::Int64(n))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<FamlValue, Error> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<FamlValue, Error> {
+        Ok(FamlValue::Float64(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<FamlValue, Error> {
+        Ok(FamlValue::String(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<FamlValue, Error> {
+        Ok(FamlValue::String(v.to_string()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<FamlValue, Error> {
+        Ok(FamlValue::Array(
+            v.iter().map(|b| FamlValue::Int64(*b as i64)).collect(),
+        ))
+    }
+
+    fn serialize_none(self) -> Result<FamlValue, Error> {
+        Ok(FamlValue::None)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<FamlValue, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<FamlValue, Error> {
+        Ok(FamlValue::None)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<FamlValue, Error> {
+        Ok(FamlValue::None)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<FamlValue, Error> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<FamlValue, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<FamlValue, Error> {
+        Ok(single_entry(variant, value.serialize(self)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer, Error> {
+        Ok(SeqSerializer {
+            variant: None,
+            items: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer, Error> {
+        Ok(SeqSerializer {
+            variant: Some(variant),
+            items: Vec::with_capacity(len),
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<MapSerializer, Error> {
+        Ok(MapSerializer::new(None, false))
+    }
+
+    fn serialize_struct(self, name: &'static str, _len: usize) -> Result<MapSerializer, Error> {
+        Ok(MapSerializer::new(None, name == "Duration"))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<MapSerializer, Error> {
+        Ok(MapSerializer::new(Some(variant), false))
+    }
+}
+
+pub struct SeqSerializer {
+    variant: Option<&'static str>,
+    items: Vec<FamlValue>,
+}
+
+impl SeqSerializer {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.items.push(value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<FamlValue, Error> {
+        let value = FamlValue::Array(self.items);
+        Ok(match self.variant {
+            Some(variant) => single_entry(variant, value),
+            None => value,
+        })
+    }
+}
+
+macro_rules! impl_seq_serializer {
+    ($($trait:ident::$method:ident),*) => {
+        $(
+            impl ser::$trait for SeqSerializer {
+                type Ok = FamlValue;
+                type Error = Error;
+
+                fn $method<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+                    self.push(value)
+                }
+
+                fn end(self) -> Result<FamlValue, Error> {
+                    self.finish()
+                }
+            }
+        )*
+    };
+}
+
+impl_seq_serializer!(
+    SerializeSeq::serialize_element,
+    SerializeTuple::serialize_element,
+    SerializeTupleStruct::serialize_field,
+    SerializeTupleVariant::serialize_field
+);
+
+pub struct MapSerializer {
+    variant: Option<&'static str>,
+    is_duration: bool,
+    map: IndexMap<String, FamlValue>,
+    key: Option<String>,
+}
+
+impl MapSerializer {
+    fn new(variant: Option<&'static str>, is_duration: bool) -> Self {
+        MapSerializer {
+            variant,
+            is_duration,
+            map: IndexMap::new(),
+            key: None,
+        }
+    }
+
+    fn insert<T: Serialize + ?Sized>(&mut self, key: &str, value: &T) -> Result<(), Error> {
+        self.map
+            .insert(key.to_string(), value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    /// `std::time::Duration` serializes as struct `Duration { secs, nanos }`, serde gives
+    /// no other way to tell it apart. Any struct named `Duration` with exactly these two
+    /// integer fields in this order, and `nanos` below one second, is read as a duration.
+    fn as_duration(&self) -> Option<Duration> {
+        if !self.is_duration || !self.map.keys().eq(["secs", "nanos"]) {
+            return None;
+        }
+        match (&self.map["secs"], &self.map["nanos"]) {
+            (FamlValue::Int64(secs), FamlValue::Int64(nanos))
+                if *secs >= 0 && (0..1_000_000_000).contains(nanos) =>
+            {
+                Some(Duration::new(*secs as u64, *nanos as u32))
+            }
+            _ => None,
+        }
+    }
+
+    fn finish(self) -> Result<FamlValue, Error> {
+        if let Some(dur) = self.as_duration() {
+            return Ok(FamlValue::Duration(dur));
+        }
+        let value = FamlValue::Map(self.map);
+        Ok(match self.variant {
+            Some(variant) => single_entry(variant, value),
+            None => value,
+        })
+    }
+}
+
+impl ser::SerializeMap for MapSerializer {
+    type Ok = FamlValue;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        self.key = Some(key.serialize(KeySerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self.key.take().unwrap_or_default();
+        self.insert(&key, value)
+    }
+
+    fn end(self) -> Result<FamlValue, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for MapSerializer {
+    type Ok = FamlValue;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.insert(key, value)
+    }
+
+    fn end(self) -> Result<FamlValue, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for MapSerializer {
+    type Ok = FamlValue;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.insert(key, value)
+    }
+
+    fn end(self) -> Result<FamlValue, Error> {
+        self.finish()
+    }
+}
+
+/// Map keys are strings in faml, string, int and bool keys are converted.
+struct KeySerializer;
+
+impl KeySerializer {
+    fn unsupported(ty: &str) -> Error {
+        ser::Error::custom(format!("map key must be string, int or bool, got {ty}"))
+    }
+}
+
+impl ser::Serializer for KeySerializer {
+    type Ok = String;
+    type Error = Error;
+    type SerializeSeq = Impossible<String, Error>;
+    type SerializeTuple = Impossible<String, Error>;
+    type SerializeTupleStruct = Impossible<String, Error>;
+    type SerializeTupleVariant = Impossible<String, Error>;
+    type SerializeMap = Impossible<String, Error>;
+    type SerializeStruct = Impossible<String, Error>;
+    type SerializeStructVariant = Impossible<String, Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<String, Error> {
+        Err(Self::unsupported("float"))
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<String, Error> {
+        Err(Self::unsupported("float"))
+    }
+
+    fn serialize_char(self, v: char) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<String, Error> {
+        Err(Self::unsupported("bytes"))
+    }
+
+    fn serialize_none(self) -> Result<String, Error> {
+        Err(Self::unsupported("null"))
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<String, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<String, Error> {
+        Err(Self::unsupported("null"))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<String, Error> {
+        Err(Self::unsupported("null"))
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<String, Error> {
+        Ok(variant.to_string())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<String, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<String, Error> {
+        Err(Self::unsupported("enum"))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        Err(Self::unsupported("array"))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Error> {
+        Err(Self::unsupported("array"))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        Err(Self::unsupported("array"))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Err(Self::unsupported("enum"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Err(Self::unsupported("map"))
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Error> {
+        Err(Self::unsupported("map"))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Err(Self::unsupported("enum"))
+    }
+}
@@ -0,0 +1,59 @@
+//! Deserialize helper for `Duration` and `Distance` fields of structs generated by `IDLGenerator`.
+//!
+//! Durations and distances are serialized as strings like `"2.5 mins"` or `"1.5 kilometers"`,
+//! which std types can't read directly. Annotate such fields with
+//! `#[serde(deserialize_with = "faml::serde_units::deserialize")]`; `Distance` maps to `f64` meters.
+//! `FamlExpr::deserialize` reads them directly, the helper is for other formats like JSON.
+
+use crate::FamlValue;
+use crate::expr::eval::Op1Evaluator;
+use anyhow::anyhow;
+use serde::{Deserialize, Deserializer};
+use std::time::Duration;
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum UnitRepr {
+    Null(()),
+    Number(f64),
+    Text(String),
+    List(Vec<UnitRepr>),
+    Std(Duration),
+}
+
+impl UnitRepr {
+    fn into_value(self) -> anyhow::Result<FamlValue> {
+        Ok(match self {
+            UnitRepr::Null(_) => FamlValue::None,
+            UnitRepr::Number(n) => FamlValue::Float64(n),
+            UnitRepr::Text(s) => {
+                let (num, unit) = s
+                    .trim()
+                    .split_once(' ')
+                    .ok_or_else(|| anyhow!("value[{s}] is not a duration or distance"))?;
+                let num: f64 = num
+                    .parse()
+                    .map_err(|_| anyhow!("value[{s}] is not a duration or distance"))?;
+                Op1Evaluator::eval_suffix(FamlValue::Float64(num), unit.trim())?
+            }
+            UnitRepr::List(items) => FamlValue::Array(
+                items
+                    .into_iter()
+                    .map(|item| item.into_value())
+                    .collect::<anyhow::Result<_>>()?,
+            ),
+            UnitRepr::Std(dur) => FamlValue::Duration(dur),
+        })
+    }
+}
+
+pub fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: TryFrom<FamlValue, Error: Into<anyhow::Error>>,
+{
+    let value = UnitRepr::deserialize(deserializer)?
+        .into_value()
+        .map_err(serde::de::Error::custom)?;
+    T::try_from(value).map_err(|err| serde::de::Error::custom(err.into()))
+}
@@ -1,7 +1,9 @@
 pub trait IntoBaseExt {
     fn into_base(&self) -> String;
     fn escape(&self, dup_quote: bool) -> String;
+    #[cfg(feature = "generate")]
     fn to_pascal_case(&self) -> String;
+    fn to_snake_case(&self) -> String;
 }
 
 impl IntoBaseExt for str {
@@ -28,10 +30,36 @@ impl IntoBaseExt for str {
         ret
     }
 
+    #[cfg(feature = "generate")]
     fn to_pascal_case(&self) -> String {
-        self.split('_')
-            .map(|s| s.chars().next().unwrap().to_uppercase().collect::<String>())
+        self.split(|c: char| !c.is_alphanumeric())
+            .filter(|s| !s.is_empty())
+            .map(|s| {
+                let mut chars = s.chars();
+                let first = chars.next().unwrap().to_uppercase();
+                first.chain(chars).collect::<String>()
+            })
             .collect::<Vec<_>>()
             .join("")
     }
+
+    fn to_snake_case(&self) -> String {
+        let mut ret = "".to_string();
+        let mut prev_lower = false;
+        for c in self.chars() {
+            if c.is_alphanumeric() {
+                if c.is_uppercase() && prev_lower {
+                    ret.push('_');
+                }
+                prev_lower = c.is_lowercase() || c.is_numeric();
+                ret.extend(c.to_lowercase());
+            } else {
+                if !ret.is_empty() && !ret.ends_with('_') {
+                    ret.push('_');
+                }
+                prev_lower = false;
+            }
+        }
+        ret.trim_end_matches('_').to_string()
+    }
 }
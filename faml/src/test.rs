@@ -137,6 +137,26 @@ field2 = 111
     Ok(())
 }
 
+// 测试按路径访问数组元素
+#[test]
+fn test_get_with_path() -> anyhow::Result<()> {
+    let faml_str = r#"
+[[servers]]
+port = 80
+
+[[servers]]
+port = 443
+"#;
+    let mut value = FamlExpr::from_str(faml_str)?.evaluate()?;
+    let port = value.get_with_path("servers.[1].port");
+    assert_eq!(port.and_then(|port| port.as_int()), Some(443));
+    *value.get_with_path_mut("servers.[0].port").unwrap() = FamlValue::Int64(8080);
+    let port = value.get_with_path("servers.[0].port");
+    assert_eq!(port.and_then(|port| port.as_int()), Some(8080));
+    assert!(value.get_with_path("servers.[2].port").is_none());
+    Ok(())
+}
+
 // 测试条件判断
 #[test]
 fn test_conditional_fields() -> anyhow::Result<()> {
@@ -730,3 +750,1744 @@ combined = native.concat_strings("Hello, ", "World!")
 
     Ok(())
 }
+
+// 测试 in / not in 运算符
+#[test]
+fn test_membership_operators() -> anyhow::Result<()> {
+    let faml_str = r#"
+[group]
+env = "prod"
+port = 8080
+in_array = env in ["prod", "staging"]
+not_in_array = env not in ["prod", "staging"]
+in_map = "a" in { a: 1, b: 2 }
+in_str = "ro" in env
+in_range = port in 1024..65535
+in_float_range = 1.5 in 1..=2.5
+in_duration_range = 3 seconds in 1 seconds..5 seconds
+"#;
+    let root = FamlExpr::from_str(faml_str)?;
+    let evaluated = root.evaluate()?;
+    assert_eq!(evaluated["group"]["in_array"].as_bool(), Some(true));
+    assert_eq!(evaluated["group"]["not_in_array"].as_bool(), Some(false));
+    assert_eq!(evaluated["group"]["in_map"].as_bool(), Some(true));
+    assert_eq!(evaluated["group"]["in_str"].as_bool(), Some(true));
+    assert_eq!(evaluated["group"]["in_range"].as_bool(), Some(true));
+    assert_eq!(evaluated["group"]["in_float_range"].as_bool(), Some(true));
+    assert_eq!(
+        evaluated["group"]["in_duration_range"].as_bool(),
+        Some(true)
+    );
+    Ok(())
+}
+
+// 测试范围与切片
+#[test]
+fn test_range_and_slice() -> anyhow::Result<()> {
+    let faml_str = r#"
+[group]
+list = [1, 2, 3, 4, 5]
+name = "hello.faml"
+range = 0..3
+range_inclusive = 1..=3
+last = list[-1]
+sub = list[1:3]
+tail = list[-2:]
+step = list[::2]
+rev = list[::-1]
+ext = name[-4:]
+"#;
+    let root = FamlExpr::from_str(faml_str)?;
+    let evaluated = root.evaluate()?;
+    let ints = |val: &FamlValue| -> Vec<i64> {
+        val.as_array()
+            .unwrap()
+            .iter()
+            .map(|v| v.as_int().unwrap())
+            .collect()
+    };
+    assert_eq!(ints(&evaluated["group"]["range"]), vec![0, 1, 2]);
+    assert_eq!(ints(&evaluated["group"]["range_inclusive"]), vec![1, 2, 3]);
+    assert_eq!(evaluated["group"]["last"].as_int(), Some(5));
+    assert_eq!(ints(&evaluated["group"]["sub"]), vec![2, 3]);
+    assert_eq!(ints(&evaluated["group"]["tail"]), vec![4, 5]);
+    assert_eq!(ints(&evaluated["group"]["step"]), vec![1, 3, 5]);
+    assert_eq!(ints(&evaluated["group"]["rev"]), vec![5, 4, 3, 2, 1]);
+    assert_eq!(evaluated["group"]["ext"].as_str(), "faml");
+
+    // 范围展开不溢出，元素过多时报错
+    let root = FamlExpr::from_str(
+        "[g]\nedge = 9223372036854775806..=9223372036854775807\nbig = 0..10000000000\n",
+    )?;
+    assert_eq!(
+        ints(&root["g"]["edge"].evaluate()?),
+        vec![i64::MAX - 1, i64::MAX]
+    );
+    let err = root["g"]["big"].evaluate().unwrap_err();
+    assert!(err.to_string().contains("more than the limit 1000000"));
+    Ok(())
+}
+
+// 测试约束中的范围检查
+#[test]
+fn test_range_constraint() -> anyhow::Result<()> {
+    let faml_str = r#"
+[server]
+@constraint value in 1024..65535
+port = 8080
+"#;
+    let mut root = FamlExpr::from_str(faml_str)?;
+    assert_eq!(root["server"]["port"].evaluate()?.as_int(), Some(8080));
+    root.apply(FamlExpr::from_str(
+        "[server]\n@constraint value in 1024..65535\nport = 80\n",
+    )?)?;
+    assert!(root["server"]["port"].evaluate().is_err());
+    Ok(())
+}
+
+// 测试 match 表达式
+#[test]
+fn test_match_expression() -> anyhow::Result<()> {
+    let faml_str = r#"
+[group]
+env = "staging"
+cpu = 12
+workers = match env { "prod" => 8, "staging" | "test" => 4, _ => 1 }
+level = match cpu {
+    0..=4 => "small",
+    n if n > 16 => "huge",
+    5..=16 => "medium",
+}
+nested = cpu > 8 ? cpu > 10 ? "a" : "b" : "c"
+"#;
+    let mut root = FamlExpr::from_str(faml_str)?;
+    let evaluated = root.evaluate()?;
+    assert_eq!(evaluated["group"]["workers"].as_int(), Some(4));
+    assert_eq!(evaluated["group"]["level"].as_str(), "medium");
+    assert_eq!(evaluated["group"]["nested"].as_str(), "a");
+
+    root["group"]["cpu"].set_int(32);
+    assert_eq!(root["group"]["level"].evaluate()?.as_str(), "huge");
+    let trace = root["group"]["workers"].trace("workers")?;
+    assert!(trace.contains(r#"workers = match env { "staging" | "test" => 4 } // =4"#));
+
+    // 已存在的字段名按值比较，其余裸名才绑定
+    let faml_str = r#"
+[group]
+cpu = 12
+limit = 12
+at_limit = match cpu { limit => "at", n => n }
+bound = match limit + 1 { limit => "at", n => n }
+bad_range = match "a" { 1..5 => 1, _ => 0 }
+"#;
+    let root = FamlExpr::from_str(faml_str)?;
+    root.check_refs()?;
+    assert_eq!(root["group"]["at_limit"].evaluate()?.as_str(), "at");
+    assert_eq!(root["group"]["bound"].evaluate()?.as_int(), Some(13));
+    assert!(root["group"]["bad_range"].evaluate().is_err());
+    Ok(())
+}
+
+// 测试展开、计算键与条件元素
+#[test]
+fn test_spread_and_computed_entries() -> anyhow::Result<()> {
+    let faml_str = r#"
+[group]
+debug = true
+key = "region"
+base_hosts = ["a", "b"]
+defaults = { timeout: 3 seconds, retry: 2 }
+hosts = [...super.base_hosts, "extra", if super.debug: "debug_host"]
+options = {
+    ...super.defaults
+    timeout: 5 seconds,
+    [super.key]: "cn",
+    "log-level": "info",
+    if !super.debug: retry: 0
+    timeout_str: timeout.to_str()
+}
+"#;
+    let root = FamlExpr::from_str(faml_str)?;
+    root.check_refs()?;
+    let evaluated = root.evaluate()?;
+    let hosts: Vec<_> = evaluated["group"]["hosts"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|v| v.as_str())
+        .collect();
+    assert_eq!(hosts, vec!["a", "b", "extra", "debug_host"]);
+    let options = &evaluated["group"]["options"];
+    assert_eq!(options["timeout"].as_str(), "5 seconds");
+    assert_eq!(options["retry"].as_int(), Some(2));
+    assert_eq!(options["region"].as_str(), "cn");
+    assert_eq!(options["log-level"].as_str(), "info");
+    // 与静态字面量相同，同级的键可以直接引用
+    assert_eq!(options["timeout_str"].as_str(), "5 seconds");
+    Ok(())
+}
+
+// 测试数组与哈希表推导式
+#[test]
+fn test_comprehensions() -> anyhow::Result<()> {
+    let faml_str = r#"
+[group]
+count = 4
+regions = ["cn", "us"]
+workers = [ $"w{i}" for i in 0..count ]
+evens = [n * 10 for n in [1, 2, 3, 4] if n % 2 == 0]
+endpoints = { r: $"https://{r}.example" for r in regions }
+indexed = { name: i for i, name in regions }
+ports = { web: 80, db: 5432 }
+port_names = [ k for k in ports ]
+"#;
+    let mut root = FamlExpr::from_str(faml_str)?;
+    let evaluated = root.evaluate()?;
+    assert_eq!(evaluated["group"]["workers"].as_str(), "[ w0, w1, w2, w3 ]");
+    assert_eq!(evaluated["group"]["evens"].as_str(), "[ 20, 40 ]");
+    assert_eq!(
+        evaluated["group"]["endpoints"]["us"].as_str(),
+        "https://us.example"
+    );
+    assert_eq!(evaluated["group"]["indexed"]["us"].as_int(), Some(1));
+    assert_eq!(evaluated["group"]["port_names"].as_str(), "[ web, db ]");
+
+    root["group"]["count"].set_int(16);
+    let workers = root["group"]["workers"].evaluate()?;
+    assert_eq!(workers.as_array().unwrap().len(), 16);
+    let trace = root["group"]["workers"].trace("workers")?;
+    assert!(trace.contains(r#"workers = [$"w{i}" for i in 0..count]"#));
+    Ok(())
+}
+
+// 测试格式化字符串中的格式说明符
+#[test]
+fn test_format_specifiers() -> anyhow::Result<()> {
+    let faml_str = r#"
+[group]
+ratio = 0.1 + 0.2
+port = 80
+size = 42
+flags = 255
+delay = 2.5 mins
+dist = 1500 meters
+ratio_str = $"ratio={ratio:.2}"
+port_str = $"port={port:05}"
+size_str = $"[{size:>6}][{size:<6}][{size:*^6}]"
+flags_str = $"{flags:x} {flags:#X} {flags:b}"
+delay_str = $"{delay:secs}s {delay:.1hours}h"
+dist_str = $"{dist:km} km"
+name_str = $"[{"abc":>5}][{"abcdef":.3}]"
+"#;
+    let root = FamlExpr::from_str(faml_str)?;
+    let evaluated = root.evaluate()?;
+    let group = &evaluated["group"];
+    assert_eq!(group["ratio_str"].as_str(), "ratio=0.30");
+    assert_eq!(group["port_str"].as_str(), "port=00080");
+    assert_eq!(group["size_str"].as_str(), "[    42][42    ][**42**]");
+    assert_eq!(group["flags_str"].as_str(), "ff 0xFF 11111111");
+    assert_eq!(group["delay_str"].as_str(), "150s 0.0h");
+    assert_eq!(group["dist_str"].as_str(), "1.5 km");
+    assert_eq!(group["name_str"].as_str(), "[  abc][abc]");
+
+    let trace = root["group"]["ratio_str"].trace("ratio_str")?;
+    assert!(trace.contains(r#"ratio_str = $"ratio={ratio:.2}" // =ratio=0.30"#));
+
+    assert!(FamlExpr::from_str("[g]\na = $\"{1:.}\"\n").is_err());
+    assert!(FamlExpr::from_str("[g]\na = $\"{1:q}\"\n").is_err());
+    let root = FamlExpr::from_str("[g]\na = $\"{\"x\":x}\"\n")?;
+    assert!(root.evaluate().is_err());
+    Ok(())
+}
+
+// 测试生成 Rust 配置结构体
+#[cfg(feature = "generate")]
+#[test]
+fn test_gen_rust() -> anyhow::Result<()> {
+    let faml_str = r#"
+[server]
+host = "localhost"
+port = 8080
+timeout = 2.5 mins
+logLevel = "info"
+type = "http"
+range = 1.5 kilometers
+
+[server.tls]
+enabled = true
+
+[[workers]]
+name = "w0"
+weight = 1
+
+[[workers]]
+name = "w1"
+weight = 0.5
+retry = 3
+"#;
+    let root = FamlExpr::from_str(faml_str)?;
+    let code = crate::idl_gen::IDLGenerator::gen_rust(&root)?;
+    assert!(code.contains(
+        "pub struct Config {\n    pub server: Server,\n    pub workers: Vec<WorkersItem>,\n}"
+    ));
+    assert!(code.contains("    #[serde(rename = \"logLevel\")]\n    pub log_level: String,"));
+    assert!(code.contains("    pub timeout: std::time::Duration,"));
+    assert!(code.contains("    pub range: f64,"));
+    assert!(code.contains("    pub tls: ServerTls,"));
+    assert!(code.contains("    pub r#type: String,"));
+    assert!(code.contains("    #[serde(default)]\n    pub retry: Option<i64>,"));
+    assert!(code.contains("    pub weight: f64,"));
+
+    // 生成的结构体可直接反序列化
+    #[derive(Debug, Clone, serde::Deserialize)]
+    pub struct ServerTls {
+        pub enabled: bool,
+    }
+    #[derive(Debug, Clone, serde::Deserialize)]
+    pub struct Server {
+        pub host: String,
+        pub port: i64,
+        #[serde(deserialize_with = "crate::serde_units::deserialize")]
+        pub timeout: std::time::Duration,
+        #[serde(rename = "logLevel")]
+        pub log_level: String,
+        pub r#type: String,
+        #[serde(deserialize_with = "crate::serde_units::deserialize")]
+        pub range: f64,
+        pub tls: ServerTls,
+    }
+    #[derive(Debug, Clone, serde::Deserialize)]
+    pub struct WorkersItem {
+        pub name: String,
+        pub weight: f64,
+        #[serde(default)]
+        pub retry: Option<i64>,
+    }
+    #[derive(Debug, Clone, serde::Deserialize)]
+    pub struct Config {
+        pub server: Server,
+        pub workers: Vec<WorkersItem>,
+    }
+    let config: Config = root.deserialize()?;
+    assert_eq!(config.server.host, "localhost");
+    assert_eq!(config.server.log_level, "info");
+    assert_eq!(config.server.r#type, "http");
+    assert_eq!(config.server.port, 8080);
+    assert_eq!(config.server.timeout, std::time::Duration::from_secs(150));
+    assert_eq!(config.server.range, 1500.0);
+    assert!(config.server.tls.enabled);
+    assert_eq!(config.workers[1].name, "w1");
+    assert_eq!(config.workers[1].weight, 0.5);
+    assert_eq!(config.workers[0].retry, None);
+    assert_eq!(config.workers[1].retry, Some(3));
+    Ok(())
+}
+
+// 测试生成 C#、C++ 与 TypeScript 类型
+#[cfg(feature = "generate")]
+#[test]
+fn test_gen_other_languages() -> anyhow::Result<()> {
+    let faml_str = r#"
+[server]
+port = 8080
+timeout = 2.5 mins
+tags = ["a", "b"]
+
+[[workers]]
+name = "w0"
+
+[[workers]]
+name = "w1"
+retry = 3
+"#;
+    let root = FamlExpr::from_str(faml_str)?;
+    let csharp = crate::idl_gen::IDLGenerator::gen_csharp(&root)?;
+    assert!(csharp.contains("    public TimeSpan timeout => pval[\"timeout\"].as_duration();"));
+    assert!(csharp.contains(
+        "    public long? retry => pval[\"retry\"].is_int() ? (long?)pval[\"retry\"].as_int() : null;"
+    ));
+    assert!(csharp.contains("    public List<WorkersItem> workers => Enumerable.Range(0, pval[\"workers\"].get_array_length()).Select(i0 => new WorkersItem(pval[\"workers\"][i0])).ToList();"));
+
+    let cpp = crate::idl_gen::IDLGenerator::gen_cpp(&root)?;
+    assert!(cpp.contains("  int64_t port() { return value_[\"port\"].as_int(); }"));
+    assert!(cpp.contains("  std::optional<int64_t> retry() { return value_[\"retry\"].is_int() ? std::optional<int64_t>(value_[\"retry\"].as_int()) : std::nullopt; }"));
+    assert!(cpp.contains("  Server server() { return Server(value_[\"server\"]); }"));
+
+    let ts = crate::idl_gen::IDLGenerator::gen_typescript(&root)?;
+    assert!(ts.contains("export interface Server {\n  port: number;\n  /** duration, e.g. \"2.5 mins\" */\n  timeout: string;\n  tags: string[];\n}"));
+    assert!(ts.contains("  retry?: number | null;"));
+    assert!(ts.contains("  workers: WorkersItem[];"));
+    Ok(())
+}
+
+// 测试 JSON Schema 导出与校验
+#[test]
+fn test_json_schema() -> anyhow::Result<()> {
+    let faml_str = r#"
+[server]
+@constraint value > 0 && value <= 65535
+port = 8080
+@constraint value in ["debug", "info", "warn", "error"]
+log_level = "info"
+@constraint len() < 64
+name = "main"
+@constraint value in 1..=16
+workers = 4
+@constraint value % 2 == 0
+slots = 8
+timeout = 5 seconds
+
+[[hosts]]
+addr = "a"
+
+[[hosts]]
+addr = "b"
+weight = 2
+"#;
+    let root = FamlExpr::from_str(faml_str)?;
+    let schema = root.to_json_schema()?;
+    let server = &schema["properties"]["server"]["properties"];
+    assert_eq!(server["port"]["type"], "integer");
+    assert_eq!(server["port"]["exclusiveMinimum"], 0);
+    assert_eq!(server["port"]["maximum"], 65535);
+    assert_eq!(server["log_level"]["enum"][1], "info");
+    assert_eq!(server["name"]["maxLength"], 63);
+    assert_eq!(server["workers"]["minimum"], 1);
+    assert_eq!(server["workers"]["maximum"], 16);
+    assert_eq!(server["slots"]["multipleOf"], 2);
+    assert_eq!(server["timeout"]["type"], "string");
+    let hosts = &schema["properties"]["hosts"]["items"];
+    assert_eq!(hosts["required"], serde_json::json!(["addr"]));
+    assert_eq!(hosts["properties"]["weight"]["type"], "integer");
+    root.validate_json_schema(&schema)?;
+
+    let mut root2 = FamlExpr::from_str(faml_str)?;
+    root2["server"]["log_level"].set_string("eror");
+    root2["server"]["slots"].set_string("8");
+    let err = root2.evaluate()?.validate_json_schema(&schema).unwrap_err();
+    let err = err.to_string();
+    assert!(err.contains(r#"server.log_level: value["eror"] is not one of"#));
+    assert!(err.contains("server.slots: expected type[integer], got type[string]"));
+
+    let long_name = faml_str.replace(
+        r#"name = "main""#,
+        &format!("name = \"{}\"", "x".repeat(64)),
+    );
+    let root3 = FamlExpr::from_str(&long_name)?;
+    assert!(root3["server"]["name"].evaluate().is_err());
+
+    // 不支持的关键字直接报错，而不是被忽略
+    let unsupported = serde_json::json!({
+        "$id": "https://example.com/config.json",
+        "properties": {
+            "server": {
+                "propertyNames": { "maxLength": 3 },
+                "properties": { "name": { "format": "hostname" } }
+            },
+            "extra": { "$ref": "other.json#/port" }
+        }
+    });
+    let err = root.validate_json_schema(&unsupported).unwrap_err();
+    let err = err.to_string();
+    assert!(err.contains("#/properties/server: keyword[propertyNames] is not supported"));
+    assert!(err.contains("#/properties/server/properties/name: keyword[format] is not supported"));
+    assert!(err.contains("#/properties/extra/$ref: only local $ref is supported"));
+    Ok(())
+}
+
+// 测试类型声明与带类型的字段
+#[test]
+fn test_type_declarations() -> anyhow::Result<()> {
+    let faml_str = r#"
+type Server = {
+    host: string
+    port: int
+    timeout: duration
+    weight: float?
+}
+
+[app]
+name: string = "demo"
+ratio: float = 1
+tags: [string] = ["a", "b"]
+
+[[servers: Server]]
+host = "a.local"
+port = 80
+timeout = 3 seconds
+
+[[servers: Server]]
+host = "b.local"
+port = 8080
+timeout = 1 mins
+weight = 2
+"#;
+    let mut root = FamlExpr::from_str(faml_str)?;
+    let value = root.evaluate()?;
+    assert_eq!(value["app"]["ratio"], FamlValue::Float64(1.0));
+    assert_eq!(value["servers"][1]["weight"], FamlValue::Float64(2.0));
+    assert!(value["servers"][0]["weight"].is_none());
+    assert_eq!(root["servers"][1]["host"].evaluate()?.as_str(), "b.local");
+
+    // 表达式的值在计算时检查
+    let limit = FamlExpr::from_str("[app]\nratio = 1.5\nlimit: int = ratio * 10\n")?;
+    let err = limit["app"]["limit"].evaluate().unwrap_err().to_string();
+    assert_eq!(err, "app.limit: expected type[int], got value[15]");
+
+    // 修改值时保留类型
+    root["app"]["name"].set_int(1);
+    assert!(root["app"]["name"].evaluate().is_err());
+    root["app"]["name"].set_string("demo2");
+    assert_eq!(root["app"]["name"].evaluate()?.as_str(), "demo2");
+
+    // 字面量在解析时检查
+    let err = FamlExpr::from_str("[app]\nport: int = \"80\"\n").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "app.port: expected type[int], got value[\"80\"]"
+    );
+    let err = FamlExpr::from_str("[app: Srv]\nport = 80\n").unwrap_err();
+    assert_eq!(err.to_string(), "type[Srv] not found");
+
+    let type_decl = "type Server = { host: string, port: int }\n";
+    let missing = FamlExpr::from_str(&format!(
+        "{type_decl}[[servers: Server]]\nhost = \"a\"\nport = 80\n[[servers: Server]]\nhost = \"b\"\n"
+    ))?;
+    assert_eq!(
+        missing.evaluate().unwrap_err().to_string(),
+        "servers[1].port: missing field of type[int]"
+    );
+    let unknown = FamlExpr::from_str(&format!(
+        "{type_decl}[server: Server]\nhost = \"a\"\nport = 80\nprot = 81\n"
+    ))?;
+    assert_eq!(
+        unknown.evaluate().unwrap_err().to_string(),
+        "server: unknown field[prot] for type[Server]"
+    );
+
+    // 带类型的分组可被引用
+    let refs = FamlExpr::from_str(&format!(
+        "{type_decl}[server: Server]\nhost = \"a\"\nport = 80\n[client]\nport = base.server.port + 1\n"
+    ))?;
+    assert_eq!(refs["client"]["port"].evaluate()?.as_int(), Some(81));
+    Ok(())
+}
+
+// 测试声明的类型用于代码生成与 JSON Schema
+#[cfg(feature = "generate")]
+#[test]
+fn test_type_declarations_codegen() -> anyhow::Result<()> {
+    let faml_str = r#"
+type Server = { host: string, port: int, weight: float? }
+
+[app]
+ratio: float = 1
+name = "demo"
+
+[[servers: Server]]
+host = "a.local"
+port = 80
+"#;
+    let root = FamlExpr::from_str(faml_str)?;
+    let code = crate::idl_gen::IDLGenerator::gen_rust(&root)?;
+    assert!(code.contains(
+        "pub struct Server {\n    pub host: String,\n    pub port: i64,\n    #[serde(default)]\n    pub weight: Option<f64>,\n}"
+    ));
+    assert!(code.contains("    pub ratio: f64,"));
+    assert!(code.contains("    pub servers: Vec<Server>,"));
+    let code = crate::idl_gen::IDLGenerator::gen_typescript(&root)?;
+    assert!(code.contains(
+        "export interface Server {\n  host: string;\n  port: number;\n  weight?: number | null;\n}"
+    ));
+
+    let schema = root.to_json_schema()?;
+    let server = &schema["properties"]["servers"]["items"];
+    assert_eq!(server["title"], "Server");
+    assert_eq!(server["required"], serde_json::json!(["host", "port"]));
+    assert_eq!(server["additionalProperties"], false);
+    assert_eq!(
+        server["properties"]["weight"]["type"],
+        serde_json::json!(["number", "null"])
+    );
+    assert_eq!(
+        schema["properties"]["app"]["properties"]["ratio"]["type"],
+        "number"
+    );
+    root.validate_json_schema(&schema)?;
+    Ok(())
+}
+
+// 测试枚举声明与取值校验
+#[test]
+fn test_enum_declarations() -> anyhow::Result<()> {
+    let faml_str = r#"
+enum LogLevel { debug, info, warn, error }
+enum Transport {
+    tcp
+    "tcp+tls"
+}
+type Listener = { transport: Transport, port: int }
+
+[app]
+log_level: LogLevel = "info"
+levels: [LogLevel] = ["debug", "warn"]
+listener: Listener = { transport: "tcp+tls", port: 443 }
+"#;
+    let root = FamlExpr::from_str(faml_str)?;
+    let value = root.evaluate()?;
+    assert_eq!(value["app"]["log_level"].as_str(), "info");
+    assert_eq!(value["app"]["listener"]["transport"].as_str(), "tcp+tls");
+
+    let err = FamlExpr::from_str(&faml_str.replace("\"info\"", "\"eror\"")).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "app.log_level: value[\"eror\"] is not a variant of enum[LogLevel], did you mean \"error\"?"
+    );
+    let root2 = FamlExpr::from_str(&faml_str.replace("\"warn\"]", "\"verbose\"]"))?;
+    assert_eq!(
+        root2["app"]["levels"].evaluate().unwrap_err().to_string(),
+        "app.levels[1]: value[\"verbose\"] is not a variant of enum[LogLevel], expected one of [debug, info, warn, error]"
+    );
+    let root3 = FamlExpr::from_str(&faml_str.replace("\"tcp+tls\",", "\"tpc\","))?;
+    assert_eq!(
+        root3["app"]["listener"].evaluate().unwrap_err().to_string(),
+        "app.listener.transport: value[\"tpc\"] is not a variant of enum[Transport], did you mean \"tcp\"?"
+    );
+    assert!(FamlExpr::from_str("enum A { x, x }\n[app]\na = 1\n").is_err());
+    Ok(())
+}
+
+// 测试枚举生成原生 Rust 枚举
+#[cfg(feature = "generate")]
+#[test]
+fn test_enum_codegen() -> anyhow::Result<()> {
+    let faml_str = r#"
+enum LogLevel { debug, info, warn, error }
+enum Transport { tcp, "tcp+tls" }
+
+[app]
+log_level: LogLevel = "warn"
+transport: Transport = "tcp+tls"
+"#;
+    let root = FamlExpr::from_str(faml_str)?;
+    let code = crate::idl_gen::IDLGenerator::gen_rust(&root)?;
+    assert!(code.contains("#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]\npub enum LogLevel {\n    #[serde(rename = \"debug\")]\n    Debug,\n"));
+    assert!(code.contains("    #[serde(rename = \"tcp+tls\")]\n    TcpTls,\n"));
+    assert!(code.contains("    pub log_level: LogLevel,\n"));
+    let code = crate::idl_gen::IDLGenerator::gen_typescript(&root)?;
+    assert!(code.contains("export type Transport = \"tcp\" | \"tcp+tls\";"));
+    assert_eq!(
+        root.to_json_schema()?["properties"]["app"]["properties"]["log_level"]["enum"],
+        serde_json::json!(["debug", "info", "warn", "error"])
+    );
+
+    // 生成的枚举可直接反序列化
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+    pub enum Transport {
+        #[serde(rename = "tcp")]
+        Tcp,
+        #[serde(rename = "tcp+tls")]
+        TcpTls,
+    }
+    #[derive(Debug, Clone, serde::Deserialize)]
+    pub struct App {
+        pub transport: Transport,
+    }
+    #[derive(Debug, Clone, serde::Deserialize)]
+    pub struct Config {
+        pub app: App,
+    }
+    let config: Config = root.deserialize()?;
+    assert_eq!(config.app.transport, Transport::TcpTls);
+    Ok(())
+}
+
+// 测试直接反序列化、按路径报错与时长精度
+#[test]
+fn test_direct_deserializer() -> anyhow::Result<()> {
+    #[derive(Debug, serde::Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    enum Mode {
+        Fast,
+        Slow,
+    }
+    #[derive(Debug, serde::Deserialize)]
+    struct Host {
+        name: String,
+        weight: Option<f64>,
+    }
+    #[derive(Debug, serde::Deserialize)]
+    struct Server {
+        port: u16,
+        timeout: std::time::Duration,
+        mode: Mode,
+        hosts: Vec<Host>,
+    }
+    #[derive(Debug, serde::Deserialize)]
+    struct Config {
+        server: Server,
+    }
+
+    let faml_str = r#"
+[server]
+port = 8080
+timeout = 2.0576 mins
+mode = "slow"
+hosts = [{ name: "a" }, { name: "b", weight: 0.5 }]
+unused = missing_field + 1
+"#;
+    let root = FamlExpr::from_str(faml_str)?;
+    // 未被请求的字段不会被计算
+    assert!(root.evaluate().is_err());
+    let config: Config = root.deserialize()?;
+    assert_eq!(config.server.port, 8080);
+    assert!(matches!(config.server.mode, Mode::Slow));
+    assert_eq!(config.server.hosts[1].weight, Some(0.5));
+    assert_eq!(config.server.hosts[0].name, "a");
+    let FamlValue::Duration(timeout) = root["server"]["timeout"].evaluate()? else {
+        panic!("duration expected");
+    };
+    assert_eq!(config.server.timeout, timeout);
+
+    let check_err = |from: &str, to: &str, err: &str| -> anyhow::Result<()> {
+        let root = FamlExpr::from_str(&faml_str.replace(from, to))?;
+        assert_eq!(root.deserialize::<Config>().unwrap_err().to_string(), err);
+        Ok(())
+    };
+    check_err("8080", "\"8080\"", "server.port: expected u16, got string")?;
+    check_err(
+        "8080",
+        "70000",
+        "server.port: expected u16, got integer `70000`",
+    )?;
+    check_err(
+        "0.5",
+        "\"0.5\"",
+        "server.hosts[1].weight: expected f64, got string",
+    )?;
+    check_err("port = 8080\n", "", "server: missing field `port`")?;
+    check_err(
+        "\"slow\"",
+        "\"slower\"",
+        "server.mode: unknown variant `slower`, expected `fast` or `slow`",
+    )?;
+
+    // FamlValue 同样按路径报错
+    let faml_str = faml_str.replace("unused = missing_field + 1\n", "");
+    let value = FamlExpr::from_str(&faml_str.replace("\"a\" }", "1 }"))?.evaluate()?;
+    let err = value["server"].deserialize::<Server>().unwrap_err();
+    assert_eq!(err.to_string(), "hosts[0].name: expected a string, got int");
+
+    // 自描述的目标类型得到与 to_json 相同的字符串
+    #[derive(Debug, serde::Deserialize)]
+    #[serde(untagged)]
+    enum Limit {
+        Text(String),
+        Number(f64),
+    }
+    let value = FamlExpr::from_str("[g]\ndelay = 2.5 seconds\nrange = 1.5 kilometers\n")?;
+    let json: serde_json::Value = value.deserialize()?;
+    assert_eq!(json, value.evaluate()?.to_json());
+    assert_eq!(json["g"]["delay"], "2.5 seconds");
+    let limit: Limit = value["g"]["range"].deserialize()?;
+    assert!(matches!(limit, Limit::Text(s) if s == "1.5 kilometers"));
+    let limit: Limit = FamlExpr::from_str("[g]\nlimit = 2.5\n")?["g"]["limit"].deserialize()?;
+    assert!(matches!(limit, Limit::Number(n) if n == 2.5));
+    let meters: f64 = value["g"]["range"].deserialize()?;
+    assert_eq!(meters, 1500.0);
+    Ok(())
+}
+
+// 测试从 Rust 结构序列化为默认配置并应用覆盖
+#[test]
+fn test_to_expr_serializer() -> anyhow::Result<()> {
+    #[derive(serde::Serialize)]
+    #[serde(rename_all = "lowercase")]
+    enum Mode {
+        Fast,
+    }
+    #[derive(serde::Serialize)]
+    struct Server {
+        port: u16,
+        host: String,
+        timeout: std::time::Duration,
+        mode: Mode,
+        tags: Vec<&'static str>,
+        backup: Option<String>,
+    }
+    #[derive(serde::Serialize)]
+    struct Config {
+        server: Server,
+    }
+
+    let defaults = Config {
+        server: Server {
+            port: 8080,
+            host: "localhost".to_string(),
+            timeout: std::time::Duration::from_millis(2500),
+            mode: Mode::Fast,
+            tags: vec!["a", "b"],
+            backup: None,
+        },
+    };
+    // 字段顺序与结构体声明一致
+    let value = crate::to_value(&defaults)?;
+    assert_eq!(
+        serde_json::to_string(&value)?,
+        r#"{"server":{"port":8080,"host":"localhost","timeout":"2.5 seconds","mode":"fast","tags":["a","b"],"backup":null}}"#
+    );
+    assert_eq!(
+        value["server"]["timeout"],
+        FamlValue::Duration(std::time::Duration::from_millis(2500))
+    );
+
+    // 用 faml 公式覆盖与扩展默认值
+    let mut root = crate::to_expr(&defaults)?;
+    root.apply(FamlExpr::from_str(
+        r#"
+[server]
+port = 9000
+url = $"http://{host}:{port}"
+retry_timeout = timeout * 2.0
+"#,
+    )?)?;
+    let value = root.evaluate()?;
+    assert_eq!(value["server"]["port"].as_int(), Some(9000));
+    assert_eq!(value["server"]["url"].as_str(), "http://localhost:9000");
+    assert_eq!(value["server"]["mode"].as_str(), "fast");
+    assert_eq!(
+        value["server"]["retry_timeout"],
+        FamlValue::Duration(std::time::Duration::from_secs(5))
+    );
+
+    // 同名但字段不同的用户结构体不会被当作时长
+    #[derive(serde::Serialize)]
+    struct Duration {
+        nanos: i64,
+        secs: i64,
+    }
+    let value = crate::to_value(&Duration { nanos: 1, secs: 2 })?;
+    assert_eq!(serde_json::to_string(&value)?, r#"{"nanos":1,"secs":2}"#);
+
+    // 超出 int 范围的 u64 报错而不是转为浮点数
+    let err = crate::to_value(&u64::MAX).unwrap_err().to_string();
+    assert_eq!(
+        err,
+        "value[18446744073709551615] is out of range of type[int]"
+    );
+    Ok(())
+}
+
+// 测试 derive(Faml) 按路径绑定字段、默认值、单位换算与写回
+#[cfg(feature = "derive")]
+#[test]
+fn test_derive_faml() -> anyhow::Result<()> {
+    use crate::bind::FamlBind;
+
+    fn default_name() -> String {
+        "main".to_string()
+    }
+
+    #[derive(crate::Faml)]
+    #[faml(path = "server")]
+    struct ServerCfg {
+        port: u16,
+        timeout: std::time::Duration,
+        #[faml(path = "timeout", unit = "milliseconds")]
+        timeout_ms: u64,
+        #[faml(unit = "kilometers")]
+        range: f64,
+        url: String,
+        #[faml(default = "default_name")]
+        name: String,
+        #[faml(default)]
+        workers: usize,
+        backup: Option<String>,
+    }
+
+    let faml_str = r#"
+[server]
+port = 8080
+timeout = 2.5 seconds
+range = 1500 meters
+url = $"http://localhost:{port}"
+"#;
+    let mut root = FamlExpr::from_str(faml_str)?;
+    let mut cfg = ServerCfg::from_expr(&root)?;
+    assert_eq!(cfg.port, 8080);
+    assert_eq!(cfg.timeout, std::time::Duration::from_millis(2500));
+    assert_eq!(cfg.timeout_ms, 2500);
+    assert_eq!(cfg.range, 1.5);
+    assert_eq!(cfg.name, "main");
+    assert_eq!(cfg.workers, 0);
+    assert_eq!(cfg.backup, None);
+
+    // 写回后依赖该字段的公式重新计算
+    cfg.set_port(&mut root, 9000)?;
+    assert_eq!(cfg.url, "http://localhost:9000");
+    cfg.set_timeout_ms(&mut root, 500)?;
+    assert_eq!(cfg.timeout, std::time::Duration::from_millis(500));
+    assert_eq!(
+        root["server"]["timeout"].evaluate()?,
+        FamlValue::Duration(std::time::Duration::from_millis(500))
+    );
+
+    let root = FamlExpr::from_str(&faml_str.replace("8080", "\"8080\""))?;
+    let err = ServerCfg::from_expr(&root).err().unwrap();
+    assert_eq!(err.to_string(), "server.port: expected u16, got string");
+    let root = FamlExpr::from_str(&faml_str.replace("port = 8080\n", ""))?;
+    let err = ServerCfg::from_expr(&root).err().unwrap();
+    assert_eq!(err.to_string(), "server.port: missing field");
+    Ok(())
+}
+
+// 测试引用的静态检查：未解析的名称与循环引用
+#[test]
+fn test_check_refs() -> anyhow::Result<()> {
+    let faml_str = r#"
+[server]
+port = 8080
+hosts = [h for h in ["a", "b"] if h != "c"]
+level = match port { 80 => "http", p if p > 1024 => "high", _ => "low" }
+@constraint value > 0
+workers = port / 1000
+
+[client]
+url = $"http://localhost:{base.server.port}"
+size = base.server.hosts.len()
+"#;
+    FamlExpr::from_str(faml_str)?.check_refs()?;
+
+    let root = FamlExpr::from_str(&faml_str.replace("server.port}", "server.prot}"))?;
+    let err = root.check_refs().unwrap_err().to_string();
+    assert_eq!(err, "client.url: unresolved name[base.server.prot]");
+
+    let root = FamlExpr::from_str("[a]\nx = y + 1\ny = z * 2\nz = x - 1\n")?;
+    let err = root.check_refs().unwrap_err().to_string();
+    assert_eq!(err, "reference cycle: a.x -> a.y -> a.z -> a.x");
+    Ok(())
+}
+
+// 测试编译期嵌入 faml 文件与生成的类型
+#[test]
+fn test_include_faml() -> anyhow::Result<()> {
+    faml_macros::include_faml_types!("testdata/defaults.faml");
+
+    let defaults: &'static FamlExpr = faml_macros::include_faml!("testdata/defaults.faml");
+    let value = defaults.evaluate()?;
+    assert_eq!(
+        value["client"]["endpoint"].as_str(),
+        "http://localhost:8080"
+    );
+    let config: Config = defaults.deserialize()?;
+    assert_eq!(config.server.port, 8080);
+    assert_eq!(config.client.retries, 3);
+    assert_eq!(
+        config.server.timeout,
+        std::time::Duration::from_millis(2500)
+    );
+    Ok(())
+}
+
+// 测试 native 函数参数的类型转换错误
+#[test]
+fn test_native_function_conversion_errors() -> anyhow::Result<()> {
+    crate::Native::add_func("port_of", |port: u16, hosts: Vec<String>| {
+        format!("{}:{port}", hosts.join(","))
+    });
+    crate::Native::add_func(
+        "timeout_secs",
+        |timeout: std::time::Duration, retries: Option<u8>| {
+            timeout.as_secs_f64() * retries.unwrap_or(1) as f64
+        },
+    );
+
+    let faml_str = r#"
+[calc]
+ok = native.port_of(8080, ["a", "b"])
+bad_type = native.port_of("8080", ["a"])
+bad_range = native.port_of(70000, ["a"])
+bad_item = native.port_of(80, ["a", 1])
+secs = native.timeout_secs(2 seconds, 3)
+no_retries = native.timeout_secs(2 seconds, null)
+bad_unit = native.timeout_secs(2 meters, null)
+"#;
+    let expr = FamlExpr::from_str(faml_str)?;
+    assert_eq!(expr["calc"]["ok"].evaluate()?.as_str(), "a,b:8080");
+    assert_eq!(expr["calc"]["secs"].evaluate()?.as_float(), Some(6.0));
+    assert_eq!(expr["calc"]["no_retries"].evaluate()?.as_float(), Some(2.0));
+    let check_err = |key: &str, err: &str| {
+        let ret = expr["calc"][key].evaluate().unwrap_err().to_string();
+        assert_eq!(ret, err);
+    };
+    check_err(
+        "bad_type",
+        "native func[port_of]: arg[0]: expected type[int], got value[\"8080\"]",
+    );
+    check_err(
+        "bad_range",
+        "native func[port_of]: arg[0]: value[70000] is out of range for type[u16]",
+    );
+    check_err(
+        "bad_item",
+        "native func[port_of]: arg[1]: [1]: expected type[string], got value[1]",
+    );
+    check_err(
+        "bad_unit",
+        "native func[timeout_secs]: arg[0]: expected type[duration], got value[2 meters]",
+    );
+
+    assert_eq!(u64::try_from(FamlValue::Float64(3.0))?, 3);
+    assert!(bool::try_from(FamlValue::Int64(1)).is_err());
+    Ok(())
+}
+
+// 测试附加到文档的函数注册表：命名空间、移除、列举与回退到全局函数
+#[test]
+fn test_function_registry() -> anyhow::Result<()> {
+    use crate::native::FunctionRegistry;
+
+    crate::Native::add_func("registry_global", |n: i64| n * 2);
+    let registry = FunctionRegistry::new();
+    registry.add_func("geo.distance", |a: f64, b: f64| (a - b).abs());
+    registry.add_func("registry_global", |n: i64| n * 3);
+    assert_eq!(
+        registry.func_names(),
+        vec!["geo.distance", "registry_global"]
+    );
+
+    let faml_str = r#"
+[calc]
+dist = native.geo.distance(1.5, 4.0)
+doubled = native.registry_global(5)
+"#;
+    let mut root = FamlExpr::from_str(faml_str)?;
+    // 未附加注册表时只能使用全局函数
+    assert_eq!(root["calc"]["doubled"].evaluate()?.as_int(), Some(10));
+    let err = root["calc"]["dist"].evaluate().unwrap_err().to_string();
+    assert_eq!(err, "native func[geo.distance] not found");
+
+    root.set_registry(registry.clone());
+    assert_eq!(root["calc"]["dist"].evaluate()?.as_float(), Some(2.5));
+    assert_eq!(root["calc"]["doubled"].evaluate()?.as_int(), Some(15));
+    // 其他文档不受影响
+    let other = FamlExpr::from_str(faml_str)?;
+    assert_eq!(other["calc"]["doubled"].evaluate()?.as_int(), Some(10));
+
+    assert!(registry.remove_func("registry_global"));
+    assert!(!registry.remove_func("registry_global"));
+    assert_eq!(root["calc"]["doubled"].evaluate()?.as_int(), Some(10));
+    Ok(())
+}
+
+// 测试返回 Result 的 native 函数、参数个数检查与可变参数函数
+#[test]
+fn test_native_function_fallible_and_variadic() -> anyhow::Result<()> {
+    crate::Native::add_func("checked_div", |a: i64, b: i64| match b {
+        0 => Err(anyhow::anyhow!("division by zero")),
+        b => Ok(a / b),
+    });
+    crate::Native::add_func("parse_port", |s: String| s.parse::<u16>().map(|p| p as i64));
+    crate::Native::add_variadic_func("sum_all", |args: Vec<FamlValue>| {
+        args.into_iter()
+            .map(i64::try_from)
+            .sum::<anyhow::Result<i64>>()
+    });
+
+    let faml_str = r#"
+[calc]
+div = native.checked_div(7, 2)
+div_zero = native.checked_div(7, 0)
+too_few = native.checked_div(7)
+too_many = native.checked_div(7, 2, 1)
+bad_port = native.parse_port("http")
+sum = native.sum_all(1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12)
+sum_empty = native.sum_all()
+sum_bad = native.sum_all(1, "2")
+"#;
+    let expr = FamlExpr::from_str(faml_str)?;
+    assert_eq!(expr["calc"]["div"].evaluate()?.as_int(), Some(3));
+    assert_eq!(expr["calc"]["sum"].evaluate()?.as_int(), Some(78));
+    assert_eq!(expr["calc"]["sum_empty"].evaluate()?.as_int(), Some(0));
+    let check_err = |key: &str, err: &str| {
+        let ret = expr["calc"][key].evaluate().unwrap_err().to_string();
+        assert_eq!(ret, err);
+    };
+    check_err("div_zero", "native func[checked_div]: division by zero");
+    check_err(
+        "too_few",
+        "native func[checked_div]: expected 2 args, got 1",
+    );
+    check_err(
+        "too_many",
+        "native func[checked_div]: expected 2 args, got 3",
+    );
+    check_err(
+        "bad_port",
+        "native func[parse_port]: invalid digit found in string",
+    );
+    check_err(
+        "sum_bad",
+        "native func[sum_all]: expected type[int], got value[\"2\"]",
+    );
+    Ok(())
+}
+
+// 测试带状态的 native 闭包与宿主对象的属性、方法按需读取
+#[test]
+fn test_native_host_objects() -> anyhow::Result<()> {
+    use crate::native::{FunctionRegistry, NativeObject};
+    use std::sync::{Arc, Mutex};
+
+    let registry = FunctionRegistry::new();
+    let mut count = 0;
+    registry.add_func_mut("next_id", move || {
+        count += 1;
+        count
+    });
+    // 普通 Fn 不加锁，可以重入
+    let inner = registry.clone();
+    registry.add_func("fact", move |n: i64| -> anyhow::Result<i64> {
+        match n {
+            ..=1 => Ok(1),
+            _ => {
+                let func = inner.get_func("fact").ok_or(anyhow::anyhow!("missing"))?;
+                Ok(n * func
+                    .call(vec![FamlValue::Int64(n - 1)])?
+                    .as_int()
+                    .unwrap_or(0))
+            }
+        }
+    });
+
+    let voltage = Arc::new(Mutex::new(12.5));
+    let reads = Arc::new(Mutex::new(0));
+    let mut battery = NativeObject::new();
+    let (voltage2, reads2) = (voltage.clone(), reads.clone());
+    battery.add_property("voltage", move || {
+        *reads2.lock().unwrap() += 1;
+        *voltage2.lock().unwrap()
+    });
+    let mut robot = NativeObject::new();
+    robot.add_object("battery", battery);
+    robot.add_property("name", || "r2".to_string());
+    robot.add_method("move_to", |x: i64, y: i64| format!("{x},{y}"));
+    registry.add_object("robot", robot);
+
+    let faml_str = r#"
+[status]
+id = native.next_id()
+fact = native.fact(5)
+low_power = native.robot.battery.voltage < 11.0
+title = native.robot.name.to_uppercase()
+target = native.robot.move_to(3, 4)
+missing = native.robot.arm
+object = native.robot.battery
+"#;
+    let mut root = FamlExpr::from_str(faml_str)?;
+    root.check_refs()?;
+    root.set_registry(registry);
+    // 属性在求值时才读取
+    assert_eq!(*reads.lock().unwrap(), 0);
+    assert_eq!(root["status"]["id"].evaluate()?.as_int(), Some(1));
+    assert_eq!(root["status"]["id"].evaluate()?.as_int(), Some(2));
+    assert_eq!(root["status"]["fact"].evaluate()?.as_int(), Some(120));
+    assert_eq!(
+        root["status"]["low_power"].evaluate()?.as_bool(),
+        Some(false)
+    );
+    *voltage.lock().unwrap() = 10.8;
+    assert_eq!(
+        root["status"]["low_power"].evaluate()?.as_bool(),
+        Some(true)
+    );
+    assert_eq!(*reads.lock().unwrap(), 2);
+    assert_eq!(root["status"]["title"].evaluate()?.as_str(), "R2");
+    assert_eq!(root["status"]["target"].evaluate()?.as_str(), "3,4");
+
+    let err = root["status"]["missing"]
+        .evaluate()
+        .unwrap_err()
+        .to_string();
+    assert_eq!(err, "native[robot.arm] not found");
+    let err = root["status"]["object"].evaluate().unwrap_err().to_string();
+    assert_eq!(err, "native[robot.battery] is an object");
+    Ok(())
+}
+
+// 测试为内置值类型注册扩展方法
+#[test]
+fn test_native_methods() -> anyhow::Result<()> {
+    use crate::expr::faml_value::Distance;
+    use crate::native::FunctionRegistry;
+
+    let registry = FunctionRegistry::new();
+
+    registry.add_method("string", "slug", |s: String| {
+        s.to_lowercase()
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join("-")
+    });
+    registry.add_method("string", "parse_endpoint", |s: String| {
+        let (host, port) = s
+            .split_once(':')
+            .ok_or_else(|| anyhow::anyhow!("missing port"))?;
+        let port: u16 = port.parse()?;
+        anyhow::Ok(FamlValue::Map(
+            [
+                ("host".to_string(), FamlValue::String(host.to_string())),
+                ("port".to_string(), FamlValue::Int64(port as i64)),
+            ]
+            .into_iter()
+            .collect(),
+        ))
+    });
+    registry.add_method("distance", "to_feet", |d: Distance| d.to_meters() / 0.3048);
+    registry.add_method("any", "describe", |v: FamlValue| v.type_name().to_string());
+    // 内置方法优先，内置方法的其他错误不会转而调用扩展方法
+    registry.add_method("string", "len", |_: String| -1);
+    registry.add_method("string", "repeat", |s: String, _: String| s);
+
+    let faml_str = r#"
+[site]
+title = "Hello Faml World"
+slug = title.slug()
+addr = "localhost:8080"
+endpoint = addr.parse_endpoint()
+height = 3.048 meters
+height_ft = height.to_feet()
+ids = [1, 2]
+kind = ids.describe()
+len = title.len()
+host = "localhost"
+bad = host.parse_endpoint()
+unknown = title.no_such_method()
+word = "ab"
+rep = word.repeat("x")
+"#;
+    let mut root = FamlExpr::from_str(faml_str)?;
+    root.set_registry(registry.clone());
+    assert_eq!(
+        root["site"]["slug"].evaluate()?.as_str(),
+        "hello-faml-world"
+    );
+    let endpoint = root["site"]["endpoint"].evaluate()?;
+    assert_eq!(endpoint.get("port").and_then(|p| p.as_int()), Some(8080));
+    let height_ft = root["site"]["height_ft"]
+        .evaluate()?
+        .as_float()
+        .unwrap_or_default();
+    assert!((height_ft - 10.0).abs() < 1e-9);
+    assert_eq!(root["site"]["kind"].evaluate()?.as_str(), "array");
+    assert_eq!(root["site"]["len"].evaluate()?.as_int(), Some(16));
+    let err = root["site"]["bad"].evaluate().unwrap_err().to_string();
+    assert_eq!(err, "method[string.parse_endpoint]: missing port");
+    let err = root["site"]["unknown"].evaluate().unwrap_err().to_string();
+    assert!(err.starts_with("unknown string.no_such_method"));
+    let err = root["site"]["rep"].evaluate().unwrap_err().to_string();
+    assert_eq!(err, "only type[int] arg for method[repeat]");
+    // 扩展方法只注册在文档的 registry 中
+    assert!(crate::Native::get_method("string", "slug").is_none());
+
+    assert!(registry.remove_method("string", "slug"));
+    assert!(root["site"]["slug"].evaluate().is_err());
+    Ok(())
+}
+
+// 测试异步 native 函数：独立字段并发求值，依赖异步结果的调用按轮次继续
+#[test]
+fn test_evaluate_async() -> anyhow::Result<()> {
+    use crate::native::FunctionRegistry;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::Poll;
+
+    let in_flight = Arc::new(AtomicUsize::new(0));
+    let max_in_flight = Arc::new(AtomicUsize::new(0));
+    let registry = FunctionRegistry::new();
+    let (in_flight2, max_in_flight2) = (in_flight.clone(), max_in_flight.clone());
+    registry.add_async_func("secrets.get", move |key: String| {
+        let (in_flight, max_in_flight) = (in_flight2.clone(), max_in_flight2.clone());
+        async move {
+            let count = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            max_in_flight.fetch_max(count, Ordering::SeqCst);
+            // 让出一次，其他调用得以同时进行
+            let mut yielded = false;
+            std::future::poll_fn(|cx| match yielded {
+                true => Poll::Ready(()),
+                false => {
+                    yielded = true;
+                    cx.waker().wake_by_ref();
+                    Poll::Pending
+                }
+            })
+            .await;
+            in_flight.fetch_sub(1, Ordering::SeqCst);
+            match &key[..] {
+                "db" => Ok("pg://db".to_string()),
+                "token" => Ok("abc".to_string()),
+                _ => Err(anyhow::anyhow!("unknown secret[{key}]")),
+            }
+        }
+    });
+    registry.add_async_func("secrets.len", |s: String| async move { s.len() as i64 });
+    registry.add_async_func(
+        "sum9",
+        |a: i64, b: i64, c: i64, d: i64, e: i64, f: i64, g: i64, h: i64, i: i64| async move {
+            a + b + c + d + e + f + g + h + i
+        },
+    );
+    let id_calls = Arc::new(AtomicUsize::new(0));
+    let id_calls2 = id_calls.clone();
+    registry.add_func("next_id", move || {
+        id_calls2.fetch_add(1, Ordering::SeqCst) as i64 + 1
+    });
+
+    let faml_str = r#"
+[app]
+db_url = native.secrets.get("db")
+token = native.secrets.get("token")
+header = $"Bearer {token}"
+token_len = native.secrets.len(token)
+sum = native.sum9(1, 2, 3, 4, 5, 6, 7, 8, 9)
+
+[seq]
+id = native.next_id()
+secret = native.secrets.get("db")
+secret_len = native.secrets.len(secret)
+
+[bad]
+missing = native.secrets.get("nope")
+"#;
+    let mut root = FamlExpr::from_str(faml_str)?;
+    root.set_registry(registry.clone());
+    let app = futures_executor::block_on(root["app"].evaluate_async())?;
+    assert_eq!(
+        app.get("db_url").map(|v| v.as_str()),
+        Some("pg://db".into())
+    );
+    assert_eq!(
+        app.get("header").map(|v| v.as_str()),
+        Some("Bearer abc".into())
+    );
+    assert_eq!(app.get("token_len").and_then(|v| v.as_int()), Some(3));
+    assert_eq!(app.get("sum").and_then(|v| v.as_int()), Some(45));
+    assert_eq!(max_in_flight.load(Ordering::SeqCst), 2);
+
+    // 同步函数在多轮求值中只调用一次
+    let seq = futures_executor::block_on(root["seq"].evaluate_async())?;
+    assert_eq!(seq.get("id").and_then(|v| v.as_int()), Some(1));
+    assert_eq!(seq.get("secret_len").and_then(|v| v.as_int()), Some(7));
+    assert_eq!(id_calls.load(Ordering::SeqCst), 1);
+
+    let err = root["app"]["token"].evaluate().unwrap_err().to_string();
+    assert_eq!(err, "native func[secrets.get] is async, use evaluate_async");
+    let err = futures_executor::block_on(root["bad"].evaluate_async()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "native func[secrets.get]: unknown secret[nope]"
+    );
+
+    // 异步函数创建 future 时可以再次求值 faml
+    let nested = registry.clone();
+    registry.add_async_func("nested", move |s: String| {
+        let mut expr = FamlExpr::from_str("[g]\nlen = native.secrets.len(\"abc\")\n").unwrap();
+        expr.set_registry(nested.clone());
+        let _ = expr.evaluate();
+        async move { s }
+    });
+    let mut root = FamlExpr::from_str("[g]\nval = native.nested(\"x\")\n")?;
+    root.set_registry(registry);
+    let g = futures_executor::block_on(root["g"].evaluate_async())?;
+    assert_eq!(g.get("val").map(|v| v.as_str()), Some("x".into()));
+    Ok(())
+}
+
+// 测试从磁盘加载 wasm 插件并限制燃料与内存
+#[cfg(feature = "wasm")]
+#[test]
+fn test_wasm_plugin() -> anyhow::Result<()> {
+    use crate::native::FunctionRegistry;
+    use crate::wasm::WasmPlugin;
+
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/plugins/demo.wat");
+    let plugin = WasmPlugin::load(path)?;
+    assert_eq!(plugin.name(), "demo");
+    assert_eq!(
+        plugin.func_names(),
+        vec!["answer", "echo", "fail", "grow", "spin"]
+    );
+    let registry = FunctionRegistry::new();
+    plugin.register(&registry);
+
+    let faml_str = r#"
+[plugin]
+answer = native.demo.answer()
+echo = native.demo.echo(1, "a", [true])
+fail = native.demo.fail()
+spin = native.demo.spin()
+grow = native.demo.grow()
+"#;
+    let mut root = FamlExpr::from_str(faml_str)?;
+    root.set_registry(registry);
+    assert_eq!(root["plugin"]["answer"].evaluate()?.as_int(), Some(42));
+    let echo = root["plugin"]["echo"].evaluate()?;
+    assert_eq!(echo.to_json(), serde_json::json!([1, "a", [true]]));
+    let err = root["plugin"]["fail"].evaluate().unwrap_err().to_string();
+    assert_eq!(err, "native func[demo.fail]: boom");
+    let err = root["plugin"]["spin"].evaluate().unwrap_err().to_string();
+    assert_eq!(err, "native func[demo.spin]: out of fuel");
+    let err = root["plugin"]["grow"].evaluate().unwrap_err().to_string();
+    assert_eq!(
+        err,
+        "native func[demo.grow]: out of memory, limit[16777216 bytes]"
+    );
+    Ok(())
+}
+
+// 测试字符串标准库方法及其 null/错误语义
+#[test]
+fn test_string_methods() -> anyhow::Result<()> {
+    let faml_str = r#"
+[s]
+id = "42"
+name = "hello World"
+path = "/api/v1/users.json"
+greeting = $"hi {name}"
+padded = id.pad_left(5, "0")
+padded2 = id.pad_right(4)
+sub = path.substr(1, 3)
+sub_end = path.substr(-4)
+sub_out = path.substr(100)
+char0 = name.char_at(0)
+char_last = name.char_at(-1)
+char_out = name.char_at(100)
+accent = "é!"
+split = accent.split_at(1)
+trimmed = "  a  "
+trim_start = trimmed.trim_start()
+trim_end = trimmed.trim_end()
+stripped = path.strip_prefix("/api")
+not_stripped = path.strip_suffix(".xml")
+num = id.to_int() + 1
+num_bad = name.to_int()
+ratio = "0.5"
+float = ratio.to_float()
+flag_str = " True "
+flag = flag_str.to_bool()
+cap = name.capitalize()
+snake = name.snake_case()
+kebab = greeting.kebab_case()
+fmt_tpl = "{}-{1}-{0}"
+fmt = fmt_tpl.format("a", 2)
+count = path.count("/")
+replaced = path.replace("/", "_", 2)
+in_fmt = $"{name.capitalize()}!"
+bad_fill = id.pad_left(5, "00")
+bad_fmt = fmt_tpl.format("a")
+"#;
+    let root = FamlExpr::from_str(faml_str)?;
+    let s = &root["s"];
+    let str_of = |key: &str| -> anyhow::Result<String> { Ok(s[key].evaluate()?.as_str()) };
+    assert_eq!(str_of("padded")?, "00042");
+    assert_eq!(str_of("padded2")?, "42  ");
+    assert_eq!(str_of("sub")?, "api");
+    assert_eq!(str_of("sub_end")?, "json");
+    assert_eq!(str_of("sub_out")?, "");
+    assert_eq!(str_of("char0")?, "h");
+    assert_eq!(str_of("char_last")?, "d");
+    assert!(s["char_out"].evaluate()?.is_none());
+    assert_eq!(str_of("split")?, "[ é, ! ]");
+    assert_eq!(str_of("trim_start")?, "a  ");
+    assert_eq!(str_of("trim_end")?, "  a");
+    assert_eq!(str_of("stripped")?, "/v1/users.json");
+    assert!(s["not_stripped"].evaluate()?.is_none());
+    assert_eq!(s["num"].evaluate()?.as_int(), Some(43));
+    assert!(s["num_bad"].evaluate()?.is_none());
+    assert_eq!(s["float"].evaluate()?.as_float(), Some(0.5));
+    assert_eq!(s["flag"].evaluate()?.as_bool(), Some(true));
+    assert_eq!(str_of("cap")?, "Hello World");
+    assert_eq!(str_of("snake")?, "hello_world");
+    assert_eq!(str_of("kebab")?, "hi-hello-world");
+    assert_eq!(str_of("fmt")?, "a-2-a");
+    assert_eq!(s["count"].evaluate()?.as_int(), Some(3));
+    assert_eq!(str_of("replaced")?, "_api_v1/users.json");
+    assert_eq!(str_of("in_fmt")?, "Hello World!");
+
+    let err = s["bad_fill"].evaluate().unwrap_err().to_string();
+    assert_eq!(err, "only single char fill for method[pad_left]");
+    let err = s["bad_fmt"].evaluate().unwrap_err().to_string();
+    assert_eq!(err, "missing arg[1] for format string");
+    Ok(())
+}
+
+// 测试数组标准库方法：排序、聚合与集合运算
+#[test]
+fn test_array_methods() -> anyhow::Result<()> {
+    let faml_str = r#"
+[[servers]]
+timeout = 2 seconds
+disk = 1.5 kilometers
+
+[[servers]]
+timeout = 500 milliseconds
+disk = 300 meters
+
+[calc]
+nums = [3, 1, 2, 3, 1]
+words = ["b", "c", "a"]
+sorted = nums.sort()
+sorted_desc = nums.sort_desc()
+unique = nums.unique()
+has_two = nums.contains(2)
+idx = nums.index_of(2)
+idx_none = nums.index_of(9)
+sum = nums.sum()
+avg = nums.avg()
+min = nums.min()
+max = words.max()
+first = nums.first()
+empty = []
+last_empty = empty.last()
+sum_empty = empty.sum()
+slice = nums.slice(1, -1)
+nested = [[1, 2], [3], 4]
+flat = nested.flatten()
+zipped = words.zip(nums)
+chunks = nums.chunk(2)
+joined = nums.concat([7], [8, 9])
+inter = nums.intersect([1, 3, 5])
+diff = nums.difference([1])
+timeouts = [s.timeout for s in base.servers]
+total_timeout = timeouts.sum()
+max_timeout = timeouts.max()
+disks = [s.disk for s in base.servers]
+total_disk = disks.sum()
+min_disk = disks.min()
+mixed = [1, "a"]
+bad_sort = mixed.sort()
+ties = [1, 2.0, 2, 1.0]
+ties_desc = ties.sort_desc()
+with_nan = [1.5, nan]
+nan_sort = with_nan.sort()
+nan_max = with_nan.max()
+"#;
+    let root = FamlExpr::from_str(faml_str)?;
+    let calc = &root["calc"];
+    let json_of =
+        |key: &str| -> anyhow::Result<serde_json::Value> { Ok(calc[key].evaluate()?.to_json()) };
+    assert_eq!(json_of("sorted")?, serde_json::json!([1, 1, 2, 3, 3]));
+    assert_eq!(json_of("sorted_desc")?, serde_json::json!([3, 3, 2, 1, 1]));
+    assert_eq!(json_of("unique")?, serde_json::json!([3, 1, 2]));
+    assert_eq!(calc["has_two"].evaluate()?.as_bool(), Some(true));
+    assert_eq!(calc["idx"].evaluate()?.as_int(), Some(2));
+    assert!(calc["idx_none"].evaluate()?.is_none());
+    assert_eq!(calc["sum"].evaluate()?.as_int(), Some(10));
+    assert_eq!(calc["avg"].evaluate()?.as_float(), Some(2.0));
+    assert_eq!(calc["min"].evaluate()?.as_int(), Some(1));
+    assert_eq!(calc["max"].evaluate()?.as_str(), "c");
+    assert_eq!(calc["first"].evaluate()?.as_int(), Some(3));
+    assert!(calc["last_empty"].evaluate()?.is_none());
+    assert_eq!(calc["sum_empty"].evaluate()?.as_int(), Some(0));
+    assert_eq!(json_of("slice")?, serde_json::json!([1, 2, 3]));
+    assert_eq!(json_of("flat")?, serde_json::json!([1, 2, 3, 4]));
+    assert_eq!(
+        json_of("zipped")?,
+        serde_json::json!([["b", 3], ["c", 1], ["a", 2]])
+    );
+    assert_eq!(json_of("chunks")?, serde_json::json!([[3, 1], [2, 3], [1]]));
+    assert_eq!(
+        json_of("joined")?,
+        serde_json::json!([3, 1, 2, 3, 1, 7, 8, 9])
+    );
+    assert_eq!(json_of("inter")?, serde_json::json!([3, 1]));
+    assert_eq!(json_of("diff")?, serde_json::json!([3, 2]));
+
+    use crate::expr::faml_value::Distance;
+    use std::time::Duration;
+    let total_timeout = Duration::try_from(calc["total_timeout"].evaluate()?)?;
+    assert_eq!(total_timeout, Duration::from_millis(2500));
+    let max_timeout = Duration::try_from(calc["max_timeout"].evaluate()?)?;
+    assert_eq!(max_timeout, Duration::from_secs(2));
+    let total_disk = Distance::try_from(calc["total_disk"].evaluate()?)?;
+    assert_eq!(total_disk.to_meters(), 1800.0);
+    let min_disk = Distance::try_from(calc["min_disk"].evaluate()?)?;
+    assert_eq!(min_disk.to_meters(), 300.0);
+
+    let err = calc["bad_sort"].evaluate().unwrap_err().to_string();
+    assert_eq!(err, r#"cannot compare value["a"] with value[1]"#);
+    // 降序排序同样稳定，NaN 无法参与比较
+    assert_eq!(json_of("ties_desc")?, serde_json::json!([2.0, 2, 1, 1.0]));
+    let err = calc["nan_sort"].evaluate().unwrap_err().to_string();
+    assert_eq!(err, "cannot compare value[NaN] with value[1.5]");
+    let err = calc["nan_max"].evaluate().unwrap_err().to_string();
+    assert_eq!(err, "cannot compare value[NaN] with value[1.5]");
+    Ok(())
+}
+
+// 测试映射标准库方法，以及 json###/yaml### 与 from_json 数据的查询
+#[test]
+fn test_map_methods() -> anyhow::Result<()> {
+    let faml_str = r#"
+[m]
+base_cfg = { host: "a", ports: [80], tls: { enabled: false, cert: "x" } }
+override = { ports: [443], tls: { enabled: true }, debug: true }
+codes = { ok: 200, missing: 404 }
+keys = base_cfg.keys()
+values = codes.values()
+entries = codes.entries()
+has_host = base_cfg.contains_key("host")
+port = codes.get("ok", 0)
+fallback = codes.get("teapot", 418)
+none = codes.get("teapot")
+merged = base_cfg.merge(override)
+removed = base_cfg.remove("ports", "tls")
+picked = base_cfg.pick(["host", "tls"])
+omitted = base_cfg.omit("tls")
+inverted = codes.invert()
+raw = json###{"name": "svc", "replicas": 3}###
+raw_keys = raw.keys()
+raw_name = raw.get("name")
+yml = yaml###
+name: svc
+zone: cn
+###
+yml_has_zone = yml.contains_key("zone")
+nested = { a: [1] }
+bad_invert = nested.invert()
+"#;
+    let root = FamlExpr::from_str(faml_str)?;
+    let m = &root["m"];
+    let json_of =
+        |key: &str| -> anyhow::Result<serde_json::Value> { Ok(m[key].evaluate()?.to_json()) };
+    assert_eq!(
+        json_of("keys")?,
+        serde_json::json!(["host", "ports", "tls"])
+    );
+    assert_eq!(json_of("values")?, serde_json::json!([200, 404]));
+    assert_eq!(
+        json_of("entries")?,
+        serde_json::json!([["ok", 200], ["missing", 404]])
+    );
+    assert_eq!(m["has_host"].evaluate()?.as_bool(), Some(true));
+    assert_eq!(m["port"].evaluate()?.as_int(), Some(200));
+    assert_eq!(m["fallback"].evaluate()?.as_int(), Some(418));
+    assert!(m["none"].evaluate()?.is_none());
+    assert_eq!(
+        json_of("merged")?,
+        serde_json::json!({
+            "host": "a",
+            "ports": [80, 443],
+            "tls": { "enabled": true, "cert": "x" },
+            "debug": true
+        })
+    );
+    assert_eq!(json_of("removed")?, serde_json::json!({ "host": "a" }));
+    assert_eq!(
+        json_of("picked")?,
+        serde_json::json!({ "host": "a", "tls": { "enabled": false, "cert": "x" } })
+    );
+    assert_eq!(
+        json_of("omitted")?,
+        serde_json::json!({ "host": "a", "ports": [80] })
+    );
+    assert_eq!(
+        json_of("inverted")?,
+        serde_json::json!({ "200": "ok", "404": "missing" })
+    );
+    assert_eq!(
+        json_of("raw_keys")?,
+        serde_json::json!(["name", "replicas"])
+    );
+    assert_eq!(m["raw_name"].evaluate()?.as_str(), "svc");
+    assert_eq!(m["yml_has_zone"].evaluate()?.as_bool(), Some(true));
+    let err = m["bad_invert"].evaluate().unwrap_err().to_string();
+    assert_eq!(err, "value[[ 1 ]] cannot be used as key for method[invert]");
+
+    // from_json 构建的数据同样可以查询
+    use crate::expr::invoke::InvokeExt;
+    let data = serde_json::json!({ "svc": { "replicas": 3, "zone": "cn" } });
+    let root = FamlExpr::from_json(data)?;
+    let mut svc = root["svc"].evaluate()?;
+    let keys = svc.invoke("keys", &vec![])?;
+    assert_eq!(keys.to_json(), serde_json::json!(["replicas", "zone"]));
+    Ok(())
+}
+
+// 测试 json###/yaml###/toml### 内嵌数据的成员访问、下标、方法与比较
+#[test]
+fn test_embedded_data() -> anyhow::Result<()> {
+    use crate::native::FunctionRegistry;
+
+    let registry = FunctionRegistry::new();
+    registry.add_func("host_cfg", || {
+        FamlValue::Json(serde_json::json!({ "zone": "cn", "nodes": [1, 2] }))
+    });
+
+    let faml_str = r#"
+[app]
+raw = json###{"server": {"host": "a", "ports": [80, 443]}}###
+host = raw.server.host
+port = raw.server.ports[-1]
+first_port = json###{"ports": [80, 443]}###["ports"][0]
+port_count = raw.server.ports.len()
+same = raw.server == { host: "a", ports: [80, 443] }
+differ = raw.server.ports != [80]
+yml = yaml###
+name: svc
+tags: [a, b]
+###
+tag = yml.tags[1]
+has_b = "b" in yml.tags
+tml = toml###
+title = "demo"
+[owner]
+name = "tom"
+###
+owner = tml.owner.name
+title_upper = tml.title.to_uppercase()
+host_zone = native.host_cfg()["zone"]
+host_nodes = native.host_cfg()["nodes"] == [1, 2]
+inline_host = json###{"server": {"host": "b"}}###.server.host
+inline_port = json###{"ports": [80, 443]}###["ports"][1]
+codes = yaml###
+200: ok
+404: missing
+true: yes
+1.5: half
+###
+"#;
+    let mut root = FamlExpr::from_str(faml_str)?;
+    root.set_registry(registry);
+    let app = &root["app"];
+    assert_eq!(app["host"].evaluate()?.as_str(), "a");
+    assert_eq!(app["port"].evaluate()?.as_int(), Some(443));
+    assert_eq!(app["first_port"].evaluate()?.as_int(), Some(80));
+    assert_eq!(app["port_count"].evaluate()?.as_int(), Some(2));
+    assert_eq!(app["same"].evaluate()?.as_bool(), Some(true));
+    assert_eq!(app["differ"].evaluate()?.as_bool(), Some(true));
+    assert_eq!(app["tag"].evaluate()?.as_str(), "b");
+    assert_eq!(app["has_b"].evaluate()?.as_bool(), Some(true));
+    assert_eq!(app["owner"].evaluate()?.as_str(), "tom");
+    assert_eq!(app["title_upper"].evaluate()?.as_str(), "DEMO");
+    assert_eq!(app["host_zone"].evaluate()?.as_str(), "cn");
+    assert_eq!(app["host_nodes"].evaluate()?.as_bool(), Some(true));
+    assert_eq!(app["inline_host"].evaluate()?.as_str(), "b");
+    assert_eq!(app["inline_port"].evaluate()?.as_int(), Some(443));
+    // yaml 的非字符串键转换为字符串，复合键报错
+    assert_eq!(
+        app["codes"].evaluate()?.to_json(),
+        serde_json::json!({ "200": "ok", "404": "missing", "true": "yes", "1.5": "half" })
+    );
+    let err = FamlExpr::from_str("[g]\nbad = yaml###\n? [1, 2]\n: x\n###\n")
+        .and_then(|root| root["g"]["bad"].evaluate())
+        .unwrap_err()
+        .to_string();
+    assert_eq!(err, "only scalar yaml keys are supported");
+    assert_eq!(
+        app["raw"].evaluate()?.to_json(),
+        serde_json::json!({ "server": { "host": "a", "ports": [80, 443] } })
+    );
+    Ok(())
+}
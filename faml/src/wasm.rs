@@ -0,0 +1,194 @@
+//! Functions of `.wasm` plugins, callable from faml as `native.<plugin>.<func>(...)`.
+//!
+//! A plugin exports its `memory`, `faml_alloc(len: i32) -> i32` which returns a buffer for the
+//! host to write into, and functions with the signature `(ptr: i32, len: i32) -> i64`. The
+//! arguments are passed as a JSON array, and the function returns the pointer and length of its
+//! JSON result packed as `ptr << 32 | len`. The result is `{"ok": value}` or `{"err": "msg"}`.
+//!
+//! Every call runs in a new instance limited by `WasmLimits`, so plugins can't keep state.
+
+use crate::FamlValue;
+use crate::native::FunctionRegistry;
+use anyhow::anyhow;
+use std::path::Path;
+use std::sync::Arc;
+use wasmtime::{
+    Config, Engine, ExternType, Instance, Linker, Module, ResourceLimiter, Store, ValType,
+};
+
+/// Sandbox limits of a single call.
+#[derive(Debug, Clone, Copy)]
+pub struct WasmLimits {
+    /// Instructions budget, see `wasmtime::Config::consume_fuel`.
+    pub fuel: u64,
+    /// Max size of the linear memory in bytes.
+    pub memory: usize,
+}
+
+impl Default for WasmLimits {
+    fn default() -> Self {
+        Self {
+            fuel: 10_000_000,
+            memory: 16 << 20,
+        }
+    }
+}
+
+/// Store data of a call, denied memory growth is remembered so it can be reported instead of
+/// the trap the plugin raises after it.
+struct CallLimits {
+    memory: usize,
+    out_of_memory: bool,
+}
+
+impl ResourceLimiter for CallLimits {
+    fn memory_growing(
+        &mut self,
+        _current: usize,
+        desired: usize,
+        _maximum: Option<usize>,
+    ) -> anyhow::Result<bool> {
+        if desired > self.memory {
+            self.out_of_memory = true;
+        }
+        Ok(desired <= self.memory)
+    }
+
+    fn table_growing(
+        &mut self,
+        _current: usize,
+        _desired: usize,
+        _maximum: Option<usize>,
+    ) -> anyhow::Result<bool> {
+        Ok(true)
+    }
+}
+
+pub struct WasmPlugin {
+    name: String,
+    engine: Engine,
+    module: Module,
+    limits: WasmLimits,
+}
+
+impl WasmPlugin {
+    /// Load a `.wasm` (or `.wat`) module, the plugin is named after the file stem.
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        Self::load_with_limits(path, WasmLimits::default())
+    }
+
+    pub fn load_with_limits(path: impl AsRef<Path>, limits: WasmLimits) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .ok_or_else(|| anyhow!("plugin[{}]: invalid file name", path.display()))?;
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config)?;
+        let module =
+            Module::from_file(&engine, path).map_err(|err| anyhow!("plugin[{name}]: {err}"))?;
+        Ok(Self {
+            name,
+            engine,
+            module,
+            limits,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Sorted names of the exported faml functions.
+    pub fn func_names(&self) -> Vec<String> {
+        let mut names: Vec<_> = self
+            .module
+            .exports()
+            .filter(|export| match export.ty() {
+                ExternType::Func(ty) => {
+                    let params: Vec<_> = ty.params().collect();
+                    let results: Vec<_> = ty.results().collect();
+                    matches!(params[..], [ValType::I32, ValType::I32])
+                        && matches!(results[..], [ValType::I64])
+                }
+                _ => false,
+            })
+            .map(|export| export.name().to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Register the functions as `<plugin>.<func>`.
+    pub fn register(self, registry: &FunctionRegistry) {
+        let plugin = Arc::new(self);
+        for func in plugin.func_names() {
+            let name = format!("{}.{func}", plugin.name);
+            let plugin = Arc::clone(&plugin);
+            registry.add_variadic_func(name, move |args: Vec<FamlValue>| plugin.call(&func, args));
+        }
+    }
+
+    pub fn call(&self, func: &str, args: Vec<FamlValue>) -> anyhow::Result<FamlValue> {
+        let limits = CallLimits {
+            memory: self.limits.memory,
+            out_of_memory: false,
+        };
+        let mut store = Store::new(&self.engine, limits);
+        store.limiter(|limits: &mut CallLimits| limits);
+        store.set_fuel(self.limits.fuel)?;
+        let instance = Linker::new(&self.engine).instantiate(&mut store, &self.module)?;
+        let input = serde_json::to_vec(&FamlValue::Array(args).to_json())?;
+        let (ptr, len) = Self::invoke(&mut store, &instance, func, &input).map_err(|err| {
+            if store.data().out_of_memory {
+                return anyhow!("out of memory, limit[{} bytes]", self.limits.memory);
+            }
+            match store.get_fuel() {
+                Ok(0) => anyhow!("out of fuel"),
+                _ => err,
+            }
+        })?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| anyhow!("memory is not exported"))?;
+        let output = memory
+            .data(&store)
+            .get(ptr..ptr + len)
+            .ok_or_else(|| anyhow!("result is out of bounds"))?;
+        let output: serde_json::Value = serde_json::from_slice(output)?;
+        match output {
+            serde_json::Value::Object(mut ret) => match (ret.remove("ok"), ret.remove("err")) {
+                (Some(val), None) => FamlValue::from_json(val),
+                (None, Some(serde_json::Value::String(err))) => Err(anyhow!("{err}")),
+                _ => Err(anyhow!("expected result {{\"ok\": ..}} or {{\"err\": ..}}")),
+            },
+            _ => Err(anyhow!("expected result {{\"ok\": ..}} or {{\"err\": ..}}")),
+        }
+    }
+
+    fn invoke(
+        store: &mut Store<CallLimits>,
+        instance: &Instance,
+        func: &str,
+        input: &[u8],
+    ) -> anyhow::Result<(usize, usize)> {
+        let alloc = instance.get_typed_func::<i32, i32>(&mut *store, "faml_alloc")?;
+        let func = instance.get_typed_func::<(i32, i32), i64>(&mut *store, func)?;
+        let memory = instance
+            .get_memory(&mut *store, "memory")
+            .ok_or_else(|| anyhow!("memory is not exported"))?;
+        let ptr = alloc.call(&mut *store, input.len() as i32)?;
+        memory.write(&mut *store, ptr as u32 as usize, input)?;
+        let ret = func.call(&mut *store, (ptr, input.len() as i32))? as u64;
+        Ok(((ret >> 32) as usize, (ret & 0xffff_ffff) as usize))
+    }
+}
+
+impl FunctionRegistry {
+    /// Load a plugin with the default limits and register its functions as `<plugin>.<func>`.
+    pub fn add_plugin(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        WasmPlugin::load(path)?.register(self);
+        Ok(())
+    }
+}
@@ -0,0 +1,44 @@
+;; Plugin used by the tests, results are JSON `{"ok": ..}` or `{"err": ..}`.
+(module
+  (memory (export "memory") 1)
+  (global $heap (mut i32) (i32.const 1024))
+  (data (i32.const 0) "{\"ok\":42}")
+  (data (i32.const 16) "{\"err\":\"boom\"}")
+  (data (i32.const 32) "{\"ok\":")
+
+  (func $alloc (export "faml_alloc") (param $len i32) (result i32)
+    (local $ptr i32)
+    (local.set $ptr (global.get $heap))
+    (global.set $heap (i32.add (global.get $heap) (local.get $len)))
+    (local.get $ptr))
+
+  (func $pack (param $ptr i32) (param $len i32) (result i64)
+    (i64.or
+      (i64.shl (i64.extend_i32_u (local.get $ptr)) (i64.const 32))
+      (i64.extend_i32_u (local.get $len))))
+
+  (func (export "answer") (param i32 i32) (result i64)
+    (call $pack (i32.const 0) (i32.const 9)))
+
+  (func (export "fail") (param i32 i32) (result i64)
+    (call $pack (i32.const 16) (i32.const 14)))
+
+  ;; returns the arguments array
+  (func (export "echo") (param $ptr i32) (param $len i32) (result i64)
+    (local $out i32)
+    (local.set $out (call $alloc (i32.add (local.get $len) (i32.const 7))))
+    (memory.copy (local.get $out) (i32.const 32) (i32.const 6))
+    (memory.copy (i32.add (local.get $out) (i32.const 6)) (local.get $ptr) (local.get $len))
+    (i32.store8 (i32.add (local.get $out) (i32.add (local.get $len) (i32.const 6))) (i32.const 125))
+    (call $pack (local.get $out) (i32.add (local.get $len) (i32.const 7))))
+
+  (func (export "spin") (param i32 i32) (result i64)
+    (loop $again (br $again))
+    (i64.const 0))
+
+  ;; grows the memory to 64MB
+  (func (export "grow") (param i32 i32) (result i64)
+    (if (i32.eq (memory.grow (i32.const 1024)) (i32.const -1))
+      (then unreachable))
+    (call $pack (i32.const 0) (i32.const 9)))
+)
@@ -0,0 +1,150 @@
+//! `#[derive(Faml)]` binds the fields of a struct to paths of a `faml::FamlExpr`.
+//!
+//! ```ignore
+//! #[derive(faml::Faml)]
+//! #[faml(path = "server")]
+//! struct ServerCfg {
+//!     port: u16,
+//!     #[faml(path = "timeout", unit = "milliseconds")]
+//!     timeout_ms: u64,
+//!     #[faml(default)]
+//!     workers: usize,
+//! }
+//! ```
+//!
+//! The struct implements `faml::bind::FamlBind`, and gets a `set_<field>` method per field which
+//! writes the value back to the expression and reloads the struct.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{Data, DeriveInput, ExprPath, Fields, LitStr, Type, parse_macro_input};
+
+#[proc_macro_derive(Faml, attributes(faml))]
+pub fn derive_faml(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+#[derive(Default)]
+struct FamlAttr {
+    path: Option<String>,
+    unit: Option<String>,
+    default: Option<Option<ExprPath>>,
+}
+
+impl FamlAttr {
+    fn parse(attrs: &[syn::Attribute]) -> syn::Result<Self> {
+        let mut ret = FamlAttr::default();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("faml")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("path") {
+                    ret.path = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("unit") {
+                    ret.unit = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("default") {
+                    ret.default = Some(match meta.input.peek(syn::Token![=]) {
+                        true => Some(meta.value()?.parse::<LitStr>()?.parse()?),
+                        false => None,
+                    });
+                } else {
+                    return Err(meta.error("expected `path`, `unit` or `default`"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(ret)
+    }
+}
+
+fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(ty) => ty
+            .path
+            .segments
+            .last()
+            .is_some_and(|seg| seg.ident == "Option"),
+        _ => false,
+    }
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "Faml can only be derived for structs with named fields",
+                ));
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "Faml can only be derived for structs",
+            ));
+        }
+    };
+    let prefix = FamlAttr::parse(&input.attrs)?.path.unwrap_or_default();
+    let mut loads = vec![];
+    let mut setters = vec![];
+    for field in fields {
+        let ident = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+        let attr = FamlAttr::parse(&field.attrs)?;
+        let key = attr
+            .path
+            .unwrap_or_else(|| ident.to_string().trim_start_matches("r#").to_string());
+        let path = match prefix.is_empty() {
+            true => key,
+            false => format!("{prefix}.{key}"),
+        };
+        let unit = match &attr.unit {
+            Some(unit) => quote!(Some(#unit)),
+            None => quote!(None),
+        };
+        let fallback = match attr.default {
+            Some(Some(func)) => quote!(#func()),
+            Some(None) => quote!(::core::default::Default::default()),
+            None if is_option(ty) => quote!(None),
+            None => quote!(return Err(::faml::bind::missing_field(#path))),
+        };
+        loads.push(quote! {
+            #ident: match ::faml::bind::read::<#ty>(expr, #path, #unit)? {
+                Some(value) => value,
+                None => #fallback,
+            }
+        });
+        let setter = format_ident!("set_{}", ident.to_string().trim_start_matches("r#"));
+        let doc = format!("Write `{path}` and reload, formulas depending on it are recalculated.");
+        setters.push(quote! {
+            #[doc = #doc]
+            pub fn #setter(
+                &mut self,
+                expr: &mut ::faml::FamlExpr,
+                value: #ty,
+            ) -> ::faml::bind::Result<()> {
+                ::faml::bind::write(expr, #path, &value, #unit)?;
+                *self = <Self as ::faml::bind::FamlBind>::from_expr(expr)?;
+                Ok(())
+            }
+        });
+    }
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::faml::bind::FamlBind for #name #ty_generics #where_clause {
+            fn from_expr(expr: &::faml::FamlExpr) -> ::faml::bind::Result<Self> {
+                Ok(Self { #(#loads,)* })
+            }
+        }
+
+        impl #impl_generics #name #ty_generics #where_clause {
+            #(#setters)*
+        }
+    })
+}
@@ -0,0 +1,99 @@
+//! Compile-time faml macros, paths are relative to the `CARGO_MANIFEST_DIR` of the calling crate.
+//!
+//! ```
+//! faml_macros::include_faml_types!("tests/defaults.faml");
+//!
+//! fn main() -> anyhow::Result<()> {
+//!     let defaults: &'static faml::FamlExpr = faml_macros::include_faml!("tests/defaults.faml");
+//!     let config: Config = defaults.deserialize()?;
+//!     assert_eq!(config.server.url, "http://localhost:8080");
+//!     Ok(())
+//! }
+//! ```
+
+use faml::FamlExpr;
+use faml::idl_gen::IDLGenerator;
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use std::path::PathBuf;
+use syn::{LitStr, parse_macro_input};
+
+/// Parse a faml file at compile time and expand to a lazily initialized `&'static FamlExpr`.
+///
+/// Syntax errors, unresolved names and reference cycles are reported as compile errors.
+#[proc_macro]
+pub fn include_faml(input: TokenStream) -> TokenStream {
+    let lit = parse_macro_input!(input as LitStr);
+    match expand_expr(&lit) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// Emit the structs `IDLGenerator::gen_rust` generates for a faml file, the root is `Config`.
+#[proc_macro]
+pub fn include_faml_types(input: TokenStream) -> TokenStream {
+    let lit = parse_macro_input!(input as LitStr);
+    match expand_types(&lit) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+struct FamlFile {
+    path: String,
+    content: String,
+    expr: FamlExpr,
+}
+
+impl FamlFile {
+    fn load(lit: &LitStr) -> syn::Result<Self> {
+        let err = |msg: String| syn::Error::new(lit.span(), msg);
+        let dir = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default();
+        let path = PathBuf::from(dir).join(lit.value());
+        let path = path.to_string_lossy().to_string();
+        let content = std::fs::read_to_string(&path).map_err(|e| err(format!("{path}: {e}")))?;
+        let expr = FamlExpr::from_str(&content).map_err(|e| err(format!("{path}:\n{e}")))?;
+        expr.check_refs()
+            .map_err(|e| err(format!("{path}:\n{e}")))?;
+        Ok(FamlFile {
+            path,
+            content,
+            expr,
+        })
+    }
+
+    /// Rebuild the calling crate when the file changes.
+    fn track(&self) -> TokenStream2 {
+        let path = &self.path;
+        quote!(
+            const _: &[u8] = include_bytes!(#path);
+        )
+    }
+}
+
+fn expand_expr(lit: &LitStr) -> syn::Result<TokenStream2> {
+    let file = FamlFile::load(lit)?;
+    let track = file.track();
+    let content = &file.content;
+    Ok(quote! {{
+        #track
+        static EXPR: ::std::sync::LazyLock<::faml::FamlExpr> = ::std::sync::LazyLock::new(|| {
+            ::faml::FamlExpr::from_str(#content).expect("checked by include_faml!")
+        });
+        &*EXPR
+    }})
+}
+
+fn expand_types(lit: &LitStr) -> syn::Result<TokenStream2> {
+    let file = FamlFile::load(lit)?;
+    let track = file.track();
+    let code = IDLGenerator::gen_rust(&file.expr)
+        .map_err(|e| syn::Error::new(lit.span(), format!("{}:\n{e}", file.path)))?;
+    let code: TokenStream2 = code.parse()?;
+    Ok(quote! {
+        #track
+        #code
+    })
+}
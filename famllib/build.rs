@@ -4,6 +4,7 @@ fn main() {
     let config = cbindgen::Config {
         language: cbindgen::Language::C,
         pragma_once: true,
+        defines: [("feature = wasm".to_string(), "FAML_WASM".to_string())].into(),
         ..Default::default()
     };
     _ = cbindgen::generate_with_config(&crate_dir, config)
@@ -10,6 +10,14 @@
  */
 int faml_expr_from_str(const char *psrc, void **ppexpr, const char **pperr);
 
+#if defined(FAML_WASM)
+/**
+ * Load a `.wasm` plugin and register its functions for every document as
+ * `native.<plugin>.<func>`, only exported with the `wasm` feature.
+ */
+int faml_add_plugin(const char *ppath, const char **pperr);
+#endif
+
 void faml_expr_set_none(void *pexpr, const char *ppath);
 
 void faml_expr_set_bool(void *pexpr, const char *ppath, int value);
@@ -36,6 +44,38 @@ int faml_value_is_float(void *pval, const char *ppath);
 
 double faml_value_as_float(void *pval, const char *ppath);
 
+/**
+ * # Safety
+ *
+ * `pval` must be a value pointer returned by faml, and `ppath` a valid C string.
+ */
+int faml_value_is_duration(void *pval, const char *ppath);
+
+/**
+ * Get duration value in seconds, NaN if the value is not a duration
+ *
+ * # Safety
+ *
+ * `pval` must be a value pointer returned by faml, and `ppath` a valid C string.
+ */
+double faml_value_as_duration(void *pval, const char *ppath);
+
+/**
+ * # Safety
+ *
+ * `pval` must be a value pointer returned by faml, and `ppath` a valid C string.
+ */
+int faml_value_is_distance(void *pval, const char *ppath);
+
+/**
+ * Get distance value in meters, NaN if the value is not a distance
+ *
+ * # Safety
+ *
+ * `pval` must be a value pointer returned by faml, and `ppath` a valid C string.
+ */
+double faml_value_as_distance(void *pval, const char *ppath);
+
 int faml_value_is_str(void *pval, const char *ppath);
 
 const char *faml_value_as_str(void *pval, const char *ppath);
@@ -1,8 +1,10 @@
 #pragma once
 
+#include <chrono>
 #include <cstdint>
 #include <format>
 #include <memory>
+#include <optional>
 #include <string>
 #include <string_view>
 #include <variant>
@@ -30,6 +32,12 @@ public:
   bool is_str() { return !!faml_value_is_str(pval_.get(), path_.c_str()); }
   bool is_array() { return !!faml_value_is_array(pval_.get(), path_.c_str()); }
   bool is_map() { return !!faml_value_is_map(pval_.get(), path_.c_str()); }
+  bool is_duration() {
+    return !!faml_value_is_duration(pval_.get(), path_.c_str());
+  }
+  bool is_distance() {
+    return !!faml_value_is_distance(pval_.get(), path_.c_str());
+  }
 
   void set_none() { faml_value_set_none(pval_.get(), path_.c_str()); }
   void set_bool(bool val) {
@@ -48,6 +56,14 @@ public:
   bool as_bool() { return !!faml_value_as_bool(pval_.get(), path_.c_str()); }
   int64_t as_int() { return faml_value_as_int(pval_.get(), path_.c_str()); }
   double as_float() { return faml_value_as_float(pval_.get(), path_.c_str()); }
+  std::chrono::nanoseconds as_duration() {
+    return std::chrono::duration_cast<std::chrono::nanoseconds>(
+        std::chrono::duration<double>(
+            faml_value_as_duration(pval_.get(), path_.c_str())));
+  }
+  double as_distance() {
+    return faml_value_as_distance(pval_.get(), path_.c_str());
+  }
   std::string as_str() {
     auto str = faml_value_as_str(pval_.get(), path_.c_str());
     std::string ret = str;
@@ -143,4 +159,19 @@ private:
   std::shared_ptr<void> pexpr_;
   std::string path_;
 };
+
+#if defined(FAML_WASM)
+class Native {
+public:
+  inline static std::optional<std::string> add_plugin(const std::string &path) {
+    const char *perr = nullptr;
+    if (!!faml_add_plugin(path.c_str(), &perr)) {
+      return std::nullopt;
+    }
+    std::string err = perr;
+    faml_release_str(perr);
+    return err;
+  }
+};
+#endif
 } // namespace faml
@@ -34,6 +34,24 @@ pub extern "C" fn faml_expr_from_str(
     }
 }
 
+/// Load a `.wasm` plugin and register its functions for every document as
+/// `native.<plugin>.<func>`, only exported with the `wasm` feature.
+#[cfg(feature = "wasm")]
+#[unsafe(no_mangle)]
+pub extern "C" fn faml_add_plugin(ppath: *const c_char, pperr: *mut *const c_char) -> c_int {
+    let path = unsafe { CStr::from_ptr(ppath).to_str().unwrap_or("") };
+    match faml::Native::add_plugin(path) {
+        Ok(()) => {
+            unsafe { *pperr = std::ptr::null_mut() };
+            true.as_cint()
+        }
+        Err(err) => {
+            unsafe { *pperr = CString::new(format!("{err}")).unwrap().into_raw() };
+            false.as_cint()
+        }
+    }
+}
+
 #[unsafe(no_mangle)]
 pub extern "C" fn faml_expr_set_none(pexpr: *mut c_void, ppath: *const c_char) {
     let mut expr = unsafe { Box::from_raw(pexpr as *mut FamlExpr) };
@@ -190,6 +208,70 @@ pub extern "C" fn faml_value_as_float(pval: *mut c_void, ppath: *const c_char) -
     ret
 }
 
+/// # Safety
+///
+/// `pval` must be a value pointer returned by faml, and `ppath` a valid C string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn faml_value_is_duration(pval: *mut c_void, ppath: *const c_char) -> c_int {
+    let val = unsafe { Box::from_raw(pval as *mut FamlValue) };
+    let path = unsafe { CStr::from_ptr(ppath).to_str().unwrap_or("") };
+    let ret = matches!(val.get_with_path(path), Some(FamlValue::Duration(_)));
+    Box::leak(val);
+    ret.as_cint()
+}
+
+/// Get duration value in seconds, NaN if the value is not a duration
+///
+/// # Safety
+///
+/// `pval` must be a value pointer returned by faml, and `ppath` a valid C string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn faml_value_as_duration(
+    pval: *mut c_void,
+    ppath: *const c_char,
+) -> c_double {
+    let val = unsafe { Box::from_raw(pval as *mut FamlValue) };
+    let path = unsafe { CStr::from_ptr(ppath).to_str().unwrap_or("") };
+    let ret = match val.get_with_path(path) {
+        Some(FamlValue::Duration(dur)) => dur.as_secs_f64(),
+        _ => f64::NAN,
+    };
+    Box::leak(val);
+    ret
+}
+
+/// # Safety
+///
+/// `pval` must be a value pointer returned by faml, and `ppath` a valid C string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn faml_value_is_distance(pval: *mut c_void, ppath: *const c_char) -> c_int {
+    let val = unsafe { Box::from_raw(pval as *mut FamlValue) };
+    let path = unsafe { CStr::from_ptr(ppath).to_str().unwrap_or("") };
+    let ret = matches!(val.get_with_path(path), Some(FamlValue::Distance(_)));
+    Box::leak(val);
+    ret.as_cint()
+}
+
+/// Get distance value in meters, NaN if the value is not a distance
+///
+/// # Safety
+///
+/// `pval` must be a value pointer returned by faml, and `ppath` a valid C string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn faml_value_as_distance(
+    pval: *mut c_void,
+    ppath: *const c_char,
+) -> c_double {
+    let val = unsafe { Box::from_raw(pval as *mut FamlValue) };
+    let path = unsafe { CStr::from_ptr(ppath).to_str().unwrap_or("") };
+    let ret = match val.get_with_path(path) {
+        Some(FamlValue::Distance(dist)) => dist.to_meters(),
+        _ => f64::NAN,
+    };
+    Box::leak(val);
+    ret
+}
+
 #[unsafe(no_mangle)]
 pub extern "C" fn faml_value_is_str(pval: *mut c_void, ppath: *const c_char) -> c_int {
     let val = unsafe { Box::from_raw(pval as *mut FamlValue) };
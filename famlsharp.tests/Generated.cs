@@ -0,0 +1,22 @@
+// Generated by faml IDLGenerator, do not edit.
+using faml;
+
+public class Server
+{
+    public Server(FamlValue pval) => this.pval = pval;
+
+    public string host => pval["host"].as_str();
+    public long port => pval["port"].as_int();
+
+    private FamlValue pval;
+}
+
+public class Config
+{
+    public Config(FamlValue pval) => this.pval = pval;
+    public static Config from_str(string src) => new Config(FamlExpr.from_str(src).evaluate());
+
+    public Server server => new Server(pval["server"]);
+
+    private FamlValue pval;
+}
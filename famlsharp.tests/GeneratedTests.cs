@@ -0,0 +1,20 @@
+using Xunit;
+
+namespace faml.tests;
+
+public class GeneratedTests
+{
+    // properties of a generated class share the evaluated value, collecting them must not release it
+    [Fact]
+    public void ReadTwoProperties()
+    {
+        var server = Config.from_str("[server]\nhost = \"localhost\"\nport = 8080\n").server;
+        Assert.Equal("localhost", server.host);
+        Assert.Equal(8080, server.port);
+
+        GC.Collect();
+        GC.WaitForPendingFinalizers();
+        Assert.Equal("localhost", server.host);
+        Assert.Equal(8080, server.port);
+    }
+}
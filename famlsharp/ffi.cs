@@ -8,6 +8,11 @@ public static class FFI
     [DllImport("faml.dll", CallingConvention = CallingConvention.Cdecl)]
     public static extern int faml_expr_from_str([MarshalAs(UnmanagedType.LPStr)] string psrc, out IntPtr ppexpr, out IntPtr pperr);
 
+#if FAML_WASM
+    [DllImport("faml.dll", CallingConvention = CallingConvention.Cdecl)]
+    public static extern int faml_add_plugin([MarshalAs(UnmanagedType.LPStr)] string ppath, out IntPtr pperr);
+#endif
+
     [DllImport("faml.dll", CallingConvention = CallingConvention.Cdecl)]
     public static extern void faml_expr_set_none(IntPtr pexpr, [MarshalAs(UnmanagedType.LPStr)] string ppath);
 
@@ -47,6 +52,18 @@ public static class FFI
     [DllImport("faml.dll", CallingConvention = CallingConvention.Cdecl)]
     public static extern double faml_value_as_float(IntPtr pval, [MarshalAs(UnmanagedType.LPStr)] string ppath);
 
+    [DllImport("faml.dll", CallingConvention = CallingConvention.Cdecl)]
+    public static extern int faml_value_is_duration(IntPtr pval, [MarshalAs(UnmanagedType.LPStr)] string ppath);
+
+    [DllImport("faml.dll", CallingConvention = CallingConvention.Cdecl)]
+    public static extern double faml_value_as_duration(IntPtr pval, [MarshalAs(UnmanagedType.LPStr)] string ppath);
+
+    [DllImport("faml.dll", CallingConvention = CallingConvention.Cdecl)]
+    public static extern int faml_value_is_distance(IntPtr pval, [MarshalAs(UnmanagedType.LPStr)] string ppath);
+
+    [DllImport("faml.dll", CallingConvention = CallingConvention.Cdecl)]
+    public static extern double faml_value_as_distance(IntPtr pval, [MarshalAs(UnmanagedType.LPStr)] string ppath);
+
     [DllImport("faml.dll", CallingConvention = CallingConvention.Cdecl)]
     public static extern int faml_value_is_str(IntPtr pval, [MarshalAs(UnmanagedType.LPStr)] string ppath);
 
@@ -23,13 +23,29 @@ public static class IntPtrExtensions
     }
 }
 
+#if FAML_WASM
+public static class Native
+{
+    public static void add_plugin(string path)
+    {
+        IntPtr perr = 0;
+        if (FFI.faml_add_plugin(path, out perr) == 0)
+        {
+            throw new Exception(perr.to_str_and_release());
+        }
+    }
+}
+#endif
+
 public class FamlExpr : IDisposable
 {
     public FamlExpr(IntPtr pexpr, string path = "") => (this.pexpr, this.path) = (pexpr, path);
+    // children share the handle of the expr they are read from, only the owner releases it
+    private FamlExpr(FamlExpr parent, string path) => (this.pexpr, this.path, this.owner) = (parent.pexpr, path, parent.owner ?? parent);
     ~FamlExpr() => do_release();
 
-    public FamlExpr this[int index] => new FamlExpr(pexpr, path.Length > 0 ? $"{path}.[{index}]" : $"[{index}]");
-    public FamlExpr this[string index] => new FamlExpr(pexpr, path.Length > 0 ? $"{path}.{index}" : index);
+    public FamlExpr this[int index] => new FamlExpr(this, path.Length > 0 ? $"{path}.[{index}]" : $"[{index}]");
+    public FamlExpr this[string index] => new FamlExpr(this, path.Length > 0 ? $"{path}.{index}" : index);
 
     public static FamlExpr from_str(string src)
     {
@@ -75,21 +91,24 @@ public class FamlExpr : IDisposable
     private void do_release()
     {
         if (pexpr == 0) return;
-        FFI.faml_release_expr(pexpr);
+        if (owner == null) FFI.faml_release_expr(pexpr);
         pexpr = 0;
     }
 
     private IntPtr pexpr = 0;
     private string path = "";
+    private FamlExpr? owner = null;
 }
 
 public class FamlValue : IDisposable
 {
     public FamlValue(IntPtr pval, string path = "") => (this.pval, this.path) = (pval, path);
+    // children share the handle of the value they are read from, only the owner releases it
+    private FamlValue(FamlValue parent, string path) => (this.pval, this.path, this.owner) = (parent.pval, path, parent.owner ?? parent);
     ~FamlValue() => do_release();
 
-    public FamlValue this[int index] => new FamlValue(pval, path.Length > 0 ? $"{path}.[{index}]" : $"[{index}]");
-    public FamlValue this[string index] => new FamlValue(pval, path.Length > 0 ? $"{path}.{index}" : index);
+    public FamlValue this[int index] => new FamlValue(this, path.Length > 0 ? $"{path}.[{index}]" : $"[{index}]");
+    public FamlValue this[string index] => new FamlValue(this, path.Length > 0 ? $"{path}.{index}" : index);
 
     public bool is_none() { return FFI.faml_value_is_none(pval, path) > 0; }
     public bool is_bool() { return FFI.faml_value_is_bool(pval, path) > 0; }
@@ -98,6 +117,8 @@ public class FamlValue : IDisposable
     public bool is_str() { return FFI.faml_value_is_str(pval, path) > 0; }
     public bool is_array() { return FFI.faml_value_is_array(pval, path) > 0; }
     public bool is_map() { return FFI.faml_value_is_map(pval, path) > 0; }
+    public bool is_duration() { return FFI.faml_value_is_duration(pval, path) > 0; }
+    public bool is_distance() { return FFI.faml_value_is_distance(pval, path) > 0; }
 
     public void set_none() { FFI.faml_value_set_none(pval, path); }
     public void set_bool(bool val) { FFI.faml_value_set_bool(pval, path, val ? 1 : 0); }
@@ -108,6 +129,8 @@ public class FamlValue : IDisposable
     public bool as_bool() { return FFI.faml_value_as_bool(pval, path) > 0; }
     public long as_int() { return FFI.faml_value_as_int(pval, path); }
     public double as_float() { return FFI.faml_value_as_float(pval, path); }
+    public TimeSpan as_duration() { return TimeSpan.FromSeconds(FFI.faml_value_as_duration(pval, path)); }
+    public double as_distance() { return FFI.faml_value_as_distance(pval, path); }
     public string as_str() { return FFI.faml_value_as_str(pval, path).to_str_and_release(); }
     public int get_array_length() { return FFI.faml_value_get_array_length(pval, path); }
     public int get_map_length() { return FFI.faml_value_get_map_length(pval, path); }
@@ -126,10 +149,11 @@ public class FamlValue : IDisposable
     private void do_release()
     {
         if (pval == 0) return;
-        FFI.faml_release_value(pval);
+        if (owner == null) FFI.faml_release_value(pval);
         pval = 0;
     }
 
     private IntPtr pval = 0;
     private string path = "";
+    private FamlValue? owner = null;
 }